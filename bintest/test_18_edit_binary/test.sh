@@ -0,0 +1,14 @@
+#!/bin/sh
+
+set -e
+
+printf 'caf\351\n\000\001\002\nno newline' > /etc/thing
+printf 'caf\351\n\000\001\002\nno newline\377' > /tmp/expected
+
+cat <<'EOT' | su -s /bin/bash ed
+export EDITOR="/usr/bin/tee -a"
+printf '\377' | pleaseedit /etc/thing
+EOT
+
+cmp /etc/thing /tmp/expected
+rm /tmp/expected
@@ -0,0 +1,17 @@
+#!/bin/sh
+
+set -e
+
+echo thingy > /etc/thing_ok
+echo thingy > /etc/thing_bad
+
+cat <<'EOT' | su -s /bin/bash ed
+export EDITOR="/usr/bin/tee -a"
+echo "foo" | pleaseedit /etc/thing_ok /etc/shadow && exit 1
+grep foo /etc/thing_ok && exit 1
+echo "foo" | pleaseedit /etc/thing_ok /etc/thing_bad && exit 1
+grep foo /etc/thing_ok || exit 1
+grep foo /etc/thing_bad && exit 1
+ls -al /tmp/*thing* && exit 1
+/bin/true
+EOT
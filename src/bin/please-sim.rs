@@ -0,0 +1,203 @@
+//    please
+//    Copyright (C) 2020-2021 ed neville
+//
+//    This program is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    This program is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! please-sim.rs replay logged requests against a candidate policy
+
+use pleaser::*;
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+
+use chrono::Datelike;
+use getopts::Options;
+
+/// read a policy, exiting if it does not parse
+fn load_policy(path: &str, ro: &RunOptions) -> Vec<EnvOptions> {
+    let mut vec_eo: Vec<EnvOptions> = vec![];
+    let mut bytes = 0;
+    let mut ini_list: HashMap<String, bool> = HashMap::new();
+
+    if let Err(error) = read_ini_config_file(path, &mut vec_eo, ro, true, &mut bytes, &mut ini_list)
+    {
+        println!("{}", error);
+        std::process::exit(1);
+    }
+
+    vec_eo
+}
+
+/// the groups of a logged user, from the record or the local group database
+fn logged_groups(
+    request: &LoggedRequest,
+    cache: &mut HashMap<String, HashMap<String, u32>>,
+) -> HashMap<String, u32> {
+    if let Some(groups) = &request.groups {
+        return groups.iter().map(|g| (g.clone(), 0)).collect();
+    }
+
+    cache
+        .entry(request.user.clone())
+        .or_insert_with(|| match identity().user_by_name(&request.user) {
+            Some(user) => group_hash(identity().user_groups(&user).unwrap_or_default()),
+            None => HashMap::new(),
+        })
+        .clone()
+}
+
+/// collect the replayable requests from each log, without duplicates
+fn read_requests(logs: &[String], year: i32) -> Vec<LoggedRequest> {
+    let mut requests: Vec<LoggedRequest> = vec![];
+    let mut seen: HashMap<String, bool> = HashMap::new();
+
+    let mut readers: Vec<(String, Box<dyn BufRead>)> = vec![];
+    if logs.is_empty() {
+        readers.push((
+            "stdin".to_string(),
+            Box::new(BufReader::new(std::io::stdin())),
+        ));
+    }
+    for log in logs {
+        match std::fs::File::open(log) {
+            Ok(file) => readers.push((log.clone(), Box::new(BufReader::new(file)))),
+            Err(why) => {
+                println!("Could not open {}: {}", log, why);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    for (name, reader) in readers {
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(why) => {
+                    println!("Could not read {}: {}", name, why);
+                    std::process::exit(1);
+                }
+            };
+
+            let request = match parse_logged_request(&line, year) {
+                Some(request) => request,
+                None => continue,
+            };
+            if !REPLAY_ACTIONS.contains(&request.action.as_str()) {
+                continue;
+            }
+
+            if seen.insert(format!("{:?}", request), true).is_none() {
+                requests.push(request);
+            }
+        }
+    }
+
+    requests
+}
+
+/// main entry point
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let service = String::from("please-sim");
+    let mut ro = RunOptions::new();
+    ro.syslog = false;
+
+    let mut opts = Options::new();
+    opts.optopt(
+        "",
+        "current",
+        "policy the requests were decided by, default /etc/please.ini",
+        "FILE",
+    );
+    opts.optflag("h", "help", "print usage help");
+    opts.optopt(
+        "",
+        "hostname",
+        "replay as this host when the log does not say",
+        "NAME",
+    );
+    opts.optflag("v", "version", "print version and exit");
+
+    let header = format!("{} [arguments] <candidate.ini> [log...]", &service);
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            print_usage(&opts, &header);
+            std::process::exit(1);
+        }
+    };
+
+    if matches.opt_present("help") {
+        print_usage(&opts, &header);
+        print_version(&service);
+        std::process::exit(0);
+    }
+    if matches.opt_present("version") {
+        print_version(&service);
+        std::process::exit(0);
+    }
+    if matches.free.is_empty() {
+        println!("No candidate policy given");
+        print_usage(&opts, &header);
+        std::process::exit(1);
+    }
+    if let Some(hostname) = matches.opt_str("hostname") {
+        ro.hostname = hostname;
+    }
+
+    let current_path = matches
+        .opt_str("current")
+        .unwrap_or_else(|| "/etc/please.ini".to_string());
+    let current = load_policy(&current_path, &ro);
+    let candidate = load_policy(&matches.free[0], &ro);
+
+    let mut cache = HashMap::new();
+    let mut replay: Vec<RunOptions> = vec![];
+    let mut unreplayable: HashMap<String, usize> = HashMap::new();
+    for request in read_requests(&matches.free[1..], ro.date.year()) {
+        for key in unreplayable_keys(&[&current, &candidate], &request) {
+            *unreplayable.entry(key).or_insert(0) += 1;
+        }
+
+        let groups = logged_groups(&request, &mut cache);
+        for mut request_ro in logged_request_ro(&request, &groups) {
+            if request.hostname.is_none() {
+                request_ro.hostname.clone_from(&ro.hostname);
+            }
+            replay.push(request_ro);
+        }
+    }
+
+    let flips = simulate(&current, &candidate, &replay);
+    for line in produce_sim_report(&flips) {
+        println!("{}", line);
+    }
+    println!(
+        "{} requests replayed, {} decisions changed",
+        replay.len(),
+        flips.len()
+    );
+
+    let mut keys: Vec<(&String, &usize)> = unreplayable.iter().collect();
+    keys.sort();
+    for (key, count) in keys {
+        println!(
+            "{}= rules could not be replayed for {} requests as the log does not record their {}",
+            key, count, key
+        );
+    }
+
+    std::process::exit(if flips.is_empty() { 0 } else { 1 });
+}
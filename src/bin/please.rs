@@ -25,7 +25,14 @@ use std::collections::HashMap;
 
 use getopts::Options;
 
-use uzers::*;
+/// print why a library call failed and exit with its status
+fn exit_with(error: PleaseError) -> ! {
+    let message = error.to_string();
+    if !message.is_empty() {
+        println!("{}", message);
+    }
+    std::process::exit(error.exit_code());
+}
 
 /// walk through user ACL
 fn do_list(ro: &mut RunOptions, vec_eo: &[EnvOptions], service: &str) {
@@ -61,22 +68,312 @@ fn do_list(ro: &mut RunOptions, vec_eo: &[EnvOptions], service: &str) {
         std::process::exit(1);
     }
 
+    if locked_out(ro, &can_do).unwrap_or_else(|e| exit_with(e)) {
+        log_action(service, "lockout", ro, &ro.original_command.join(" "));
+        std::process::exit(1);
+    }
+
     // check if a password is required
-    if !challenge_password(ro, &can_do, service) {
+    if !challenge_password(ro, &can_do, service).unwrap_or_else(|e| exit_with(e)) {
         log_action(service, "deny", ro, &ro.original_command.join(" "));
         std::process::exit(1);
     }
 
     log_action(service, "permit", ro, &ro.command);
-    println!("{} may run the following:", name);
+
+    // uses counters are only readable by root
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    if ro.list_format == ListFormat::Json {
+        let mut records = vec![];
+        for acl_type in [Acltype::Run, Acltype::Edit, Acltype::List] {
+            ro.acl_type = acl_type;
+            records.append(&mut produce_list_json(vec_eo, ro));
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::Value::Array(records)).unwrap()
+        );
+    } else {
+        println!("{} may run the following:", name);
+        ro.acl_type = Acltype::Run;
+        list(vec_eo, ro);
+        println!("{} may edit the following:", name);
+        ro.acl_type = Acltype::Edit;
+        list(vec_eo, ro);
+        println!("{} may list the following:", name);
+        ro.acl_type = Acltype::List;
+        list(vec_eo, ro);
+    }
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+}
+
+/// report whether the command would be permitted, without running it
+fn do_check(ro: &mut RunOptions, vec_eo: &[EnvOptions], service: &str) {
+    if ro.target.is_empty() {
+        ro.target = "root".to_string();
+    }
     ro.acl_type = Acltype::Run;
-    list(vec_eo, ro);
-    println!("{} may edit the following:", name);
-    ro.acl_type = Acltype::Edit;
-    list(vec_eo, ro);
-    println!("{} may list the following:", name);
-    ro.acl_type = Acltype::List;
-    list(vec_eo, ro);
+
+    let entry = can(vec_eo, ro);
+    if let Some(syslog) = entry.syslog {
+        ro.syslog = syslog;
+    }
+    log_action(
+        service,
+        if entry.permit() {
+            "check_permit"
+        } else {
+            "check_deny"
+        },
+        ro,
+        &ro.original_command.join(" "),
+    );
+
+    if !entry.permit() && ro.list_format == ListFormat::Text {
+        is_command_cd(ro, service);
+        exit_if_command_not_found(ro, service);
+    }
+
+    // tokens and uses counters are only readable by root
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    let password_needed = entry.require_pass() && !valid_token(&ro.name, &entry);
+    if ro.list_format == ListFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&produce_check_json(&entry, ro, password_needed)).unwrap()
+        );
+    } else {
+        for line in produce_check(&entry, ro, password_needed) {
+            println!("{}", line);
+        }
+        if !entry.permit() {
+            if let Some(why) = tty_explain(vec_eo, ro) {
+                println!("{}", why);
+            }
+        }
+    }
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+
+    std::process::exit(if entry.permit() { 0 } else { 1 });
+}
+
+/// approve or reject a spooled request on behalf of a second person
+fn do_approval_decision(ro: &mut RunOptions, service: &str) {
+    let (id, status) = match (&ro.approve, &ro.reject) {
+        (Some(id), None) => (id.clone(), ApprovalStatus::Approved),
+        (None, Some(id)) => (id.clone(), ApprovalStatus::Rejected),
+        _ => {
+            println!("Cannot use --approve and --reject together");
+            std::process::exit(1);
+        }
+    };
+
+    if !challenge_password(ro, &EnvOptions::new(), service).unwrap_or_else(|e| exit_with(e)) {
+        log_action(service, "deny", ro, &ro.original_command.join(" "));
+        std::process::exit(1);
+    }
+
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    let req = read_approval(&id);
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+
+    let mut req = match req {
+        Some(r) => r,
+        None => {
+            println!("No such request {}", &id);
+            std::process::exit(1);
+        }
+    };
+
+    let mut log_ro = ro.clone();
+    log_ro.target.clone_from(&req.target);
+    log_ro.reason = Some(format!(
+        "request {} from {}{}",
+        &req.id,
+        &req.user,
+        match &ro.reason {
+            Some(r) => format!(": {}", r),
+            None => "".to_string(),
+        }
+    ));
+
+    if !approver_ok(&req, ro) {
+        log_action(service, "deny", &log_ro, &req.command);
+        println!("You may not approve or reject request {}", &req.id);
+        std::process::exit(1);
+    }
+
+    if req.status != ApprovalStatus::Pending {
+        println!("Request {} has already been {}", &req.id, req.status);
+        std::process::exit(1);
+    }
+
+    if req.expired(epoch_secs()) {
+        println!("Request {} has expired", &req.id);
+        std::process::exit(1);
+    }
+
+    req.status = status;
+    req.approver = Some(ro.name.clone());
+
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    if !write_approval(&req) {
+        std::process::exit(1);
+    }
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+
+    log_action(
+        service,
+        if req.status == ApprovalStatus::Approved {
+            "approve"
+        } else {
+            "reject"
+        },
+        &log_ro,
+        &req.command,
+    );
+    println!(
+        "Request {} from {} to run \"{}\" as {} has been {}",
+        &req.id, &req.user, &req.command, &req.target, req.status
+    );
+}
+
+/// hold the command until a second person approves it, exit 1 if rejected
+fn do_approval_wait(ro: &RunOptions, entry: &EnvOptions, service: &str) {
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    let mut req = match find_approval(ro) {
+        Some(r) => r,
+        None => {
+            let r = ApprovalRequest::new(ro, entry);
+            if !write_approval(&r) {
+                std::process::exit(1);
+            }
+            log_action(
+                service,
+                "approval_pending",
+                ro,
+                &ro.original_command.join(" "),
+            );
+            r
+        }
+    };
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+
+    if req.status == ApprovalStatus::Pending {
+        println!("Request {} is awaiting approval", &req.id);
+        if !ro.prompt {
+            std::process::exit(1);
+        }
+        println!("Waiting for approval, interrupt to stop waiting");
+
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(2));
+
+            if !esc_privs() {
+                std::process::exit(1);
+            }
+            let update = read_approval(&req.id);
+            if !drop_privs(ro) {
+                std::process::exit(1);
+            }
+
+            match update {
+                None => {
+                    println!("Request {} has been removed", &req.id);
+                    std::process::exit(1);
+                }
+                Some(r) => {
+                    if r.status != ApprovalStatus::Pending {
+                        req = r;
+                        break;
+                    }
+                    if r.expired(epoch_secs()) {
+                        println!("Request {} has expired", &req.id);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+    }
+
+    // a decision is only good for one run, only the run that claims it goes on
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    let claimed = claim_approval(&req.id);
+    if !drop_privs(ro) {
+        std::process::exit(1);
+    }
+    let req = match claimed {
+        Some(r) => r,
+        None => {
+            log_action(
+                service,
+                "approval_unclaimed",
+                ro,
+                &ro.original_command.join(" "),
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let approver = req.approver.clone().unwrap_or_default();
+    if req.status == ApprovalStatus::Rejected {
+        log_action(
+            service,
+            "approval_rejected",
+            ro,
+            &ro.original_command.join(" "),
+        );
+        println!("Request {} was rejected by {}", &req.id, approver);
+        std::process::exit(1);
+    }
+
+    log_action(
+        service,
+        "approval_granted",
+        ro,
+        &ro.original_command.join(" "),
+    );
+    println!("Request {} was approved by {}", &req.id, approver);
+}
+
+/// log and explain that the section has no uses left, then exit 1
+fn quota_exhausted(service: &str, ro: &RunOptions, entry: &EnvOptions) -> ! {
+    log_action(
+        service,
+        "quota_exhausted",
+        ro,
+        &ro.original_command.join(" "),
+    );
+    println!(
+        "You have used all of your uses ({}) of \"{}\" on {} as {}",
+        entry.max_uses.as_ref().unwrap(),
+        &ro.command,
+        &ro.hostname,
+        &ro.target
+    );
+    std::process::exit(1);
 }
 
 /// navigate to directory or exit 1
@@ -109,14 +406,34 @@ fn general_options(
         "allow permitted comma separated envs",
         "LIST",
     );
+    opts.optopt("", "approve", "approve a pending request", "ID");
     opts.optopt("c", "check", "check config file", "FILE");
     opts.optopt("d", "dir", "change to directory prior to execution", "DIR");
+    opts.optopt(
+        "",
+        "diff",
+        "compare config file OLD with the NEW file that follows",
+        "OLD",
+    );
+    opts.optflag(
+        "",
+        "effective",
+        "list rules with defaults merged and shadowed rules marked",
+    );
+    opts.optopt("", "format", "output format of -l, text or json", "FORMAT");
     opts.optopt("g", "group", "become target group", "GROUP");
     opts.optflag("h", "help", "print usage help");
     opts.optflag("l", "list", "list effective rules, can combine with -t/-u");
+    opts.optopt(
+        "",
+        "lint",
+        "check config file for dead, shadowed and broad rules",
+        "FILE",
+    );
     opts.optflag("n", "noprompt", "do nothing if a password is required");
     opts.optflag("p", "purge", "purge access token");
     opts.optopt("r", "reason", "provide reason for execution", "REASON");
+    opts.optopt("", "reject", "reject a pending request", "ID");
     opts.optopt("t", "target", "become target user", "USER");
     opts.optopt("u", "user", "become target user", "USER");
     opts.optflag("v", "version", "print version and exit");
@@ -133,14 +450,74 @@ fn general_options(
     if matches.opt_present("c") {
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        std::process::exit(read_ini_config_file(
+        if let Err(error) = read_ini_config_file(
             &matches.opt_str("c").unwrap(),
             vec_eo,
             ro,
             true,
             &mut bytes,
             &mut ini_list,
-        ) as i32);
+        ) {
+            exit_with(error);
+        }
+        std::process::exit(0);
+    }
+
+    if matches.opt_present("lint") {
+        // read as the caller, so that lint does not show files they cannot read
+        if !drop_privs(ro) {
+            std::process::exit(1);
+        }
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        if let Err(error) = read_ini_config_file(
+            &matches.opt_str("lint").unwrap(),
+            vec_eo,
+            ro,
+            true,
+            &mut bytes,
+            &mut ini_list,
+        ) {
+            exit_with(error);
+        }
+
+        let warnings = lint(vec_eo, ro.date);
+        for warning in warnings.iter() {
+            println!("{}", warning);
+        }
+        std::process::exit(if warnings.is_empty() { 0 } else { 1 });
+    }
+
+    if matches.opt_present("diff") {
+        if matches.free.len() != 1 {
+            println!("--diff needs the old and new config files");
+            std::process::exit(1);
+        }
+
+        // read as the caller, so that diff does not show files they cannot read
+        if !drop_privs(ro) {
+            std::process::exit(1);
+        }
+
+        let mut policies = vec![];
+        for config in [matches.opt_str("diff").unwrap(), matches.free[0].clone()] {
+            let mut policy: Vec<EnvOptions> = vec![];
+            let mut bytes = 0;
+            let mut ini_list: HashMap<String, bool> = HashMap::new();
+            if let Err(error) =
+                read_ini_config_file(&config, &mut policy, ro, true, &mut bytes, &mut ini_list)
+            {
+                exit_with(error);
+            }
+            policies.push(policy);
+        }
+
+        let changes = diff_policy(&policies[0], &policies[1]);
+        for line in produce_diff(&changes) {
+            println!("{}", line);
+        }
+        std::process::exit(if changes.is_empty() { 0 } else { 1 });
     }
 
     let root_uid = nix::unistd::Uid::from_raw(0);
@@ -173,11 +550,50 @@ fn general_options(
     if matches.opt_present("l") {
         ro.acl_type = Acltype::List;
     }
+    if matches.opt_present("effective") {
+        if ro.acl_type != Acltype::List {
+            println!("--effective can only be used with --list");
+            std::process::exit(1);
+        }
+        ro.effective = true;
+    }
+    if matches.opt_present("format") {
+        if ro.acl_type != Acltype::List {
+            println!("--format can only be used with --list");
+            std::process::exit(1);
+        }
+        ro.list_format = match parse_list_format(&matches.opt_str("format").unwrap()) {
+            Some(format) => format,
+            None => {
+                println!("--format must be text or json");
+                std::process::exit(1);
+            }
+        };
+    }
+    if matches.opt_present("approve") {
+        ro.approve = Some(matches.opt_str("approve").unwrap());
+    }
+    if matches.opt_present("reject") {
+        ro.reject = Some(matches.opt_str("reject").unwrap());
+    }
 
     let header = format!("{} [arguments] </path/to/executable>", &service);
-    common_opt_arguments(&matches, &opts, ro, service, &header);
+    if let Err(error) = common_opt_arguments(&matches, &opts, ro, service, &header) {
+        if matches!(error, PleaseError::Usage(_)) {
+            println!("{}", error);
+            print_usage(&opts, &header);
+            std::process::exit(1);
+        }
+        exit_with(error);
+    }
 
-    if ro.new_args.is_empty() && !ro.warm_token && !ro.purge_token && ro.acl_type != Acltype::List {
+    if ro.new_args.is_empty()
+        && !ro.warm_token
+        && !ro.purge_token
+        && ro.acl_type != Acltype::List
+        && ro.approve.is_none()
+        && ro.reject.is_none()
+    {
         println!("No command given");
         print_usage(&opts, &header);
         print_version(service);
@@ -212,8 +628,8 @@ fn main() {
     let args: Vec<String> = std::env::args().collect();
     let service = String::from("please");
     let mut ro = RunOptions::new();
-    let original_uid = get_current_uid();
-    let original_user = get_user_by_uid(original_uid).unwrap();
+    let original_uid = identity().current_uid();
+    let original_user = identity().user_by_uid(original_uid).unwrap();
     ro.name = original_user.name().to_string_lossy().to_string();
     ro.syslog = true;
     ro.original_command.clone_from(&args);
@@ -235,14 +651,17 @@ fn main() {
 
     clean_environment(&mut ro);
 
-    ro.groups = group_hash(original_user.groups().unwrap());
+    ro.groups = group_hash(identity().user_groups(&original_user).unwrap());
     if !esc_privs() {
         std::process::exit(1);
     }
 
+    // the sshd session's sockets are read as root
+    ro.from = connection_source(ro.tty.as_deref());
+
     let mut bytes = 0;
     let mut ini_list: HashMap<String, bool> = HashMap::new();
-    if read_ini_config_file(
+    if let Err(error) = read_ini_config_file(
         "/etc/please.ini",
         &mut vec_eo,
         &ro,
@@ -250,16 +669,25 @@ fn main() {
         &mut bytes,
         &mut ini_list,
     ) {
-        println!("Exiting due to error, cannot fully process /etc/please.ini");
-        std::process::exit(1);
+        exit_with(error);
     }
 
     if !drop_privs(&ro) {
         std::process::exit(1);
     }
 
+    if ro.approve.is_some() || ro.reject.is_some() {
+        do_approval_decision(&mut ro, &service);
+        return;
+    }
+
     ro.command = replace_new_args(ro.new_args.clone());
 
+    if ro.acl_type == Acltype::List && !ro.new_args.is_empty() {
+        do_check(&mut ro, &vec_eo, &service);
+        return;
+    }
+
     if ro.acl_type == Acltype::List {
         if ro.target.is_empty() {
             ro.target = ro.name.to_string();
@@ -287,6 +715,9 @@ fn main() {
         exit_if_command_not_found(&ro, &service);
 
         print_may_not(&ro);
+        if let Some(why) = tty_explain(&vec_eo, &ro) {
+            println!("{}", why);
+        }
         std::process::exit(1);
     }
 
@@ -296,8 +727,13 @@ fn main() {
         std::process::exit(1);
     }
 
+    if locked_out(&ro, &entry).unwrap_or_else(|e| exit_with(e)) {
+        log_action(&service, "lockout", &ro, &ro.original_command.join(" "));
+        std::process::exit(1);
+    }
+
     // password required?
-    if !challenge_password(&ro, &entry, &service) {
+    if !challenge_password(&ro, &entry, &service).unwrap_or_else(|e| exit_with(e)) {
         log_action(&service, "deny", &ro, &ro.original_command.join(" "));
         std::process::exit(1);
     }
@@ -306,15 +742,30 @@ fn main() {
         std::process::exit(1);
     }
 
+    if entry.approval.is_some() {
+        do_approval_wait(&ro, &entry, &service);
+    }
+
+    // refuse early, the use is only charged once the command is about to run
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    if !uses_left(&ro.name, &entry) {
+        quota_exhausted(&service, &ro, &entry);
+    }
+    if !drop_privs(&ro) {
+        std::process::exit(1);
+    }
+
     // target user
-    let lookup_name = get_user_by_name(&ro.target);
+    let lookup_name = identity().user_by_name(&ro.target);
     if lookup_name.is_none() {
         println!("Could not lookup {}", &ro.target);
         std::process::exit(1);
     }
     let lookup_name = lookup_name.unwrap();
     let target_uid = nix::unistd::Uid::from_raw(lookup_name.uid());
-    let target_gid = runopt_target_gid(&ro, &lookup_name);
+    let target_gid = runopt_target_gid(&ro, &lookup_name).unwrap_or_else(|e| exit_with(e));
 
     if !esc_privs() {
         std::process::exit(1);
@@ -330,6 +781,16 @@ fn main() {
         std::process::exit(1);
     }
 
+    if !esc_privs() {
+        std::process::exit(1);
+    }
+    if !consume_use(&ro.name, &entry) {
+        quota_exhausted(&service, &ro, &entry);
+    }
+    if !drop_privs(&ro) {
+        std::process::exit(1);
+    }
+
     log_action(&service, "permit", &ro, &ro.original_command.join(" "));
 
     set_environment(&ro, &entry, &original_user, original_uid, &lookup_name);
@@ -18,43 +18,697 @@ use regex::Regex;
 use std::collections::HashMap;
 use std::env;
 use std::ffi::{CStr, CString};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
 use syslog::{Facility, Formatter3164};
 
-use chrono::{NaiveDate, NaiveDateTime, Utc};
-use nix::sys::signal;
-use nix::sys::signal::*;
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
 
 use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::net::IpAddr;
 use std::time::SystemTime;
 use uzers::os::unix::UserExt;
 use uzers::*;
 
 use getopts::{Matches, Options};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
 use nix::unistd::{alarm, gethostname, setegid, seteuid, setgid, setuid};
 use pam::Authenticator;
 
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
+use std::os::fd::IntoRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+/// why a library call could not complete. messages that explain the detail,
+/// such as each config error, have already gone to the reporter
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum PleaseError {
+    /// nothing left to do, such as after --help, or the reason has already
+    /// been reported, exit with the status
+    Exit(i32),
+    /// the arguments given cannot be used together
+    Usage(String),
+    /// the config file could not be read or has errors
+    Config(String),
+    /// the config files read are larger than the limit
+    ConfigTooLarge,
+    /// privileges could not be changed
+    Privileges,
+    /// the terminal could not be used
+    Terminal(String),
+    /// no password was given within the timeout
+    Timeout,
+    /// pam could not be started
+    Pam(String),
+    /// the target group does not exist
+    Group(String),
+    /// the name of this host could not be read
+    Hostname(String),
+    /// the request is not permitted
+    Denied(String),
+    /// a file of the edit could not be read, written or replaced
+    Edit(String),
+    /// the user does not exist
+    User(String),
+}
+
+impl fmt::Display for PleaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PleaseError::Exit(_) => Ok(()),
+            PleaseError::Usage(message) => write!(f, "{}", message),
+            PleaseError::Config(path) => {
+                write!(f, "Exiting due to error, cannot fully process {}", path)
+            }
+            PleaseError::ConfigTooLarge => {
+                write!(f, "Exiting as too much config has already been read.")
+            }
+            PleaseError::Privileges => write!(f, "Could not change privileges"),
+            PleaseError::Terminal(message) => write!(f, "{}", message),
+            PleaseError::Timeout => write!(f, "Timed out getting password"),
+            PleaseError::Pam(message) => write!(f, "Cannot init PAM: {}", message),
+            PleaseError::Group(group) => write!(f, "Cannot assign group {}", group),
+            PleaseError::Hostname(message) => write!(f, "Could not get hostname: {}", message),
+            PleaseError::Denied(message) => write!(f, "{}", message),
+            PleaseError::Edit(message) => write!(f, "{}", message),
+            PleaseError::User(user) => write!(f, "Could not lookup {}", user),
+        }
+    }
+}
+
+impl std::error::Error for PleaseError {}
+
+impl PleaseError {
+    /// the status a frontend should exit with
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PleaseError::Exit(code) => *code,
+            _ => 1,
+        }
+    }
+}
+
+/// where the library sends messages meant for the user
+pub trait Reporter: Send + Sync {
+    fn report(&self, message: &str);
+
+    /// ask a question and return the answer, None if there is nobody to ask
+    fn ask(&self, _prompt: &str) -> Option<String> {
+        None
+    }
+}
+
+/// print each message on a line of stdout, used unless set_reporter is called
+pub struct StdoutReporter;
+
+impl Reporter for StdoutReporter {
+    fn report(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn ask(&self, prompt: &str) -> Option<String> {
+        print!("{}", prompt);
+        std::io::stdout().flush().ok()?;
+
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => {
+                println!();
+                None
+            }
+            Ok(_) => Some(answer),
+        }
+    }
+}
+
+/// keep the messages, for callers that show them their own way
+#[derive(Default)]
+pub struct BufferReporter {
+    messages: Mutex<Vec<String>>,
+}
+
+impl BufferReporter {
+    pub fn new() -> BufferReporter {
+        BufferReporter::default()
+    }
+
+    /// the messages so far, leaving the buffer empty
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+}
+
+impl Reporter for BufferReporter {
+    fn report(&self, message: &str) {
+        self.messages.lock().unwrap().push(message.to_string());
+    }
+}
+
+static REPORTER: RwLock<Option<Arc<dyn Reporter>>> = RwLock::new(None);
+
+/// send the library messages to reporter rather than stdout
+pub fn set_reporter(reporter: Arc<dyn Reporter>) {
+    *REPORTER.write().unwrap() = Some(reporter);
+}
+
+/// pass a message to the reporter
+pub fn report(message: &str) {
+    match REPORTER.read().unwrap().as_ref() {
+        Some(reporter) => reporter.report(message),
+        None => StdoutReporter.report(message),
+    }
+}
+
+/// put a question to the reporter
+pub fn ask(prompt: &str) -> Option<String> {
+    match REPORTER.read().unwrap().as_ref() {
+        Some(reporter) => reporter.ask(prompt),
+        None => StdoutReporter.ask(prompt),
+    }
+}
+
+/// user and group lookups
+pub trait Identity: Send + Sync {
+    fn current_uid(&self) -> u32;
+    fn current_gid(&self) -> u32;
+    fn user_by_uid(&self, uid: u32) -> Option<User>;
+    fn user_by_name(&self, name: &str) -> Option<User>;
+    fn group_by_name(&self, name: &str) -> Option<Group>;
+    /// the groups the user is a member of, including the primary group
+    fn user_groups(&self, user: &User) -> Option<Vec<Group>>;
+}
+
+/// the time that dated rules are checked against
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// the name of this host, for hostname rules
+pub trait HostnameSource: Send + Sync {
+    fn hostname(&self) -> Result<String, PleaseError>;
+}
+
+/// the file lookups made while reading config and searching for commands
+pub trait Filesystem: Send + Sync {
+    fn exists(&self, path: &str) -> bool;
+    fn is_file(&self, path: &str) -> bool;
+    /// the paths within a directory, unsorted
+    fn read_dir(&self, path: &str) -> std::io::Result<Vec<String>>;
+    /// up to limit bytes of a config file, the error is the message to report
+    fn read_config(&self, path: &str, limit: u64) -> Result<String, String>;
+}
+
+/// the user and group databases of this system
+pub struct SystemIdentity;
+
+impl Identity for SystemIdentity {
+    fn current_uid(&self) -> u32 {
+        get_current_uid()
+    }
+
+    fn current_gid(&self) -> u32 {
+        get_current_gid()
+    }
+
+    fn user_by_uid(&self, uid: u32) -> Option<User> {
+        get_user_by_uid(uid)
+    }
+
+    fn user_by_name(&self, name: &str) -> Option<User> {
+        get_user_by_name(name)
+    }
+
+    fn group_by_name(&self, name: &str) -> Option<Group> {
+        get_group_by_name(name)
+    }
+
+    fn user_groups(&self, user: &User) -> Option<Vec<Group>> {
+        user.groups()
+    }
+}
+
+/// the system clock, in UTC
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Utc::now().naive_utc()
+    }
+}
+
+/// the hostname given by gethostname
+pub struct SystemHostname;
+
+impl HostnameSource for SystemHostname {
+    fn hostname(&self) -> Result<String, PleaseError> {
+        gethostname()
+            .map_err(|x| PleaseError::Hostname(x.to_string()))?
+            .into_string()
+            .map_err(|_| PleaseError::Hostname("not valid UTF-8".to_string()))
+    }
+}
+
+/// the files on disk
+pub struct SystemFilesystem;
+
+impl Filesystem for SystemFilesystem {
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        Path::new(path).is_file()
+    }
+
+    fn read_dir(&self, path: &str) -> std::io::Result<Vec<String>> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(path)? {
+            paths.push(entry?.path().to_string_lossy().to_string());
+        }
+        Ok(paths)
+    }
+
+    fn read_config(&self, path: &str, limit: u64) -> Result<String, String> {
+        let file = File::open(path).map_err(|why| format!("Could not open {}: {}", path, why))?;
+
+        let stat_data = nix::sys::stat::fstat(&file)
+            .map_err(|why| format!("Could not stat {}: {}", path, why))?;
+        if stat_data.st_mode & libc::S_IFREG != libc::S_IFREG {
+            return Err("Refusing to open non-regular file".to_string());
+        }
+        if (stat_data.st_mode & !libc::S_IFMT) & (0o022) != 0 {
+            return Err(
+                "Refusing to parse file as group or other write permission bits are set"
+                    .to_string(),
+            );
+        }
+
+        let mut s = String::new();
+        BufReader::new(file)
+            .take(limit)
+            .read_to_string(&mut s)
+            .map_err(|why| format!("Could not read {}: {}", path, why))?;
+        Ok(s)
+    }
+}
+
+/// users and groups held in memory, for tests
+#[cfg(any(test, feature = "testing"))]
+#[derive(Default)]
+pub struct FakeIdentity {
+    pub uid: u32,
+    pub gid: u32,
+    pub users: Vec<User>,
+    pub groups: Vec<Group>,
+    /// user name to the names of its supplementary groups
+    pub members: HashMap<String, Vec<String>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl FakeIdentity {
+    pub fn new() -> FakeIdentity {
+        FakeIdentity::default()
+    }
+
+    /// add a user and its primary group, which shares its name and id
+    pub fn add_user(&mut self, name: &str, uid: u32) {
+        self.users.push(User::new(uid, name, uid));
+        self.add_group(name, uid);
+    }
+
+    pub fn add_group(&mut self, name: &str, gid: u32) {
+        if self.group_by_name(name).is_none() {
+            self.groups.push(Group::new(gid, name));
+        }
+    }
+
+    pub fn add_member(&mut self, user: &str, group: &str) {
+        self.members
+            .entry(user.to_string())
+            .or_default()
+            .push(group.to_string());
+    }
+
+    /// make name the user that is running
+    pub fn login(&mut self, name: &str) {
+        if let Some(user) = self.user_by_name(name) {
+            self.uid = user.uid();
+            self.gid = user.primary_group_id();
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Identity for FakeIdentity {
+    fn current_uid(&self) -> u32 {
+        self.uid
+    }
+
+    fn current_gid(&self) -> u32 {
+        self.gid
+    }
+
+    fn user_by_uid(&self, uid: u32) -> Option<User> {
+        self.users.iter().find(|u| u.uid() == uid).cloned()
+    }
+
+    fn user_by_name(&self, name: &str) -> Option<User> {
+        self.users.iter().find(|u| u.name() == name).cloned()
+    }
+
+    fn group_by_name(&self, name: &str) -> Option<Group> {
+        self.groups.iter().find(|g| g.name() == name).cloned()
+    }
+
+    fn user_groups(&self, user: &User) -> Option<Vec<Group>> {
+        let name = user.name().to_string_lossy().to_string();
+        let mut groups: Vec<Group> = self
+            .groups
+            .iter()
+            .filter(|g| g.gid() == user.primary_group_id())
+            .cloned()
+            .collect();
+        for group in self.members.get(&name).into_iter().flatten() {
+            groups.push(self.group_by_name(group)?);
+        }
+        Some(groups)
+    }
+}
+
+/// a clock stopped at a fixed time, for tests
+#[cfg(any(test, feature = "testing"))]
+pub struct FakeClock(pub NaiveDateTime);
+
+#[cfg(any(test, feature = "testing"))]
+impl Clock for FakeClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+/// a fixed hostname, for tests
+#[cfg(any(test, feature = "testing"))]
+pub struct FakeHostname(pub String);
+
+#[cfg(any(test, feature = "testing"))]
+impl HostnameSource for FakeHostname {
+    fn hostname(&self) -> Result<String, PleaseError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// files and directories held in memory, for tests
+#[cfg(any(test, feature = "testing"))]
+#[derive(Default)]
+pub struct FakeFilesystem {
+    /// path to the content and mode of each file
+    pub files: HashMap<String, (String, u32)>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl FakeFilesystem {
+    pub fn new() -> FakeFilesystem {
+        FakeFilesystem::default()
+    }
+
+    /// add a file with mode 0644
+    pub fn add_file(&mut self, path: &str, content: &str) {
+        self.add_file_mode(path, content, 0o644);
+    }
+
+    pub fn add_file_mode(&mut self, path: &str, content: &str, mode: u32) {
+        self.files
+            .insert(path.to_string(), (content.to_string(), mode));
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.files.keys().any(|f| f.starts_with(&prefix))
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Filesystem for FakeFilesystem {
+    fn exists(&self, path: &str) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read_dir(&self, path: &str) -> std::io::Result<Vec<String>> {
+        if !self.is_dir(path) {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        }
+
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut paths: Vec<String> = vec![];
+        for file in self.files.keys() {
+            if let Some(rest) = file.strip_prefix(&prefix) {
+                let entry = format!("{}{}", prefix, rest.split('/').next().unwrap());
+                if !paths.contains(&entry) {
+                    paths.push(entry);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    fn read_config(&self, path: &str, limit: u64) -> Result<String, String> {
+        if self.is_dir(path) {
+            return Err("Refusing to open non-regular file".to_string());
+        }
+        match self.files.get(path) {
+            None => Err(format!(
+                "Could not open {}: No such file or directory (os error 2)",
+                path
+            )),
+            Some((_, mode)) if mode & 0o022 != 0 => Err(
+                "Refusing to parse file as group or other write permission bits are set"
+                    .to_string(),
+            ),
+            Some((content, _)) => {
+                let mut end = (limit as usize).min(content.len());
+                while !content.is_char_boundary(end) {
+                    end -= 1;
+                }
+                Ok(content[..end].to_string())
+            }
+        }
+    }
+}
+
+static IDENTITY: RwLock<Option<Arc<dyn Identity>>> = RwLock::new(None);
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+static HOSTNAME: RwLock<Option<Arc<dyn HostnameSource>>> = RwLock::new(None);
+static FILESYSTEM: RwLock<Option<Arc<dyn Filesystem>>> = RwLock::new(None);
+
+/// look up users and groups with identity rather than the system databases
+pub fn set_identity(identity: Arc<dyn Identity>) {
+    *IDENTITY.write().unwrap() = Some(identity);
+}
+
+/// take the time from clock rather than the system clock
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap() = Some(clock);
+}
+
+/// take the hostname from source rather than gethostname
+pub fn set_hostname_source(source: Arc<dyn HostnameSource>) {
+    *HOSTNAME.write().unwrap() = Some(source);
+}
+
+/// read config and find commands in filesystem rather than on disk
+pub fn set_filesystem(filesystem: Arc<dyn Filesystem>) {
+    *FILESYSTEM.write().unwrap() = Some(filesystem);
+}
+
+/// go back to the system providers and printing messages to stdout
+pub fn reset_providers() {
+    *IDENTITY.write().unwrap() = None;
+    *CLOCK.write().unwrap() = None;
+    *HOSTNAME.write().unwrap() = None;
+    *FILESYSTEM.write().unwrap() = None;
+    *REPORTER.write().unwrap() = None;
+}
+
+/// calls reset_providers when dropped, so that a test that fails does not
+/// leave its providers in place for the next
+#[cfg(any(test, feature = "testing"))]
+pub struct RestoreProviders;
+
+#[cfg(any(test, feature = "testing"))]
+impl Drop for RestoreProviders {
+    fn drop(&mut self) {
+        reset_providers();
+    }
+}
+
+/// the identity in use
+pub fn identity() -> Arc<dyn Identity> {
+    match IDENTITY.read().unwrap().as_ref() {
+        Some(identity) => identity.clone(),
+        None => Arc::new(SystemIdentity),
+    }
+}
+
+/// the time now, from the clock in use
+pub fn clock_now() -> NaiveDateTime {
+    match CLOCK.read().unwrap().as_ref() {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+/// the hostname, from the source in use
+pub fn hostname() -> Result<String, PleaseError> {
+    match HOSTNAME.read().unwrap().as_ref() {
+        Some(source) => source.hostname(),
+        None => SystemHostname.hostname(),
+    }
+}
+
+/// the filesystem in use
+pub fn filesystem() -> Arc<dyn Filesystem> {
+    match FILESYSTEM.read().unwrap().as_ref() {
+        Some(filesystem) => filesystem.clone(),
+        None => Arc::new(SystemFilesystem),
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum EditMode {
     Mode(i32),
     Keep(bool),
 }
 
+/// built-in checks of an edited file before it replaces the original
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Validate {
+    Ini,
+    Please,
+    Json,
+    Toml,
+    Yaml,
+    Sudoers,
+    Shell,
+}
+
+impl fmt::Display for Validate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Validate::Ini => write!(f, "ini"),
+            Validate::Please => write!(f, "please"),
+            Validate::Json => write!(f, "json"),
+            Validate::Toml => write!(f, "toml"),
+            Validate::Yaml => write!(f, "yaml"),
+            Validate::Sudoers => write!(f, "sudoers"),
+            Validate::Shell => write!(f, "shell"),
+        }
+    }
+}
+
+/// owner given to the file that replaces the edited one
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EditOwner {
+    Keep,
+    Target,
+    Owner(String, String),
+}
+
+impl fmt::Display for EditOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditOwner::Keep => write!(f, "keep"),
+            EditOwner::Target => write!(f, "target"),
+            EditOwner::Owner(user, group) => write!(f, "{}:{}", user, group),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ReasonType {
     Need(bool),
     Text(String),
 }
 
+/// permitted number of uses within period seconds
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaxUses {
+    pub count: u32,
+    pub period: u64,
+}
+
+impl fmt::Display for MaxUses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (secs, unit) in [(604800, "w"), (86400, "d"), (3600, "h"), (60, "m")] {
+            if self.period >= secs && (self.period / secs) * secs == self.period {
+                return write!(f, "{}/{}{}", self.count, self.period / secs, unit);
+            }
+        }
+        write!(f, "{}/{}s", self.count, self.period)
+    }
+}
+
+/// how please -l prints the rules
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ListFormat {
+    Text,
+    Json,
+}
+
+/// parse the -l output format, text or json
+pub fn parse_list_format(value: &str) -> Option<ListFormat> {
+    match value.to_lowercase().as_str() {
+        "text" => Some(ListFormat::Text),
+        "json" => Some(ListFormat::Json),
+        _ => None,
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TtyMode {
+    Required,
+    Forbidden,
+    Any,
+}
+
+impl fmt::Display for TtyMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TtyMode::Required => write!(f, "required"),
+            TtyMode::Forbidden => write!(f, "forbidden"),
+            TtyMode::Any => write!(f, "any"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct EnvOptions {
     pub name: Option<String>,
     pub exact_name: Option<String>,
@@ -79,7 +733,18 @@ pub struct EnvOptions {
     pub dir: Option<String>,
     pub exact_dir: Option<String>,
     pub exitcmd: Option<String>,
+    pub backup: Option<String>,
+    pub backup_keep: Option<u32>,
+    pub validate: Option<Validate>,
+    pub editor: Option<String>,
+    pub editor_default: Option<String>,
     pub edit_mode: Option<EditMode>,
+    pub edit_owner: Option<EditOwner>,
+    pub create: Option<bool>,
+    pub template: Option<String>,
+    pub mkdir: Option<bool>,
+    pub mkdir_mode: Option<u32>,
+    pub mkdir_owner: Option<EditOwner>,
     pub reason: Option<ReasonType>,
     pub last: Option<bool>,
     pub syslog: Option<bool>,
@@ -88,6 +753,18 @@ pub struct EnvOptions {
     pub timeout: Option<u32>,
     pub search_path: Option<String>,
     pub token_timeout: Option<u64>,
+    pub password_retries: Option<u32>,
+    pub failure_delay: Option<u64>,
+    pub lockout: Option<u32>,
+    pub lockout_window: Option<u64>,
+    pub lockout_time: Option<u64>,
+    pub approval: Option<String>,
+    pub approval_timeout: Option<u64>,
+    pub max_uses: Option<MaxUses>,
+    pub from: Option<String>,
+    pub tty: Option<TtyMode>,
+    pub confirm: Option<bool>,
+    pub atomic: Option<bool>,
 }
 
 impl EnvOptions {
@@ -116,7 +793,18 @@ impl EnvOptions {
             dir: None,
             exact_dir: None,
             exitcmd: None,
+            backup: None,
+            backup_keep: None,
+            validate: None,
+            editor: None,
+            editor_default: None,
             edit_mode: None,
+            edit_owner: None,
+            create: None,
+            template: None,
+            mkdir: None,
+            mkdir_mode: None,
+            mkdir_owner: None,
             reason: None,
             last: None,
             syslog: None,
@@ -125,6 +813,18 @@ impl EnvOptions {
             timeout: None,
             search_path: None,
             token_timeout: None,
+            password_retries: None,
+            failure_delay: None,
+            lockout: None,
+            lockout_window: None,
+            lockout_time: None,
+            approval: None,
+            approval_timeout: None,
+            max_uses: None,
+            from: None,
+            tty: None,
+            confirm: None,
+            atomic: None,
         }
     }
     fn new_deny() -> EnvOptions {
@@ -157,10 +857,44 @@ impl Default for EnvOptions {
     }
 }
 
+/// uid and gid are written as their number
+#[cfg(feature = "serde")]
+mod serde_id {
+    pub mod uid {
+        use nix::unistd::Uid;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(uid: &Uid, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u32(uid.as_raw())
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uid, D::Error> {
+            Ok(Uid::from_raw(u32::deserialize(deserializer)?))
+        }
+    }
+
+    pub mod gid {
+        use nix::unistd::Gid;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(gid: &Gid, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u32(gid.as_raw())
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Gid, D::Error> {
+            Ok(Gid::from_raw(u32::deserialize(deserializer)?))
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct RunOptions {
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(with = "serde_id::uid"))]
     pub original_uid: nix::unistd::Uid,
+    #[cfg_attr(feature = "serde", serde(with = "serde_id::gid"))]
     pub original_gid: nix::unistd::Gid,
     pub target: String,
     pub target_group: Option<String>,
@@ -176,7 +910,14 @@ pub struct RunOptions {
     pub prompt: bool,
     pub purge_token: bool,
     pub warm_token: bool,
+    pub history: bool,
+    pub rollback: bool,
+    pub policy: bool,
+    pub recover: bool,
+    pub list_format: ListFormat,
+    pub effective: bool,
     pub new_args: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub old_umask: Option<nix::sys::stat::Mode>,
     pub old_envs: Option<HashMap<String, String>>,
     pub allow_env_list: Option<Vec<String>>,
@@ -184,20 +925,26 @@ pub struct RunOptions {
     pub cloned_args: Option<Vec<String>>,
     pub located_bin: HashMap<String, Option<String>>,
     pub resume: Option<bool>,
+    pub approve: Option<String>,
+    pub reject: Option<String>,
+    pub from: String,
+    pub tty: Option<String>,
+    pub confirm: Option<bool>,
+    pub atomic: Option<bool>,
 }
 
 impl RunOptions {
     pub fn new() -> RunOptions {
         RunOptions {
             name: "root".to_string(),
-            original_uid: nix::unistd::Uid::from_raw(get_current_uid()),
-            original_gid: nix::unistd::Gid::from_raw(get_current_gid()),
+            original_uid: nix::unistd::Uid::from_raw(identity().current_uid()),
+            original_gid: nix::unistd::Gid::from_raw(identity().current_gid()),
             target: "".to_string(),
             target_group: None,
             command: "".to_string(),
             original_command: vec![],
             hostname: "localhost".to_string(),
-            date: Utc::now().naive_utc(),
+            date: clock_now(),
             groups: HashMap::new(),
             directory: None,
             acl_type: Acltype::Run,
@@ -206,6 +953,12 @@ impl RunOptions {
             prompt: true,
             purge_token: false,
             warm_token: false,
+            history: false,
+            rollback: false,
+            policy: false,
+            recover: false,
+            list_format: ListFormat::Text,
+            effective: false,
             new_args: vec![],
             old_umask: None,
             old_envs: None,
@@ -214,6 +967,12 @@ impl RunOptions {
             cloned_args: None,
             located_bin: HashMap::new(),
             resume: None,
+            approve: None,
+            reject: None,
+            from: "unknown".to_string(),
+            tty: tty_name(),
+            confirm: None,
+            atomic: None,
         }
     }
 }
@@ -228,36 +987,134 @@ struct PamConvo {
     login: String,
     passwd: Option<String>,
     service: String,
+    timeout: Option<u32>,
+    failure: Option<PleaseError>,
 }
 
-impl pam::Converse for PamConvo {
-    fn prompt_echo(&mut self, _msg: &CStr) -> Result<CString, ()> {
-        CString::new(self.login.clone()).map_err(|_| ())
-    }
-    fn prompt_blind(&mut self, _msg: &CStr) -> Result<CString, ()> {
-        match rpassword::prompt_password(format!(
-            "[{}] password for {}: ",
-            self.service, self.login
-        )) {
-            Ok(password) => self.passwd = Some(password),
-            Err(_) => {
-                println!("Cannot read from terminal");
-                std::process::exit(1);
-            }
-        }
+/// set by SIGALRM while a password is read
+static PASSWORD_TIMED_OUT: AtomicBool = AtomicBool::new(false);
 
-        CString::new(self.passwd.clone().unwrap()).map_err(|_| ())
-    }
-    fn info(&mut self, _msg: &CStr) {}
-    fn error(&mut self, msg: &CStr) {
-        println!("[{} pam error] {}", self.service, msg.to_string_lossy());
-    }
-    fn username(&self) -> &str {
-        &self.login
-    }
+/// only async-signal-safe work here, the interrupted read sees the flag
+extern "C" fn password_timeout(_: libc::c_int) {
+    PASSWORD_TIMED_OUT.store(true, Ordering::SeqCst);
+}
+
+/// set what SIGALRM does while a password is read. without SA_RESTART the
+/// blocked read fails with EINTR
+fn password_alarm(handler: SigHandler) -> Result<(), PleaseError> {
+    let sa = SigAction::new(handler, SaFlags::empty(), SigSet::empty());
+    unsafe { sigaction(Signal::SIGALRM, &sa) }
+        .map(|_| ())
+        .map_err(|_| PleaseError::Terminal("Couldn't reset alarm".to_string()))
+}
+
+/// the terminal, reads fail with TimedOut once the password alarm has fired
+/// as std would otherwise retry the interrupted read
+struct PasswordTty(File);
+
+impl Read for PasswordTty {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if PASSWORD_TIMED_OUT.load(Ordering::SeqCst) {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+            match self.0.read(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                res => return res,
+            }
+        }
+    }
+}
+
+/// read a password from the terminal without echo, Err(Timeout) when it is
+/// not given within timeout seconds
+fn read_password(prompt: &str, timeout: Option<u32>) -> Result<String, PleaseError> {
+    use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg};
+    let no_tty = || PleaseError::Terminal("Cannot read from terminal".to_string());
+
+    let tty = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| no_tty())?;
+    let term = tcgetattr(&tty).map_err(|_| no_tty())?;
+    let mut hidden = term.clone();
+    hidden.local_flags.remove(LocalFlags::ECHO);
+    hidden.local_flags.insert(LocalFlags::ECHONL);
+    tcsetattr(&tty, SetArg::TCSANOW, &hidden).map_err(|_| no_tty())?;
+
+    let config = rpassword::ConfigBuilder::new()
+        .input_reader(PasswordTty(tty.try_clone().map_err(|_| no_tty())?))
+        .output_writer(tty.try_clone().map_err(|_| no_tty())?)
+        .build();
+
+    PASSWORD_TIMED_OUT.store(false, Ordering::SeqCst);
+    if let Some(timeout) = timeout {
+        if let Err(e) = password_alarm(SigHandler::Handler(password_timeout)) {
+            let _ = tcsetattr(&tty, SetArg::TCSANOW, &term);
+            return Err(e);
+        }
+        alarm::set(timeout);
+    }
+
+    let password =
+        rpassword::prompt_password_with_config(prompt, config).map_err(|e| match e.kind() {
+            std::io::ErrorKind::TimedOut => PleaseError::Timeout,
+            _ => no_tty(),
+        });
+
+    if timeout.is_some() {
+        alarm::cancel();
+    }
+    if tcsetattr(&tty, SetArg::TCSANOW, &term).is_err() {
+        return Err(PleaseError::Terminal(
+            "Couldn't return terminal to original settings".to_string(),
+        ));
+    }
+    if timeout.is_some() {
+        password_alarm(SigHandler::SigDfl)?;
+    }
+    if password == Err(PleaseError::Timeout) {
+        let _ = (&tty).write_all(b"\n");
+    }
+
+    password
+}
+
+impl pam::Converse for PamConvo {
+    fn prompt_echo(&mut self, _msg: &CStr) -> Result<CString, ()> {
+        CString::new(self.login.clone()).map_err(|_| ())
+    }
+    fn prompt_blind(&mut self, _msg: &CStr) -> Result<CString, ()> {
+        match read_password(
+            &format!("[{}] password for {}: ", self.service, self.login),
+            self.timeout,
+        ) {
+            Ok(password) => self.passwd = Some(password),
+            Err(error) => {
+                self.failure = Some(error);
+                return Err(());
+            }
+        }
+
+        CString::new(self.passwd.clone().unwrap()).map_err(|_| ())
+    }
+    fn info(&mut self, _msg: &CStr) {}
+    fn error(&mut self, msg: &CStr) {
+        report(&format!(
+            "[{} pam error] {}",
+            self.service,
+            msg.to_string_lossy()
+        ));
+    }
+    fn username(&self) -> &str {
+        &self.login
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Acltype {
     Run,
     List,
@@ -275,7 +1132,7 @@ impl fmt::Display for Acltype {
 }
 
 pub fn print_may_not(ro: &RunOptions) {
-    println!(
+    report(&format!(
         "You may not {} \"{}\" on {} as {}",
         if ro.acl_type == Acltype::Run {
             "execute".to_string()
@@ -285,7 +1142,7 @@ pub fn print_may_not(ro: &RunOptions) {
         &ro.command,
         &ro.hostname,
         &ro.target
-    );
+    ));
 }
 
 /// build a regex and replace %{USER} with the user str, prefix with ^ and suffix with $
@@ -302,15 +1159,15 @@ pub fn regex_build(
             .replace("%{HOSTNAME}", &ro.hostname)
     ));
     if rule.is_err() {
-        println!(
+        report(&format!(
             "Error parsing {}{}",
             config_path,
-            if line.is_some() {
-                format!(": {}:{}", section, line.unwrap())
+            if let Some(line) = line {
+                format!(": {}:{}", section, line)
             } else {
                 "".to_string()
             }
-        );
+        ));
         return None;
     }
     Some(rule.unwrap())
@@ -318,9 +1175,7 @@ pub fn regex_build(
 
 /// return true if the inclusion exists and ends with .ini
 pub fn can_dir_include(file: &str) -> bool {
-    let p = Path::new(file);
-
-    if !p.is_file() {
+    if !filesystem().is_file(file) {
         return false;
     }
     can_include_file_pattern(file)
@@ -353,8 +1208,8 @@ pub fn can_include_file_pattern(file: &str) -> bool {
 
 /// print the usage
 pub fn print_usage(opts: &Options, header: &str) {
-    println!("usage:");
-    println!("{}", opts.usage(header));
+    report("usage:");
+    report(&opts.usage(header));
 }
 
 /// added around easter time
@@ -375,26 +1230,27 @@ pub fn credits(service: &str) {
 
     contributors.sort_unstable();
 
-    println!("\nWith thanks to the following teams and people, you got us where we are today.\n");
-    println!("If your name is missing, or incorrect, please get in contact.\n");
-    println!("In sort order:\n");
+    report("\nWith thanks to the following teams and people, you got us where we are today.\n");
+    report("If your name is missing, or incorrect, please get in contact.\n");
+    report("In sort order:\n");
 
     for i in contributors.iter() {
-        println!("\t{}", i);
+        report(&format!("\t{}", i));
     }
 
-    println!("\nYou too of course, for motivating me.");
-    println!("\nI thank you all for your help.\n\n\t-- Edward Neville");
+    report("\nYou too of course, for motivating me.");
+    report("\nI thank you all for your help.\n\n\t-- Edward Neville");
 }
 
-/// common opt arguments
+/// common opt arguments, Err(PleaseError::Exit) when there is nothing left
+/// to do
 pub fn common_opt_arguments(
     matches: &Matches,
     opts: &Options,
     ro: &mut RunOptions,
     service: &str,
     header: &str,
-) {
+) -> Result<(), PleaseError> {
     ro.new_args.clone_from(&matches.free);
 
     if matches.opt_present("r") {
@@ -414,9 +1270,9 @@ pub fn common_opt_arguments(
         && matches.opt_str("t").is_some()
         && matches.opt_str("t").unwrap() != matches.opt_str("u").unwrap()
     {
-        println!("Cannot use -t and -u with conflicting values");
-        print_usage(opts, header);
-        std::process::exit(1);
+        return Err(PleaseError::Usage(
+            "Cannot use -t and -u with conflicting values".to_string(),
+        ));
     }
 
     if matches.opt_present("p") {
@@ -424,7 +1280,7 @@ pub fn common_opt_arguments(
     }
     if matches.opt_present("v") {
         print_version(service);
-        std::process::exit(0);
+        return Err(PleaseError::Exit(0));
     }
     if matches.opt_present("w") {
         ro.warm_token = true;
@@ -437,36 +1293,39 @@ pub fn common_opt_arguments(
     if matches.opt_present("h") {
         if ro.new_args == ["credits"] {
             credits(service);
-            std::process::exit(0);
+            return Err(PleaseError::Exit(0));
         }
 
         print_usage(opts, header);
         print_version(service);
-        std::process::exit(0);
+        return Err(PleaseError::Exit(0));
     }
 
     if ro.purge_token {
-        if !esc_privs() {
-            std::process::exit(1);
-        }
+        try_esc_privs()?;
         remove_token(&ro.name);
-        if !drop_privs(ro) {
-            std::process::exit(1);
-        }
-        std::process::exit(0);
+        try_drop_privs(ro)?;
+        return Err(PleaseError::Exit(0));
     }
 
     if ro.warm_token {
         if ro.prompt {
-            challenge_password(ro, &EnvOptions::new(), service);
+            challenge_password(ro, &EnvOptions::new(), service)?;
         }
-        std::process::exit(0);
+        return Err(PleaseError::Exit(0));
     }
 
-    ro.hostname = gethostname()
-        .expect("Failed getting hostname")
-        .into_string()
-        .expect("Hostname wasn't valid UTF-8");
+    ro.hostname = hostname()?;
+
+    Ok(())
+}
+
+/// what has been read of a config and its includes so far
+struct ConfigRead<'a> {
+    bytes: &'a mut u64,
+    ini_list: &'a mut HashMap<String, bool>,
+    /// the path and text of a candidate file, read in place of the file on disk
+    candidate: Option<(&'a str, &'a str)>,
 }
 
 /// read an ini file and traverse includes
@@ -478,7 +1337,24 @@ pub fn read_ini(
     config_path: &str,
     bytes: &mut u64,
     ini_list: &mut HashMap<String, bool>,
-) -> bool {
+) -> Result<(), PleaseError> {
+    let mut read = ConfigRead {
+        bytes,
+        ini_list,
+        candidate: None,
+    };
+    parse_ini(conf, vec_eo, ro, fail_error, config_path, &mut read)
+}
+
+/// the work of read_ini, includes are read through read
+fn parse_ini(
+    conf: &str,
+    vec_eo: &mut Vec<EnvOptions>,
+    ro: &RunOptions,
+    fail_error: bool,
+    config_path: &str,
+    read: &mut ConfigRead,
+) -> Result<(), PleaseError> {
     let parse_datetime_from_str = NaiveDateTime::parse_from_str;
     let parse_date_from_str = NaiveDate::parse_from_str;
     let mut faulty = false;
@@ -486,12 +1362,12 @@ pub fn read_ini(
     let mut in_section = false;
     let mut opt = EnvOptions::new();
 
-    if ini_list.contains_key(config_path) {
-        println!("Error parsing already read file {}", config_path);
-        return false;
+    if read.ini_list.contains_key(config_path) {
+        report(&format!("Error parsing already read file {}", config_path));
+        return Ok(());
     }
 
-    ini_list.insert(config_path.to_string(), true);
+    read.ini_list.insert(config_path.to_string(), true);
 
     for (mut line_number, l) in conf.split('\n').enumerate() {
         line_number += 1;
@@ -505,6 +1381,9 @@ pub fn read_ini(
             in_section = true;
             section = line[1..line.len() - 1].to_string();
             if opt.configured {
+                if !section_ok(&opt, config_path) {
+                    faulty = true;
+                }
                 vec_eo.push(opt);
             }
             opt = EnvOptions::new();
@@ -520,9 +1399,10 @@ pub fn read_ini(
 
         let key = line[0..equals_pos.unwrap()].trim();
         let value = line[equals_pos.unwrap() + 1..].trim();
+        let at = Some(line_number as i32);
 
         if !in_section {
-            println!("Error parsing {}:{}", config_path, line_number);
+            report(&format!("Error parsing {}:{}", config_path, line_number));
             faulty = true;
             continue;
         }
@@ -548,38 +1428,33 @@ pub fn read_ini(
         match key {
             "include" => {
                 if !value.starts_with('/') {
-                    println!("Includes should start with /");
-                    return true;
+                    report("Includes should start with /");
+                    return Err(PleaseError::Config(config_path.to_string()));
                 }
-                if read_ini_config_file(value, vec_eo, ro, fail_error, bytes, ini_list) {
-                    println!("Could not include file");
-                    return true;
+                if let Err(error) = read_config(value, vec_eo, ro, fail_error, read) {
+                    report("Could not include file");
+                    return Err(include_error(error, config_path));
                 }
                 continue;
             }
             "includedir" => {
                 if !value.starts_with('/') {
-                    println!("Includes should start with /");
-                    return true;
+                    report("Includes should start with /");
+                    return Err(PleaseError::Config(config_path.to_string()));
                 }
-                match fs::read_dir(value) {
+                match filesystem().read_dir(value) {
                     Err(_x) => {
                         faulty = true;
                     }
-                    Ok(inc) => {
-                        let mut collect = vec![];
-                        for file in inc {
-                            collect.push(file.unwrap().path().to_str().unwrap().to_string());
-                        }
+                    Ok(mut collect) => {
                         collect.sort();
                         for file in collect {
                             if !can_dir_include(&file) {
                                 continue;
                             }
-                            if read_ini_config_file(&file, vec_eo, ro, fail_error, bytes, ini_list)
-                            {
-                                println!("Could not include file");
-                                return true;
+                            if let Err(error) = read_config(&file, vec_eo, ro, fail_error, read) {
+                                report("Could not include file");
+                                return Err(include_error(error, config_path));
                             }
                         }
                     }
@@ -590,10 +1465,7 @@ pub fn read_ini(
             "name" => {
                 opt.name = Some(value.to_string());
                 opt.configured = true;
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -604,10 +1476,7 @@ pub fn read_ini(
             "hostname" => {
                 opt.hostname = Some(value.to_string());
                 opt.configured = true;
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -617,10 +1486,7 @@ pub fn read_ini(
             }
             "target" => {
                 opt.target = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -629,10 +1495,7 @@ pub fn read_ini(
             }
             "target_group" => {
                 opt.target_group = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -649,10 +1512,7 @@ pub fn read_ini(
             "group" => opt.group = value == "true",
             "regex" | "rule" => {
                 opt.rule = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -684,28 +1544,19 @@ pub fn read_ini(
             }
             "datematch" => {
                 opt.datematch = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
             "dir" => {
                 opt.dir = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
             "exact_dir" => {
                 opt.exact_dir = Some(value.to_string());
-                if fail_error
-                    && regex_build(value, ro, config_path, &section, Some(line_number as i32))
-                        .is_none()
-                {
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
                     faulty = true;
                 }
             }
@@ -719,6 +1570,20 @@ pub fn read_ini(
                     opt.exitcmd = Some(value.to_string());
                 }
             }
+            "backup" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.backup = Some(value.trim_end_matches('/').to_string());
+                } else {
+                    faulty = true;
+                }
+            }
+            "backup_keep" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.backup_keep = value.parse().ok();
+                } else {
+                    faulty = true;
+                }
+            }
             "editmode" => {
                 if !value.is_empty() {
                     if value.parse::<i16>().is_ok() {
@@ -729,11 +1594,47 @@ pub fn read_ini(
                     } else if value.to_lowercase() == "keep" {
                         opt.edit_mode = Some(EditMode::Keep(true));
                     } else {
-                        println!("Could not convert {} to numerical file mode", value);
+                        report(&format!(
+                            "Could not convert {} to numerical file mode",
+                            value
+                        ));
                         faulty = true;
                     }
                 }
             }
+            "editor" => {
+                opt.editor = Some(value.to_string());
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
+                    faulty = true;
+                }
+            }
+            "editor_default" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.editor_default = Some(value.to_string());
+                } else {
+                    faulty = true;
+                }
+            }
+            "validate" => match parse_validate(value) {
+                Some(validate) => opt.validate = Some(validate),
+                None => {
+                    report(&format!(
+                        "Error parsing {}:{}, validate must be one of ini, please, json, toml, yaml, sudoers or shell",
+                        config_path, line_number
+                    ));
+                    faulty = true;
+                }
+            },
+            "editowner" => match parse_edit_owner(value) {
+                Some(owner) => opt.edit_owner = Some(owner),
+                None => {
+                    report(&format!(
+                        "Error parsing {}:{}, editowner must be keep, target or user:group",
+                        config_path, line_number
+                    ));
+                    faulty = true;
+                }
+            },
             "reason" => {
                 if value == "true" || value == "false" {
                     opt.reason = Some(ReasonType::Need(value == "true"));
@@ -741,39 +1642,246 @@ pub fn read_ini(
                     opt.reason = Some(ReasonType::Text(value.to_string()));
                 }
             }
-            "last" => opt.last = Some(value == "true"),
-            "syslog" => opt.syslog = Some(value == "true"),
-            "timeout" => {
-                let timeout: Result<u32, core::num::ParseIntError> = value.parse();
-                if fail_error && timeout.is_err() {
+            "mkdir_owner" => match parse_edit_owner(value) {
+                Some(owner) => opt.mkdir_owner = Some(owner),
+                None => {
+                    report(&format!(
+                        "Error parsing {}:{}, mkdir_owner must be keep, target or user:group",
+                        config_path, line_number
+                    ));
+                    faulty = true;
+                }
+            },
+            "mkdir_mode" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.mkdir_mode = u32::from_str_radix(value, 8).ok();
+                } else {
                     faulty = true;
+                }
+            }
+            "template" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.template = Some(value.to_string());
                 } else {
-                    opt.timeout = Some(timeout.unwrap());
+                    faulty = true;
                 }
             }
+            "create" => opt.create = Some(value == "true"),
+            "mkdir" => opt.mkdir = Some(value == "true"),
+            "confirm" => opt.confirm = Some(value == "true"),
+            "atomic" => opt.atomic = Some(value == "true"),
+            "last" => opt.last = Some(value == "true"),
+            "syslog" => opt.syslog = Some(value == "true"),
+            "timeout" => match value.parse::<u32>() {
+                Ok(timeout) => opt.timeout = Some(timeout),
+                Err(_) => faulty = true,
+            },
             "search_path" => {
                 opt.search_path = Some(value.to_string());
             }
-            "token_timeout" => {
-                let token_timeout: Result<u64, core::num::ParseIntError> = value.parse();
-                if fail_error && token_timeout.is_err() {
+            "token_timeout" => match value.parse::<u64>() {
+                Ok(token_timeout) => opt.token_timeout = Some(token_timeout),
+                Err(_) => faulty = true,
+            },
+            "approval" => {
+                opt.approval = Some(value.to_string());
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
+                    faulty = true;
+                }
+            }
+            "approval_timeout" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.approval_timeout = value.parse().ok();
+                } else {
+                    faulty = true;
+                }
+            }
+            "from" => {
+                opt.from = Some(value.to_string());
+                if fail_error && !check_key(key, value, ro, config_path, &section, at) {
+                    faulty = true;
+                }
+            }
+            "tty" => match value.to_lowercase().as_str() {
+                "required" => opt.tty = Some(TtyMode::Required),
+                "forbidden" => opt.tty = Some(TtyMode::Forbidden),
+                "any" => opt.tty = Some(TtyMode::Any),
+                _ => {
+                    report(&format!(
+                        "Could not convert {} to required, forbidden or any at {}:{}",
+                        value, config_path, line_number
+                    ));
                     faulty = true;
+                }
+            },
+            "max_uses" => {
+                if check_key(key, value, ro, config_path, &section, at) {
+                    opt.max_uses = parse_max_uses(value);
                 } else {
-                    opt.token_timeout = Some(token_timeout.unwrap());
+                    faulty = true;
                 }
             }
+            "password_retries" => match value.parse::<u32>() {
+                Ok(password_retries) => opt.password_retries = Some(password_retries),
+                Err(_) => faulty = true,
+            },
+            "failure_delay" => match value.parse::<u64>() {
+                Ok(failure_delay) => opt.failure_delay = Some(failure_delay),
+                Err(_) => faulty = true,
+            },
+            "lockout" => match value.parse::<u32>() {
+                Ok(lockout) => opt.lockout = Some(lockout),
+                Err(_) => faulty = true,
+            },
+            "lockout_window" => match value.parse::<u64>() {
+                Ok(lockout_window) => opt.lockout_window = Some(lockout_window),
+                Err(_) => faulty = true,
+            },
+            "lockout_time" => match value.parse::<u64>() {
+                Ok(lockout_time) => opt.lockout_time = Some(lockout_time),
+                Err(_) => faulty = true,
+            },
             &_ => {
-                println!("Error parsing {}:{}", config_path, line_number);
+                report(&format!("Error parsing {}:{}", config_path, line_number));
                 faulty = true;
             }
         }
     }
 
     if opt.configured {
+        if !section_ok(&opt, config_path) {
+            faulty = true;
+        }
         vec_eo.push(opt);
     }
 
-    fail_error && faulty
+    if fail_error && faulty {
+        return Err(PleaseError::Config(config_path.to_string()));
+    }
+    Ok(())
+}
+
+/// check the value of a key, as read from please.ini or found in a policy
+/// document, reporting it at the line when there is one or else the section.
+/// false if the value cannot be used
+fn check_key(
+    key: &str,
+    value: &str,
+    ro: &RunOptions,
+    config_path: &str,
+    section: &str,
+    line: Option<i32>,
+) -> bool {
+    let at = match line {
+        Some(line) => format!("{}:{}", config_path, line),
+        None => format!("{}: {}", config_path, section),
+    };
+    let error = |message: &str| {
+        report(&format!("Error parsing {}, {}", at, message));
+        false
+    };
+
+    match key {
+        "name" | "hostname" | "target" | "target_group" | "regex" | "rule" | "datematch"
+        | "dir" | "exact_dir" | "editor" | "approval" => {
+            regex_build(value, ro, config_path, section, line).is_some()
+        }
+        "from" => {
+            let mut ok = true;
+            for source in value.split(',') {
+                let source = source.trim();
+                match parse_cidr(source) {
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) => {
+                        report(&format!("Could not parse {} at {}", source, at));
+                        ok = false;
+                    }
+                    None => {
+                        if regex_build(source, ro, config_path, section, line).is_none() {
+                            ok = false;
+                        }
+                    }
+                }
+            }
+            ok
+        }
+        "backup" if !value.starts_with('/') => error("backup must be an absolute directory"),
+        "backup_keep" if !matches!(value.parse::<u32>(), Ok(keep) if keep > 0) => {
+            error("backup_keep must be a number above 0")
+        }
+        "editor_default" if !matches!(shell_split(value), Ok(args) if !args.is_empty()) => {
+            error("editor_default must be a command")
+        }
+        "mkdir_mode" if !matches!(u32::from_str_radix(value, 8), Ok(mode) if mode <= 0o7777) => {
+            error("mkdir_mode must be an octal mode")
+        }
+        "template" if !value.starts_with('/') => error("template must be an absolute file"),
+        "approval_timeout" if !matches!(value.parse::<u64>(), Ok(secs) if secs > 0) => {
+            error("approval_timeout must be a number of seconds above 0")
+        }
+        "max_uses" if parse_max_uses(value).is_none() => {
+            report(&format!(
+                "Could not convert {} to uses/period at {}",
+                value, at
+            ));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// checks that need every key of a section, made once the section is read
+fn section_ok(opt: &EnvOptions, config_path: &str) -> bool {
+    // pleaseedit cannot hold an edit while it waits for a second person
+    if opt.acl_type == Acltype::Edit && opt.approval.is_some() {
+        report(&format!(
+            "Error parsing {}: {}, approval cannot be used with type = edit",
+            config_path, opt.section
+        ));
+        return false;
+    }
+    true
+}
+
+/// the error for a file that includes one that failed, too much config
+/// stays as it is
+fn include_error(error: PleaseError, config_path: &str) -> PleaseError {
+    match error {
+        PleaseError::ConfigTooLarge => error,
+        _ => PleaseError::Config(config_path.to_string()),
+    }
+}
+
+/// parse uses/period, where period is seconds, a number suffixed with one of
+/// s, m, h, d or w, or one of minute, hour, day or week
+pub fn parse_max_uses(value: &str) -> Option<MaxUses> {
+    let slash_pos = value.find('/')?;
+    let count = value[0..slash_pos].trim().parse::<u32>().ok()?;
+    let period = value[slash_pos + 1..].trim().to_lowercase();
+
+    let period = match period.as_str() {
+        "minute" => 60,
+        "hour" => 3600,
+        "day" => 86400,
+        "week" => 604800,
+        _ => {
+            let (number, multiplier) = match period.chars().last()? {
+                's' => (&period[0..period.len() - 1], 1),
+                'm' => (&period[0..period.len() - 1], 60),
+                'h' => (&period[0..period.len() - 1], 3600),
+                'd' => (&period[0..period.len() - 1], 86400),
+                'w' => (&period[0..period.len() - 1], 604800),
+                _ => (period.as_str(), 1),
+            };
+            number.parse::<u64>().ok()?.checked_mul(multiplier)?
+        }
+    };
+
+    if period == 0 {
+        return None;
+    }
+
+    Some(MaxUses { count, period })
 }
 
 /// read through an ini config file, appending EnvOptions to vec_eo
@@ -785,72 +1893,207 @@ pub fn read_ini_config_file(
     fail_error: bool,
     bytes: &mut u64,
     ini_list: &mut HashMap<String, bool>,
-) -> bool {
-    let path = Path::new(config_path);
-    let display = path.display();
+) -> Result<(), PleaseError> {
+    let mut read = ConfigRead {
+        bytes,
+        ini_list,
+        candidate: None,
+    };
+    read_config(config_path, vec_eo, ro, fail_error, &mut read)
+}
 
-    let file = match File::open(path) {
-        Err(why) => {
-            println!("Could not open {}: {}", display, why);
-            return true;
+/// the work of read_ini_config_file, includes are read through read
+fn read_config(
+    config_path: &str,
+    vec_eo: &mut Vec<EnvOptions>,
+    ro: &RunOptions,
+    fail_error: bool,
+    read: &mut ConfigRead,
+) -> Result<(), PleaseError> {
+    // a candidate policy is read in place of the file on disk
+    if let Some((candidate_path, text)) = read.candidate {
+        if candidate_path == config_path {
+            *read.bytes += text.len() as u64;
+            return parse_ini(text, vec_eo, ro, fail_error, config_path, read);
         }
-        Ok(file) => file,
-    };
+    }
+
+    let byte_limit = 1024 * 1024 * 10;
 
-    match nix::sys::stat::fstat(&file) {
+    let s = match filesystem().read_config(config_path, byte_limit) {
         Err(why) => {
-            println!("Could not stat {}: {}", display, why);
-            return true;
+            report(&why);
+            return Err(PleaseError::Config(config_path.to_string()));
         }
-        Ok(stat_data) => {
-            if stat_data.st_mode & libc::S_IFREG != libc::S_IFREG {
-                println!("Refusing to open non-regular file");
-                return true;
-            }
+        Ok(s) => s,
+    };
 
-            if (stat_data.st_mode & !libc::S_IFMT) & (0o022) != 0 {
-                println!("Refusing to parse file as group or other write permission bits are set");
-                return true;
+    if *read.bytes >= byte_limit {
+        return Err(PleaseError::ConfigTooLarge);
+    }
+
+    *read.bytes += s.len() as u64;
+    if s.len() as u64 >= byte_limit {
+        return Err(PleaseError::ConfigTooLarge);
+    }
+
+    parse_ini(&s, vec_eo, ro, fail_error, config_path, read)
+}
+
+pub fn read_ini_config_str(
+    config: &str,
+    vec_eo: &mut Vec<EnvOptions>,
+    ro: &RunOptions,
+    fail_error: bool,
+    bytes: &mut u64,
+    ini_list: &mut HashMap<String, bool>,
+) -> Result<(), PleaseError> {
+    read_ini(config, vec_eo, ro, fail_error, "static", bytes, ini_list)
+}
+
+/// the checks read_ini makes of each key, for sections that were built
+/// without ini text
+pub fn check_env_options(item: &EnvOptions, ro: &RunOptions, config_path: &str) -> bool {
+    let mut faulty = false;
+    let section = &item.section;
+
+    if section.is_empty() {
+        report(&format!(
+            "Error parsing {}, section has no name",
+            config_path
+        ));
+        faulty = true;
+    }
+
+    // the values as they would be written in please.ini
+    let keys = [
+        ("name", item.name.clone()),
+        ("hostname", item.hostname.clone()),
+        ("target", item.target.clone()),
+        ("target_group", item.target_group.clone()),
+        ("rule", item.rule.clone()),
+        ("datematch", item.datematch.clone()),
+        ("dir", item.dir.clone()),
+        ("exact_dir", item.exact_dir.clone()),
+        ("editor", item.editor.clone()),
+        ("approval", item.approval.clone()),
+        (
+            "approval_timeout",
+            item.approval_timeout.map(|secs| secs.to_string()),
+        ),
+        ("from", item.from.clone()),
+        ("backup", item.backup.clone()),
+        ("backup_keep", item.backup_keep.map(|keep| keep.to_string())),
+        ("editor_default", item.editor_default.clone()),
+        (
+            "mkdir_mode",
+            item.mkdir_mode.map(|mode| format!("{:o}", mode)),
+        ),
+        ("template", item.template.clone()),
+        ("max_uses", item.max_uses.as_ref().map(|m| m.to_string())),
+    ];
+    for (key, value) in keys.iter() {
+        if let Some(value) = value {
+            if !check_key(key, value, ro, config_path, section, None) {
+                faulty = true;
             }
         }
     }
 
-    let byte_limit = 1024 * 1024 * 10;
-
-    if *bytes >= byte_limit {
-        println!("Exiting as too much config has already been read.");
-        std::process::exit(1);
+    if !section_ok(item, config_path) {
+        faulty = true;
     }
 
-    let mut s = String::new();
-    let reader = BufReader::new(file).take(byte_limit).read_to_string(&mut s);
+    !faulty
+}
 
-    match reader {
-        Ok(n) => {
-            *bytes += s.as_bytes().len() as u64;
-            if n >= byte_limit as usize {
-                println!("Exiting as too much config has already been read.");
-                std::process::exit(1);
-            }
+/// the formats a policy document can be written in
+#[cfg(feature = "serde")]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PolicyFormat {
+    Json,
+    Toml,
+}
+
+/// toml documents hold their sections in [[section]] tables
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TomlPolicy {
+    #[serde(default)]
+    section: Vec<EnvOptions>,
+}
+
+/// read the sections of a policy document, a json list or toml [[section]]
+/// tables, checking each as read_ini would
+#[cfg(feature = "serde")]
+pub fn read_policy_str(
+    data: &str,
+    format: PolicyFormat,
+    vec_eo: &mut Vec<EnvOptions>,
+    ro: &RunOptions,
+    config_path: &str,
+) -> Result<(), PleaseError> {
+    let sections = match format {
+        PolicyFormat::Json => {
+            serde_json::from_str::<Vec<EnvOptions>>(data).map_err(|e| e.to_string())
         }
+        PolicyFormat::Toml => toml::from_str::<TomlPolicy>(data)
+            .map(|policy| policy.section)
+            .map_err(|e| e.to_string()),
+    };
+    let sections = match sections {
+        Ok(sections) => sections,
         Err(why) => {
-            println!("Could not read {}: {}", display, why);
-            return true;
+            report(&format!("Error parsing {}: {}", config_path, why));
+            return Err(PleaseError::Config(config_path.to_string()));
+        }
+    };
+
+    let mut faulty = false;
+    let mut checked = vec![];
+    for mut item in sections {
+        if !check_env_options(&item, ro, config_path) {
+            faulty = true;
         }
+        item.file_name = config_path.to_string();
+        item.configured = true;
+        checked.push(item);
     }
 
-    read_ini(&s, vec_eo, ro, fail_error, config_path, bytes, ini_list)
+    if faulty {
+        return Err(PleaseError::Config(config_path.to_string()));
+    }
+    vec_eo.append(&mut checked);
+    Ok(())
 }
 
-pub fn read_ini_config_str(
-    config: &str,
+/// read a policy document, the format is taken from the .json or .toml
+/// extension
+#[cfg(feature = "serde")]
+pub fn read_policy_file(
+    config_path: &str,
     vec_eo: &mut Vec<EnvOptions>,
     ro: &RunOptions,
-    fail_error: bool,
-    bytes: &mut u64,
-    ini_list: &mut HashMap<String, bool>,
-) -> bool {
-    read_ini(config, vec_eo, ro, fail_error, "static", bytes, ini_list)
+) -> Result<(), PleaseError> {
+    let format = match Path::new(config_path).extension().and_then(|e| e.to_str()) {
+        Some("json") => PolicyFormat::Json,
+        Some("toml") => PolicyFormat::Toml,
+        _ => {
+            report(&format!(
+                "Error parsing {}, policy documents end .json or .toml",
+                config_path
+            ));
+            return Err(PleaseError::Config(config_path.to_string()));
+        }
+    };
+
+    match filesystem().read_config(config_path, 1024 * 1024 * 10) {
+        Ok(data) => read_policy_str(&data, format, vec_eo, ro, config_path),
+        Err(why) => {
+            report(&why);
+            Err(PleaseError::Config(config_path.to_string()))
+        }
+    }
 }
 
 /// may we execute with this hostname
@@ -878,7 +2121,10 @@ pub fn hostname_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> boo
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.hostname.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.hostname.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -894,12 +2140,125 @@ pub fn hostname_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> boo
     true
 }
 
-pub fn target_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bool {
-    if item.exact_target.is_some() {
-        let exact_target = item.exact_target.as_ref().unwrap();
-        if exact_target == &ro.target {
-            return true;
-        }
+/// parse an address or address/prefix. None if it does not look like an
+/// address, so should be treated as a regex, Err if the prefix is too long
+pub fn parse_cidr(source: &str) -> Option<Result<(IpAddr, u8), ()>> {
+    let (addr, prefix) = match source.find('/') {
+        Some(pos) => (&source[0..pos], Some(&source[pos + 1..])),
+        None => (source, None),
+    };
+
+    let addr: IpAddr = addr.parse().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+
+    let prefix = match prefix {
+        None => max,
+        Some(p) => match p.parse::<u8>() {
+            Ok(p) if p <= max => p,
+            _ => return Some(Err(())),
+        },
+    };
+
+    Some(Ok((addr, prefix)))
+}
+
+/// does addr fall within network/prefix
+pub fn cidr_match(network: &IpAddr, prefix: u8, addr: &IpAddr) -> bool {
+    let addr = match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) if network.is_ipv4() => IpAddr::V4(v4),
+            _ => *addr,
+        },
+        _ => *addr,
+    };
+
+    match (network, addr) {
+        (IpAddr::V4(n), IpAddr::V4(a)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(*n) & mask == u32::from(a) & mask
+        }
+        (IpAddr::V6(n), IpAddr::V6(a)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(*n) & mask == u128::from(a) & mask
+        }
+        _ => false,
+    }
+}
+
+/// may we execute from where the user is connected
+pub fn from_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bool {
+    let from = match &item.from {
+        Some(f) => f,
+        None => return true,
+    };
+
+    // a source that could not be found only matches an explicit wildcard
+    if ro.from == "unknown" {
+        return from.split(',').any(|source| source.trim() == ".*");
+    }
+
+    let addr: Option<IpAddr> = ro.from.parse().ok();
+
+    for source in from.split(',') {
+        let source = source.trim();
+        match parse_cidr(source) {
+            Some(Ok((network, prefix))) => {
+                if addr.is_some() && cidr_match(&network, prefix, addr.as_ref().unwrap()) {
+                    return true;
+                }
+            }
+            Some(Err(_)) => {}
+            None => {
+                let from_re = match regex_build(source, ro, &item.file_name, &item.section, line) {
+                    Some(check) => check,
+                    None => {
+                        report(&format!("Could not compile {}", source));
+                        return false;
+                    }
+                };
+
+                if from_re.is_match(&ro.from) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// may we execute with or without a tty
+pub fn tty_ok(item: &EnvOptions, ro: &RunOptions) -> bool {
+    match item.tty {
+        Some(TtyMode::Required) => ro.tty.is_some(),
+        Some(TtyMode::Forbidden) => ro.tty.is_none(),
+        _ => true,
+    }
+}
+
+/// when denied, explain if having (or not having) a tty would have permitted
+pub fn tty_explain(vec_eo: &[EnvOptions], ro: &RunOptions) -> Option<String> {
+    let mut other = ro.clone();
+    other.tty = match ro.tty {
+        Some(_) => None,
+        None => Some("/dev/tty".to_string()),
+    };
+
+    if !can(vec_eo, &mut other).permit() {
+        return None;
+    }
+
+    Some(match ro.tty {
+        Some(_) => "This may not be run from a terminal (tty=forbidden)".to_string(),
+        None => "This must be run from a terminal (tty=required)".to_string(),
+    })
+}
+
+pub fn target_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bool {
+    if item.exact_target.is_some() {
+        let exact_target = item.exact_target.as_ref().unwrap();
+        if exact_target == &ro.target {
+            return true;
+        }
 
         // println!("{}: target mismatch: {} != {}", item.section, exact_target, ro.target);
         return false;
@@ -915,7 +2274,10 @@ pub fn target_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bool
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.target.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.target.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -961,7 +2323,10 @@ pub fn target_group_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) ->
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.target_group.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.target_group.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -995,7 +2360,10 @@ pub fn rule_match(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bool
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.rule.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.rule.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -1040,7 +2408,10 @@ pub fn directory_check_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>)
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.dir.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.dir.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -1078,7 +2449,10 @@ pub fn environment_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) ->
     ) {
         Some(check) => check,
         None => {
-            println!("Could not compile {}", &item.env_permit.as_ref().unwrap());
+            report(&format!(
+                "Could not compile {}",
+                &item.env_permit.as_ref().unwrap()
+            ));
             return false;
         }
     };
@@ -1116,7 +2490,10 @@ pub fn permitted_dates_ok(item: &EnvOptions, ro: &RunOptions, line: Option<i32>)
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.datematch.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.datematch.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -1149,7 +2526,10 @@ pub fn name_matches(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> bo
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.name.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.name.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -1187,7 +2567,10 @@ pub fn group_matches(item: &EnvOptions, ro: &RunOptions, line: Option<i32>) -> b
         ) {
             Some(check) => check,
             None => {
-                println!("Could not compile {}", &item.name.as_ref().unwrap());
+                report(&format!(
+                    "Could not compile {}",
+                    &item.name.as_ref().unwrap()
+                ));
                 return false;
             }
         };
@@ -1225,6 +2608,16 @@ pub fn matching(item: &EnvOptions, ro: &mut RunOptions, line_error: Option<i32>)
         return false;
     }
 
+    if !from_ok(item, ro, line_error) {
+        // println!("from does not match");
+        return false;
+    }
+
+    if !tty_ok(item, ro) {
+        // println!("tty does not match");
+        return false;
+    }
+
     if !directory_check_ok(item, ro, line_error) {
         // println!("directory does not match");
         return false;
@@ -1309,11 +2702,54 @@ pub fn merge_default(default: &EnvOptions, item: &EnvOptions) -> EnvOptions {
         merged.exitcmd.clone_from(&default.exitcmd);
     }
 
+    if default.validate.is_some() && item.validate.is_none() {
+        merged.validate.clone_from(&default.validate);
+    }
+
+    if default.editor.is_some() && item.editor.is_none() {
+        merged.editor.clone_from(&default.editor);
+    }
+
+    if default.editor_default.is_some() && item.editor_default.is_none() {
+        merged.editor_default.clone_from(&default.editor_default);
+    }
+
+    if default.backup.is_some() && item.backup.is_none() {
+        merged.backup.clone_from(&default.backup);
+    }
+    if default.backup_keep.is_some() && item.backup_keep.is_none() {
+        merged.backup_keep = default.backup_keep;
+    }
+
     if default.edit_mode.is_some() && item.edit_mode.is_none() {
         // println!("merging edit_mode");
         merged.edit_mode.clone_from(&default.edit_mode);
     }
 
+    if default.edit_owner.is_some() && item.edit_owner.is_none() {
+        merged.edit_owner.clone_from(&default.edit_owner);
+    }
+
+    if default.create.is_some() && item.create.is_none() {
+        merged.create = default.create;
+    }
+
+    if default.template.is_some() && item.template.is_none() {
+        merged.template.clone_from(&default.template);
+    }
+
+    if default.mkdir.is_some() && item.mkdir.is_none() {
+        merged.mkdir = default.mkdir;
+    }
+
+    if default.mkdir_mode.is_some() && item.mkdir_mode.is_none() {
+        merged.mkdir_mode = default.mkdir_mode;
+    }
+
+    if default.mkdir_owner.is_some() && item.mkdir_owner.is_none() {
+        merged.mkdir_owner.clone_from(&default.mkdir_owner);
+    }
+
     if default.timeout.is_some() && item.timeout.is_none() {
         // println!("merging timeout");
         merged.timeout = default.timeout;
@@ -1344,6 +2780,49 @@ pub fn merge_default(default: &EnvOptions, item: &EnvOptions) -> EnvOptions {
         merged.token_timeout = default.token_timeout;
     }
 
+    if default.approval.is_some() && item.approval.is_none() {
+        merged.approval.clone_from(&default.approval);
+    }
+
+    if default.approval_timeout.is_some() && item.approval_timeout.is_none() {
+        merged.approval_timeout = default.approval_timeout;
+    }
+
+    if default.confirm.is_some() && item.confirm.is_none() {
+        merged.confirm = default.confirm;
+    }
+    if default.atomic.is_some() && item.atomic.is_none() {
+        merged.atomic = default.atomic;
+    }
+
+    if default.tty.is_some() && item.tty.is_none() {
+        merged.tty.clone_from(&default.tty);
+    }
+
+    if default.max_uses.is_some() && item.max_uses.is_none() {
+        merged.max_uses.clone_from(&default.max_uses);
+    }
+
+    if default.password_retries.is_some() && item.password_retries.is_none() {
+        merged.password_retries = default.password_retries;
+    }
+
+    if default.failure_delay.is_some() && item.failure_delay.is_none() {
+        merged.failure_delay = default.failure_delay;
+    }
+
+    if default.lockout.is_some() && item.lockout.is_none() {
+        merged.lockout = default.lockout;
+    }
+
+    if default.lockout_window.is_some() && item.lockout_window.is_none() {
+        merged.lockout_window = default.lockout_window;
+    }
+
+    if default.lockout_time.is_some() && item.lockout_time.is_none() {
+        merged.lockout_time = default.lockout_time;
+    }
+
     merged
 }
 
@@ -1383,64 +2862,3923 @@ pub fn can(vec_eo: &[EnvOptions], ro: &mut RunOptions) -> EnvOptions {
     opt
 }
 
-/// check reason. this happens post authorize in order to provide feedback
-pub fn reason_ok(item: &EnvOptions, ro: &RunOptions) -> bool {
-    if item.reason.is_none() {
-        return true;
-    }
+/// check reason. this happens post authorize in order to provide feedback
+pub fn reason_ok(item: &EnvOptions, ro: &RunOptions) -> bool {
+    if reason_matches(item, ro) {
+        return true;
+    }
+
+    match &item.reason.as_ref().unwrap() {
+        ReasonType::Text(_) => {
+            report(&format!(
+                "Sorry but there is no reason match to {} \"{}\" on {} as {}",
+                &ro.acl_type, &ro.command, &ro.hostname, &ro.target
+            ));
+
+            false
+        }
+        ReasonType::Need(_) => {
+            report(&format!(
+                "Sorry but no reason was given to {} \"{}\" on {} as {}",
+                &ro.acl_type,
+                if ro.acl_type == Acltype::List {
+                    &ro.target
+                } else {
+                    &ro.command
+                },
+                &ro.hostname,
+                &ro.target
+            ));
+            false
+        }
+    }
+}
+
+/// does the reason given in ro satisfy the section, without printing why not
+pub fn reason_matches(item: &EnvOptions, ro: &RunOptions) -> bool {
+    match &item.reason {
+        None => true,
+        Some(ReasonType::Text(value)) => {
+            let m_re = match regex_build(value, ro, &item.file_name, &item.section, None) {
+                Some(check) => check,
+                None => {
+                    report(&format!("Could not compile {}", &value));
+                    return false;
+                }
+            };
+
+            ro.reason.is_some() && m_re.is_match(ro.reason.as_ref().unwrap())
+        }
+        Some(ReasonType::Need(value)) => !value || ro.reason.is_some(),
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// split data into lines, keeping the new line so that a missing one at the
+/// end of the file is a difference
+fn diff_split_lines(data: &[u8]) -> Vec<&[u8]> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for (pos, c) in data.iter().enumerate() {
+        if *c == b'\n' {
+            lines.push(&data[start..pos + 1]);
+            start = pos + 1;
+        }
+    }
+    if start < data.len() {
+        lines.push(&data[start..]);
+    }
+    lines
+}
+
+/// past this many edits a range is given up on and replaced whole, which
+/// bounds the work spent on files that have little in common
+const DIFF_MAX_COST: usize = 1024;
+
+/// shortest edit script between a and b (Myers, in linear space)
+fn diff_ops(a: &[&[u8]], b: &[&[u8]]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    diff_range(a, b, &mut ops);
+    ops
+}
+
+/// append the edit script of a to b to ops, after trimming the lines that
+/// they start and end with
+fn diff_range(a: &[&[u8]], b: &[&[u8]], ops: &mut Vec<DiffOp>) {
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, prefix));
+
+    match diff_split(a_mid, b_mid) {
+        Some((x, y)) => {
+            diff_range(&a_mid[..x], &b_mid[..y], ops);
+            diff_range(&a_mid[x..], &b_mid[y..], ops);
+        }
+        None => {
+            ops.extend(std::iter::repeat_n(DiffOp::Delete, a_mid.len()));
+            ops.extend(std::iter::repeat_n(DiffOp::Insert, b_mid.len()));
+        }
+    }
+
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, suffix));
+}
+
+/// find where the forward and reverse searches meet on the shortest edit
+/// script, None if either side is empty or the search costs too much
+fn diff_split(a: &[&[u8]], b: &[&[u8]]) -> Option<(usize, usize)> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max_d = ((n + m + 1) / 2).min(DIFF_MAX_COST as isize);
+    let offset = max_d + 1;
+    let size = (2 * max_d + 3) as usize;
+    let mut forward = vec![-1isize; size];
+    let mut reverse = vec![-1isize; size];
+    forward[(offset + 1) as usize] = 0;
+    reverse[(offset + 1) as usize] = 0;
+
+    let delta = n - m;
+    // when the difference is odd the paths meet on a forward step
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    for d in 0..max_d {
+        for k1 in ((-d + k1_start)..=(d - k1_end)).step_by(2) {
+            let idx = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[idx - 1] < forward[idx + 1]) {
+                forward[idx + 1]
+            } else {
+                forward[idx - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[idx] = x1;
+
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2 = offset + delta - k1;
+                if k2 >= 0 && (k2 as usize) < size && reverse[k2 as usize] != -1 {
+                    let x2 = n - reverse[k2 as usize];
+                    if x1 >= x2 {
+                        return diff_split_point(a, b, x1, y1);
+                    }
+                }
+            }
+        }
+
+        for k2 in ((-d + k2_start)..=(d - k2_end)).step_by(2) {
+            let idx = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && reverse[idx - 1] < reverse[idx + 1]) {
+                reverse[idx + 1]
+            } else {
+                reverse[idx - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - x2 - 1) as usize] == b[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            reverse[idx] = x2;
+
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1 = offset + delta - k2;
+                if k1 >= 0 && (k1 as usize) < size && forward[k1 as usize] != -1 {
+                    let x1 = forward[k1 as usize];
+                    let y1 = offset + x1 - k1;
+                    if x1 >= n - x2 {
+                        return diff_split_point(a, b, x1, y1);
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// a split that leaves a range unchanged would never finish
+fn diff_split_point(a: &[&[u8]], b: &[&[u8]], x: isize, y: isize) -> Option<(usize, usize)> {
+    let (x, y) = (x as usize, y as usize);
+    if (x == 0 && y == 0) || (x == a.len() && y == b.len()) {
+        return None;
+    }
+    Some((x, y))
+}
+
+/// return a unified diff of old and new with three lines of context, empty
+/// if there is no difference
+pub fn unified_diff(old: &[u8], new: &[u8], old_name: &str, new_name: &str) -> String {
+    let context = 3;
+    let a = diff_split_lines(old);
+    let b = diff_split_lines(new);
+    let ops = diff_ops(&a, &b);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal))
+        .map(|(pos, _)| pos)
+        .collect();
+
+    if changes.is_empty() {
+        return "".to_string();
+    }
+
+    // line positions in a and b before each op
+    let mut positions = vec![];
+    let (mut a_pos, mut b_pos) = (0, 0);
+    for op in &ops {
+        positions.push((a_pos, b_pos));
+        match op {
+            DiffOp::Equal => {
+                a_pos += 1;
+                b_pos += 1;
+            }
+            DiffOp::Delete => a_pos += 1,
+            DiffOp::Insert => b_pos += 1,
+        }
+    }
+
+    // group changes that are close enough to share context
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for pos in changes {
+        let start = pos.saturating_sub(context);
+        let end = (pos + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let line = |prefix: char, data: &[u8]| -> String {
+        let text = String::from_utf8_lossy(data);
+        match text.strip_suffix('\n') {
+            Some(t) => format!("{}{}\n", prefix, t),
+            None => format!("{}{}\n\\ No newline at end of file\n", prefix, text),
+        }
+    };
+
+    for (start, end) in hunks {
+        let a_len = ops[start..end]
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert))
+            .count();
+        let b_len = ops[start..end]
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete))
+            .count();
+        let (a_start, b_start) = positions[start];
+
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            if a_len == 0 { a_start } else { a_start + 1 },
+            a_len,
+            if b_len == 0 { b_start } else { b_start + 1 },
+            b_len
+        ));
+
+        for (pos, op) in ops[start..end].iter().enumerate() {
+            let (a_pos, b_pos) = positions[start + pos];
+            out.push_str(&match op {
+                DiffOp::Equal => line(' ', b[b_pos]),
+                DiffOp::Delete => line('-', a[a_pos]),
+                DiffOp::Insert => line('+', b[b_pos]),
+            });
+        }
+    }
+
+    out
+}
+
+/// for each line of a, the line of b that it is kept as
+fn diff_matches(a: &[&[u8]], b: &[&[u8]]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; a.len()];
+    let (mut a_pos, mut b_pos) = (0, 0);
+    for op in diff_ops(a, b) {
+        match op {
+            DiffOp::Equal => {
+                matches[a_pos] = Some(b_pos);
+                a_pos += 1;
+                b_pos += 1;
+            }
+            DiffOp::Delete => a_pos += 1,
+            DiffOp::Insert => b_pos += 1,
+        }
+    }
+    matches
+}
+
+/// three way merge of ours and theirs, both changed from base. conflicting
+/// changes are written between diff3 style markers. return the merged data
+/// and the number of conflicts
+pub fn merge3(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    ours_name: &str,
+    theirs_name: &str,
+) -> (Vec<u8>, usize) {
+    let o = diff_split_lines(base);
+    let a = diff_split_lines(ours);
+    let b = diff_split_lines(theirs);
+    let ours_match = diff_matches(&o, &a);
+    let theirs_match = diff_matches(&o, &b);
+
+    let mut out: Vec<u8> = vec![];
+    let mut conflicts = 0;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+
+    let push_lines = |out: &mut Vec<u8>, lines: &[&[u8]], terminate: bool| {
+        for line in lines {
+            out.extend_from_slice(line);
+        }
+        if terminate && out.last().is_some() && out.last() != Some(&b'\n') {
+            out.push(b'\n');
+        }
+    };
+
+    loop {
+        // the next line of base that both sides kept
+        let stable = (i..o.len()).find(|x| ours_match[*x].is_some() && theirs_match[*x].is_some());
+        let (ie, je, ke) = match stable {
+            Some(x) => (x, ours_match[x].unwrap(), theirs_match[x].unwrap()),
+            None => (o.len(), a.len(), b.len()),
+        };
+
+        let base_chunk = &o[i..ie];
+        let ours_chunk = &a[j..je];
+        let theirs_chunk = &b[k..ke];
+
+        if ours_chunk == base_chunk {
+            push_lines(&mut out, theirs_chunk, false);
+        } else if theirs_chunk == base_chunk || ours_chunk == theirs_chunk {
+            push_lines(&mut out, ours_chunk, false);
+        } else {
+            conflicts += 1;
+            push_lines(&mut out, &[], true);
+            out.extend_from_slice(format!("<<<<<<< {}\n", ours_name).as_bytes());
+            push_lines(&mut out, ours_chunk, true);
+            out.extend_from_slice(b"||||||| original\n");
+            push_lines(&mut out, base_chunk, true);
+            out.extend_from_slice(b"=======\n");
+            push_lines(&mut out, theirs_chunk, true);
+            out.extend_from_slice(format!(">>>>>>> {}\n", theirs_name).as_bytes());
+        }
+
+        match stable {
+            Some(x) => {
+                out.extend_from_slice(o[x]);
+                i = x + 1;
+                j = je + 1;
+                k = ke + 1;
+            }
+            None => break,
+        }
+    }
+
+    (out, conflicts)
+}
+
+/// parse validate, the name of a built-in validator
+pub fn parse_validate(value: &str) -> Option<Validate> {
+    match value.to_lowercase().as_str() {
+        "ini" => Some(Validate::Ini),
+        "please" => Some(Validate::Please),
+        "json" => Some(Validate::Json),
+        "toml" => Some(Validate::Toml),
+        "yaml" | "yml" => Some(Validate::Yaml),
+        "sudoers" => Some(Validate::Sudoers),
+        "shell" | "sh" => Some(Validate::Shell),
+        _ => None,
+    }
+}
+
+/// data as text or an error naming the file type
+fn validate_text<'a>(data: &'a [u8], kind: &str) -> Result<&'a str, String> {
+    std::str::from_utf8(data).map_err(|x| format!("{} is not valid UTF-8: {}", kind, x))
+}
+
+/// check for [section] headers, key=value pairs, blank lines and comments
+pub fn validate_ini(data: &[u8]) -> Result<(), String> {
+    let text = validate_text(data, "ini")?;
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if !line.ends_with(']') || line.len() < 3 {
+                return Err(format!("line {}: bad section header {}", number + 1, line));
+            }
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, _)) if !key.trim().is_empty() => {}
+            _ => return Err(format!("line {}: expected key=value", number + 1)),
+        }
+    }
+
+    Ok(())
+}
+
+/// check data as a please.ini, every error is fatal. errors are printed by
+/// read_ini as they are found
+pub fn validate_please_ini(data: &[u8], ro: &RunOptions, config_path: &str) -> Result<(), String> {
+    let text = validate_text(data, "please.ini")?;
+    let mut vec_eo: Vec<EnvOptions> = vec![];
+    let mut bytes = 0;
+    let mut ini_list: HashMap<String, bool> = HashMap::new();
+
+    if read_ini(
+        text,
+        &mut vec_eo,
+        ro,
+        true,
+        config_path,
+        &mut bytes,
+        &mut ini_list,
+    )
+    .is_err()
+    {
+        return Err(format!("{} is not a valid please.ini", config_path));
+    }
+    Ok(())
+}
+
+pub fn validate_json(data: &[u8]) -> Result<(), String> {
+    serde_json::from_slice::<serde_json::Value>(data)
+        .map(|_| ())
+        .map_err(|x| format!("json: {}", x))
+}
+
+pub fn validate_toml(data: &[u8]) -> Result<(), String> {
+    toml::from_str::<toml::Value>(validate_text(data, "toml")?)
+        .map(|_| ())
+        .map_err(|x| format!("toml: {}", x))
+}
+
+pub fn validate_yaml(data: &[u8]) -> Result<(), String> {
+    yaml_rust::YamlLoader::load_from_str(validate_text(data, "yaml")?)
+        .map(|_| ())
+        .map_err(|x| format!("yaml: {}", x))
+}
+
+/// run a checking program with data on stdin, the output becomes the error
+fn validate_program(program: &str, args: &[&str], data: &[u8]) -> Result<(), String> {
+    let mut child = match process::Command::new(program)
+        .args(args)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(x) => return Err(format!("could not run {}: {}", program, x)),
+    };
+
+    // a checker that exits early closes stdin, the exit status still counts
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(data);
+    }
+
+    match child.wait_with_output() {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => Err(format!(
+            "{}{}",
+            String::from_utf8_lossy(&out.stdout),
+            String::from_utf8_lossy(&out.stderr)
+        )
+        .trim_end()
+        .to_string()),
+        Err(x) => Err(format!("could not run {}: {}", program, x)),
+    }
+}
+
+/// check data with the validator, config_path names the file in messages
+pub fn validate_data(
+    validate: &Validate,
+    data: &[u8],
+    ro: &RunOptions,
+    config_path: &str,
+) -> Result<(), String> {
+    match validate {
+        Validate::Ini => validate_ini(data),
+        Validate::Please => validate_please_ini(data, ro, config_path),
+        Validate::Json => validate_json(data),
+        Validate::Toml => validate_toml(data),
+        Validate::Yaml => validate_yaml(data),
+        Validate::Sudoers => validate_program("visudo", &["-c", "-q", "-f", "-"], data),
+        Validate::Shell => validate_program("/bin/sh", &["-n"], data),
+    }
+}
+
+/// join words into a command line that shell_split reads back as the same
+/// words, single quoting those that need it
+pub fn shell_join(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|word| {
+            if !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c))
+            {
+                word.clone()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// split a command line into words as a shell would, honouring single and
+/// double quotes and backslash escapes, but without any expansion
+pub fn shell_split(line: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if "\"\\$`".contains(c) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err("trailing backslash".to_string()),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// an edit file left behind by a pleaseedit session that did not finish
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LeftEdit {
+    pub path: String,
+    pub source: String,
+    pub modified: SystemTime,
+}
+
+/// is path a directory that only uid may use
+fn private_dir(path: &Path, uid: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match fs::symlink_metadata(path) {
+        Ok(m) => m.is_dir() && m.uid() == uid && m.mode() & 0o077 == 0,
+        Err(_) => false,
+    }
+}
+
+/// where edit session directories are made, XDG_RUNTIME_DIR when it is the
+/// user's own private directory, otherwise /tmp
+pub fn edit_session_base(xdg_runtime_dir: Option<&str>, uid: u32) -> String {
+    match xdg_runtime_dir {
+        Some(dir) if dir.starts_with('/') && private_dir(Path::new(dir), uid) => {
+            dir.trim_end_matches('/').to_string()
+        }
+        _ => "/tmp".to_string(),
+    }
+}
+
+/// the edit files left in uid's session directories under each base, oldest
+/// first
+pub fn find_left_edits(bases: &[String], service: &str, uid: u32) -> Vec<LeftEdit> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut left: Vec<LeftEdit> = vec![];
+    let prefix = format!("{}.", service);
+
+    for base in bases {
+        let entries = match fs::read_dir(base) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with(&prefix)
+                || !private_dir(&entry.path(), uid)
+            {
+                continue;
+            }
+
+            let files = match fs::read_dir(entry.path()) {
+                Ok(files) => files,
+                Err(_) => continue,
+            };
+
+            for file in files.flatten() {
+                let m = match fs::symlink_metadata(file.path()) {
+                    Ok(m) => m,
+                    Err(_) => continue,
+                };
+                if !m.is_file() || m.uid() != uid {
+                    continue;
+                }
+
+                left.push(LeftEdit {
+                    path: file.path().to_string_lossy().to_string(),
+                    source: unescape_path(&file.file_name().to_string_lossy()),
+                    modified: m.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+            }
+        }
+    }
+
+    left.sort_by_key(|edit| edit.modified);
+    left
+}
+
+/// the config file that pleaseedit --policy edits and checks
+pub const CONFIG_FILE: &str = "/etc/please.ini";
+
+/// lock held while the policy is edited
+pub fn policy_lock_path() -> String {
+    "/var/run/please/policy.lock".to_string()
+}
+
+/// take the policy edit lock, None if someone else holds it
+pub fn policy_lock() -> Option<nix::fcntl::Flock<File>> {
+    let old_mode = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o077).unwrap());
+    let lock_path = policy_lock_path();
+    let lock_dir = Path::new(&lock_path).parent().unwrap();
+    if !lock_dir.is_dir() && fs::create_dir_all(lock_dir).is_err() {
+        nix::sys::stat::umask(old_mode);
+        report(&format!("Could not create {}", lock_dir.display()));
+        return None;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    let file = options.open(&lock_path);
+    nix::sys::stat::umask(old_mode);
+
+    let file = match file {
+        Ok(f) => f,
+        Err(x) => {
+            report(&format!("Could not open {}: {}", lock_path, x));
+            return None;
+        }
+    };
+
+    match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockExclusiveNonblock) {
+        Ok(f) => Some(f),
+        Err(_) => {
+            report("The policy is already being edited by someone else");
+            None
+        }
+    }
+}
+
+/// read the whole policy from config_path with data in place of edited,
+/// every error is fatal
+pub fn policy_check(
+    config_path: &str,
+    edited: &str,
+    data: &[u8],
+    ro: &RunOptions,
+) -> Result<Vec<EnvOptions>, String> {
+    let text = validate_text(data, edited)?;
+
+    let mut vec_eo: Vec<EnvOptions> = vec![];
+    let mut bytes = 0;
+    let mut ini_list: HashMap<String, bool> = HashMap::new();
+    let mut read = ConfigRead {
+        bytes: &mut bytes,
+        ini_list: &mut ini_list,
+        candidate: Some((edited, text)),
+    };
+    if read_config(config_path, &mut vec_eo, ro, true, &mut read).is_err() {
+        return Err(format!("{} would not be a valid policy", config_path));
+    }
+
+    if !ini_list.contains_key(edited) {
+        return Err(format!(
+            "{} would no longer be included by {}",
+            edited, config_path
+        ));
+    }
+
+    Ok(vec_eo)
+}
+
+/// would the user still be permitted to edit the file under this policy
+pub fn policy_keeps_access(vec_eo: &[EnvOptions], ro: &RunOptions, edited: &str) -> bool {
+    let mut ro = ro.clone();
+    ro.acl_type = Acltype::Edit;
+    ro.new_args = vec![edited.to_string()];
+    ro.command = edited.to_string();
+    can(vec_eo, &mut ro).permit()
+}
+
+/// a problem found in a section by lint
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LintWarning {
+    pub file_name: String,
+    pub section: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: [{}] {}", self.file_name, self.section, self.message)
+    }
+}
+
+/// the section without its name and location, for finding duplicates
+fn lint_key(item: &EnvOptions) -> String {
+    let mut key = item.clone();
+    key.section = String::new();
+    key.file_name = String::new();
+    key.env_assign = None;
+    format!("{:?}", key)
+}
+
+/// why a rule is broader than it may look, if it is
+fn lint_broad_rule(rule: &str) -> Option<String> {
+    if regex_universal(rule) {
+        return Some(format!("rule {} matches anything", rule));
+    }
+    if rule.starts_with(".*") || rule.starts_with("^.*") {
+        return Some(format!(
+            "rule {} begins with .* so matches any prefix",
+            rule
+        ));
+    }
+    if rule.ends_with(".*") || rule.ends_with(".*$") {
+        return Some(format!("rule {} ends with .* so matches any suffix", rule));
+    }
+    None
+}
+
+/// why a search_path directory is unsafe, if it is
+fn lint_search_dir(dir: &str) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    if !dir.starts_with('/') {
+        return Some(format!("search_path entry {} is not absolute", dir));
+    }
+    match fs::metadata(dir) {
+        Err(_) => Some(format!("search_path entry {} does not exist", dir)),
+        Ok(m) if m.uid() != 0 => Some(format!("search_path entry {} is not owned by root", dir)),
+        Ok(m) if m.mode() & 0o022 != 0 => Some(format!(
+            "search_path entry {} is writable by group or other",
+            dir
+        )),
+        Ok(_) => None,
+    }
+}
+
+/// why the exitcmd program cannot be run, if it cannot
+fn lint_exitcmd(exitcmd: &str) -> Option<String> {
+    let program = match shell_split(exitcmd) {
+        Ok(args) if !args.is_empty() => args[0].clone(),
+        _ => return Some(format!("exitcmd {} cannot be parsed", exitcmd)),
+    };
+
+    let found = if program.contains('/') {
+        Path::new(&program).is_file()
+    } else {
+        DEFAULT_SEARCH_PATH
+            .split(':')
+            .any(|dir| Path::new(&format!("{}/{}", dir, program)).is_file())
+    };
+
+    if found {
+        None
+    } else {
+        Some(format!("exitcmd program {} does not exist", program))
+    }
+}
+
+/// look for sections that are dead, duplicated, shadowed or broader than
+/// intended
+pub fn lint(vec_eo: &[EnvOptions], now: NaiveDateTime) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+
+    for (pos, item) in vec_eo.iter().enumerate() {
+        let mut messages = vec![];
+
+        if let Some(notafter) = item.notafter {
+            if notafter < now {
+                messages.push(format!("expired, notafter {} has passed", notafter));
+            }
+        }
+
+        let key = lint_key(item);
+        if let Some(first) = vec_eo[..pos]
+            .iter()
+            .find(|earlier| lint_key(earlier) == key && earlier.env_assign == item.env_assign)
+        {
+            messages.push(format!("duplicate of [{}]", first.section));
+        } else if let Some(wide) = vec_eo[..pos]
+            .iter()
+            .find(|earlier| section_shadows(earlier, item))
+        {
+            messages.push(format!(
+                "never reached, [{}] has last=true and always matches first",
+                wide.section
+            ));
+        }
+
+        if item.permit() && item.exact_rule.is_none() {
+            if let Some(message) = item.rule.as_deref().and_then(lint_broad_rule) {
+                messages.push(message);
+            }
+        }
+
+        if item.env_permit.as_deref().is_some_and(regex_universal) {
+            messages.push("permit_env allows any environment variable".to_string());
+        }
+
+        if let Some(search_path) = &item.search_path {
+            for dir in search_path.split(':').filter(|dir| !dir.trim().is_empty()) {
+                if let Some(message) = lint_search_dir(dir.trim()) {
+                    messages.push(message);
+                }
+            }
+        }
+
+        if let Some(message) = item.exitcmd.as_deref().and_then(lint_exitcmd) {
+            messages.push(message);
+        }
+
+        for message in messages {
+            warnings.push(LintWarning {
+                file_name: item.file_name.clone(),
+                section: item.section.clone(),
+                message,
+            });
+        }
+    }
+
+    warnings
+}
+
+/// how a change to a policy affects who may do what
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AccessChange {
+    Broadens,
+    MayBroaden,
+    Narrows,
+    Neutral,
+}
+
+impl fmt::Display for AccessChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AccessChange::Broadens => write!(f, " (broadens access)"),
+            AccessChange::MayBroaden => write!(f, " (may broaden access)"),
+            AccessChange::Narrows => write!(f, " (narrows access)"),
+            AccessChange::Neutral => Ok(()),
+        }
+    }
+}
+
+/// a key whose normalised value differs between two versions of a section
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub access: AccessChange,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SectionChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// a section that was added, removed or changed between two policies
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SectionChange {
+    pub section: String,
+    pub kind: SectionChangeKind,
+    pub access: AccessChange,
+    pub keys: Vec<KeyChange>,
+}
+
+/// the text a regex matches when it has no special characters
+fn regex_literal(regex: &str) -> Option<String> {
+    let mut literal = String::new();
+    let mut chars = regex.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if !escaped.is_alphanumeric() => literal.push(escaped),
+                _ => return None,
+            },
+            '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return None
+            }
+            _ => literal.push(c),
+        }
+    }
+
+    Some(literal)
+}
+
+/// a regex or exact value in one form, quoted when it matches only itself.
+/// rules are always anchored so anchors are dropped
+fn diff_pattern(regex: &Option<String>, exact: &Option<String>) -> Option<String> {
+    if let Some(exact) = exact {
+        return Some(format!("\"{}\"", exact));
+    }
+
+    let regex = regex.as_ref()?;
+    let mut regex = regex.strip_prefix('^').unwrap_or(regex);
+    if regex.ends_with('$') && !regex.ends_with("\\$") {
+        regex = &regex[..regex.len() - 1];
+    }
+
+    Some(match regex_literal(regex) {
+        Some(literal) => format!("\"{}\"", literal),
+        None => regex.to_string(),
+    })
+}
+
+/// does the wide diff_pattern match everything that the narrow one does
+fn pattern_covers(wide: &str, narrow: &str) -> bool {
+    if wide == narrow || regex_universal(wide) {
+        return true;
+    }
+    if wide.starts_with('"') {
+        return false;
+    }
+
+    match narrow.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(literal) => match Regex::new(&format!("^{}$", wide)) {
+            Ok(rule) => rule.is_match(literal),
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+/// judge a change to a pattern key. an unset key matches anything when
+/// none_is_any, otherwise nothing
+fn pattern_change(old: Option<&str>, new: Option<&str>, none_is_any: bool) -> AccessChange {
+    let covers = |wide: Option<&str>, narrow: Option<&str>| match (wide, narrow) {
+        (None, _) => none_is_any,
+        (Some(_), None) if !none_is_any => true,
+        (Some(wide), None) => regex_universal(wide),
+        (Some(wide), Some(narrow)) => pattern_covers(wide, narrow),
+    };
+
+    match (covers(new, old), covers(old, new)) {
+        (true, false) => AccessChange::Broadens,
+        (false, true) => AccessChange::Narrows,
+        (true, true) => AccessChange::Neutral,
+        (false, false) => AccessChange::MayBroaden,
+    }
+}
+
+/// judge a change to a key that limits when a section applies, so removing
+/// it broadens access
+fn restriction_change(old: Option<&str>, new: Option<&str>) -> AccessChange {
+    match (old, new) {
+        (_, None) => AccessChange::Broadens,
+        (None, _) => AccessChange::Narrows,
+        _ => AccessChange::MayBroaden,
+    }
+}
+
+/// judge a change to a boolean that grants more when true
+fn grant_change(new: Option<&str>) -> AccessChange {
+    if new == Some("true") {
+        AccessChange::Broadens
+    } else {
+        AccessChange::Narrows
+    }
+}
+
+/// how the change of a normalised key affects access
+fn key_change_access(key: &str, old: Option<&str>, new: Option<&str>) -> AccessChange {
+    match key {
+        "name" | "rule" | "target" | "dir" | "permit_env" => pattern_change(old, new, false),
+        "hostname" | "editor" => pattern_change(old, new, true),
+        "target_group" if old.is_some() && new.is_some() => pattern_change(old, new, false),
+        "permit" | "create" | "mkdir" => grant_change(new),
+        "require_pass" | "confirm" => grant_change(old),
+        "notbefore" => match (old, new) {
+            (Some(old), Some(new)) if new > old => AccessChange::Narrows,
+            (None, Some(_)) => AccessChange::Narrows,
+            _ => AccessChange::Broadens,
+        },
+        "notafter" => match (old, new) {
+            (Some(old), Some(new)) if new < old => AccessChange::Narrows,
+            (None, Some(_)) => AccessChange::Narrows,
+            _ => AccessChange::Broadens,
+        },
+        "reason" => match (old, new) {
+            (Some("required"), Some(_)) => AccessChange::Narrows,
+            (Some(_), Some("required")) => AccessChange::Broadens,
+            (Some(_), Some(_)) => pattern_change(old, new, false),
+            _ => restriction_change(old, new),
+        },
+        "exitcmd" | "validate" | "approval" | "datematch" | "from" | "max_uses" | "tty"
+        | "lockout" => restriction_change(old, new),
+        "type" | "group" | "last" | "target_group" | "search_path" => AccessChange::MayBroaden,
+        _ => AccessChange::Neutral,
+    }
+}
+
+/// the keys of a section in a normalised form, exact values and regex are
+/// written alike, defaults are filled in and modes are octal
+pub fn normalised_section(item: &EnvOptions) -> Vec<(String, String)> {
+    let mut keys: Vec<(&str, Option<String>)> = vec![
+        ("type", Some(item.acl_type.to_string())),
+        ("group", Some(item.group.to_string())),
+        ("name", diff_pattern(&item.name, &item.exact_name)),
+        ("rule", diff_pattern(&item.rule, &item.exact_rule)),
+        ("target", diff_pattern(&item.target, &item.exact_target)),
+        (
+            "target_group",
+            diff_pattern(&item.target_group, &item.exact_target_group),
+        ),
+        (
+            "hostname",
+            diff_pattern(&item.hostname, &item.exact_hostname),
+        ),
+        ("dir", diff_pattern(&item.dir, &item.exact_dir)),
+        ("permit", Some(item.permit().to_string())),
+        ("require_pass", Some(item.require_pass().to_string())),
+        ("last", Some((item.last == Some(true)).to_string())),
+        ("notbefore", item.notbefore.map(|d| d.to_string())),
+        ("notafter", item.notafter.map(|d| d.to_string())),
+        ("datematch", item.datematch.clone()),
+        (
+            "reason",
+            match &item.reason {
+                None | Some(ReasonType::Need(false)) => None,
+                Some(ReasonType::Need(true)) => Some("required".to_string()),
+                Some(ReasonType::Text(rule)) => diff_pattern(&Some(rule.clone()), &None),
+            },
+        ),
+        ("permit_env", diff_pattern(&item.env_permit, &None)),
+        ("from", item.from.clone()),
+        ("tty", item.tty.as_ref().map(|t| t.to_string())),
+        ("approval", item.approval.clone()),
+        ("max_uses", item.max_uses.as_ref().map(|m| m.to_string())),
+        ("confirm", item.confirm.map(|c| c.to_string())),
+        ("search_path", item.search_path.clone()),
+        ("exitcmd", item.exitcmd.clone()),
+        ("validate", item.validate.as_ref().map(|v| v.to_string())),
+        ("editor", diff_pattern(&item.editor, &None)),
+        ("editor_default", item.editor_default.clone()),
+        (
+            "editmode",
+            item.edit_mode.as_ref().map(|mode| match mode {
+                EditMode::Mode(mode) => format!("{:04o}", mode),
+                EditMode::Keep(_) => "keep".to_string(),
+            }),
+        ),
+        ("editowner", item.edit_owner.as_ref().map(|o| o.to_string())),
+        ("create", Some((item.create != Some(false)).to_string())),
+        ("template", item.template.clone()),
+        ("mkdir", Some((item.mkdir == Some(true)).to_string())),
+        ("mkdir_mode", item.mkdir_mode.map(|m| format!("{:04o}", m))),
+        (
+            "mkdir_owner",
+            item.mkdir_owner.as_ref().map(|o| o.to_string()),
+        ),
+        ("backup", item.backup.clone()),
+        ("backup_keep", item.backup_keep.map(|b| b.to_string())),
+        ("atomic", item.atomic.map(|a| a.to_string())),
+        ("syslog", item.syslog.map(|s| s.to_string())),
+        ("timeout", item.timeout.map(|t| t.to_string())),
+        ("token_timeout", item.token_timeout.map(|t| t.to_string())),
+        (
+            "approval_timeout",
+            item.approval_timeout.map(|t| t.to_string()),
+        ),
+        (
+            "password_retries",
+            item.password_retries.map(|p| p.to_string()),
+        ),
+        ("failure_delay", item.failure_delay.map(|f| f.to_string())),
+        ("lockout", item.lockout.map(|l| l.to_string())),
+        ("lockout_window", item.lockout_window.map(|l| l.to_string())),
+        ("lockout_time", item.lockout_time.map(|l| l.to_string())),
+    ];
+
+    // keys that do nothing for the other types are left out
+    if item.acl_type != Acltype::Edit {
+        keys.retain(|(key, _)| {
+            !matches!(
+                *key,
+                "exitcmd"
+                    | "validate"
+                    | "editor"
+                    | "editor_default"
+                    | "editmode"
+                    | "editowner"
+                    | "create"
+                    | "template"
+                    | "mkdir"
+                    | "mkdir_mode"
+                    | "mkdir_owner"
+                    | "backup"
+                    | "backup_keep"
+                    | "atomic"
+            )
+        });
+    }
+    if item.tty == Some(TtyMode::Any) {
+        keys.retain(|(key, _)| *key != "tty");
+    }
+
+    let mut normalised: Vec<(String, String)> = keys
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key.to_string(), value)))
+        .collect();
+
+    if let Some(env_assign) = &item.env_assign {
+        let mut assigned: Vec<(&String, &String)> = env_assign.iter().collect();
+        assigned.sort();
+        for (key, value) in assigned {
+            normalised.push((format!("env_assign.{}", key), value.clone()));
+        }
+    }
+
+    normalised
+}
+
+/// the sections of a policy by name, with the default sections that apply
+/// to the same type and name merged in. a repeated name is numbered
+fn diff_sections(vec_eo: &[EnvOptions]) -> Vec<(String, EnvOptions)> {
+    let mut sections: Vec<(String, EnvOptions)> = vec![];
+    let mut defaults: Vec<&EnvOptions> = vec![];
+    let mut seen: HashMap<String, u32> = HashMap::new();
+
+    for item in vec_eo {
+        let name = diff_pattern(&item.name, &item.exact_name);
+        let mut default = EnvOptions::new();
+        for earlier in &defaults {
+            let earlier_name = diff_pattern(&earlier.name, &earlier.exact_name);
+            if earlier.acl_type == item.acl_type
+                && earlier.group == item.group
+                && match (&earlier_name, &name) {
+                    (Some(wide), Some(narrow)) => pattern_covers(wide, narrow),
+                    _ => false,
+                }
+            {
+                default = merge_default(&default, earlier);
+            }
+        }
+
+        let count = seen.entry(item.section.clone()).or_insert(0);
+        *count += 1;
+        let section = if *count == 1 {
+            item.section.clone()
+        } else {
+            format!("{} #{}", item.section, count)
+        };
+
+        if item.section.starts_with("default") {
+            sections.push((section, item.clone()));
+            defaults.push(item);
+        } else {
+            sections.push((section, merge_default(&default, item)));
+        }
+    }
+
+    sections
+}
+
+/// the widest of the access changes
+fn widest_change(changes: impl Iterator<Item = AccessChange>) -> AccessChange {
+    let mut widest = AccessChange::Neutral;
+    for change in changes {
+        widest = match (widest, change) {
+            (AccessChange::Broadens, _) | (_, AccessChange::Broadens) => AccessChange::Broadens,
+            (AccessChange::MayBroaden, _) | (_, AccessChange::MayBroaden) => {
+                AccessChange::MayBroaden
+            }
+            (AccessChange::Narrows, _) | (_, AccessChange::Narrows) => AccessChange::Narrows,
+            _ => AccessChange::Neutral,
+        };
+    }
+    widest
+}
+
+/// compare two policies section by section
+pub fn diff_policy(old: &[EnvOptions], new: &[EnvOptions]) -> Vec<SectionChange> {
+    let old_sections = diff_sections(old);
+    let new_sections = diff_sections(new);
+    let mut changes = vec![];
+
+    let listed = |item: &EnvOptions| -> Vec<KeyChange> {
+        normalised_section(item)
+            .into_iter()
+            .map(|(key, value)| KeyChange {
+                key,
+                old: None,
+                new: Some(value),
+                access: AccessChange::Neutral,
+            })
+            .collect()
+    };
+
+    for (section, old_item) in &old_sections {
+        if new_sections.iter().any(|(s, _)| s == section) {
+            continue;
+        }
+        changes.push(SectionChange {
+            section: section.clone(),
+            kind: SectionChangeKind::Removed,
+            access: if old_item.permit() {
+                AccessChange::Narrows
+            } else {
+                AccessChange::Broadens
+            },
+            keys: listed(old_item)
+                .into_iter()
+                .map(|k| KeyChange {
+                    old: k.new,
+                    new: None,
+                    ..k
+                })
+                .collect(),
+        });
+    }
+
+    for (section, new_item) in &new_sections {
+        let old_item = match old_sections.iter().find(|(s, _)| s == section) {
+            Some((_, old_item)) => old_item,
+            None => {
+                changes.push(SectionChange {
+                    section: section.clone(),
+                    kind: SectionChangeKind::Added,
+                    access: if new_item.permit() {
+                        AccessChange::Broadens
+                    } else {
+                        AccessChange::Narrows
+                    },
+                    keys: listed(new_item),
+                });
+                continue;
+            }
+        };
+
+        let old_keys = normalised_section(old_item);
+        let new_keys = normalised_section(new_item);
+        let mut names: Vec<&String> = old_keys.iter().map(|(k, _)| k).collect();
+        for (key, _) in &new_keys {
+            if !names.contains(&key) {
+                names.push(key);
+            }
+        }
+
+        let mut keys = vec![];
+        for key in names {
+            let old_value = old_keys.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let new_value = new_keys.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            if old_value == new_value {
+                continue;
+            }
+
+            keys.push(KeyChange {
+                key: key.clone(),
+                old: old_value.cloned(),
+                new: new_value.cloned(),
+                access: key_change_access(
+                    key,
+                    old_value.map(|v| v.as_str()),
+                    new_value.map(|v| v.as_str()),
+                ),
+            });
+        }
+
+        if keys.is_empty() {
+            continue;
+        }
+        changes.push(SectionChange {
+            section: section.clone(),
+            kind: SectionChangeKind::Changed,
+            access: widest_change(keys.iter().map(|k| k.access)),
+            keys,
+        });
+    }
+
+    changes
+}
+
+/// describe the changes between two policies
+pub fn produce_diff(changes: &[SectionChange]) -> Vec<String> {
+    let mut lines = vec![];
+
+    for change in changes {
+        let kind = match change.kind {
+            SectionChangeKind::Added => "added",
+            SectionChangeKind::Removed => "removed",
+            SectionChangeKind::Changed => "changed",
+        };
+        lines.push(format!("{} [{}]{}", kind, change.section, change.access));
+
+        for key in &change.keys {
+            lines.push(match (&key.old, &key.new) {
+                (None, Some(new)) if change.kind == SectionChangeKind::Added => {
+                    format!("    {} = {}", key.key, new)
+                }
+                (Some(old), None) if change.kind == SectionChangeKind::Removed => {
+                    format!("    {} = {}", key.key, old)
+                }
+                (old, new) => format!(
+                    "    {}: {} -> {}{}",
+                    key.key,
+                    old.as_deref().unwrap_or("(unset)"),
+                    new.as_deref().unwrap_or("(unset)"),
+                    key.access
+                ),
+            });
+        }
+    }
+
+    lines
+}
+
+/// a request read back from a log_action line or a json audit record
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LoggedRequest {
+    pub action: String,
+    pub user: String,
+    pub target: String,
+    pub acl_type: Acltype,
+    pub reason: Option<String>,
+    pub args: Vec<String>,
+    pub target_group: Option<String>,
+    pub directory: Option<String>,
+    pub allow_env: Option<Vec<String>>,
+    pub hostname: Option<String>,
+    pub date: Option<NaiveDateTime>,
+    pub groups: Option<Vec<String>>,
+    /// the tty as logged, no_tty when there was none
+    pub tty: Option<String>,
+    /// where the request came from, only json records give it
+    pub from: Option<String>,
+}
+
+/// the actions whose log lines record a decision worth replaying
+pub const REPLAY_ACTIONS: [&str; 3] = ["permit", "deny", "reason_fail"];
+
+/// parse the key="value" pairs that log_action writes
+pub fn parse_log_fields(line: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let start = pos;
+        while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_') {
+            pos += 1;
+        }
+
+        if pos == start || pos + 1 >= chars.len() || chars[pos] != '=' || chars[pos + 1] != '"' {
+            pos = start + 1;
+            continue;
+        }
+
+        let key: String = chars[start..pos].iter().collect();
+        pos += 2;
+
+        let mut value = String::new();
+        while pos < chars.len() && chars[pos] != '"' {
+            if chars[pos] == '\\' && pos + 1 < chars.len() && chars[pos + 1] == '"' {
+                pos += 1;
+            }
+            value.push(chars[pos]);
+            pos += 1;
+        }
+        pos += 1;
+
+        fields.insert(key, value);
+    }
+
+    fields
+}
+
+/// the hostname and time from the syslog prefix of a line, the year is used
+/// when the timestamp has none
+fn parse_syslog_prefix(prefix: &str, year: i32) -> (Option<String>, Option<NaiveDateTime>) {
+    let tokens: Vec<&str> = prefix.split_whitespace().collect();
+    let process = match tokens.iter().rposition(|t| t.ends_with(':')) {
+        Some(process) if process > 0 => process,
+        _ => return (None, None),
+    };
+
+    let hostname = tokens[process - 1].to_string();
+    let stamp = tokens[..process - 1].join(" ");
+
+    // dates are compared in UTC, a stamp without an offset is local time
+    let date = if let Ok(date) = chrono::DateTime::parse_from_rfc3339(&stamp) {
+        Some(date.naive_utc())
+    } else {
+        NaiveDateTime::parse_from_str(&format!("{} {}", year, stamp), "%Y %b %d %H:%M:%S")
+            .ok()
+            .and_then(|date| chrono::Local.from_local_datetime(&date).earliest())
+            .map(|date| date.naive_utc())
+    };
+
+    (Some(hostname), date)
+}
+
+/// split a logged command into the arguments and the options that matter to
+/// a decision
+fn parse_logged_command(command: &str, request: &mut LoggedRequest) {
+    const VALUE_SHORT: &str = "acdgrtu";
+    const VALUE_LONG: [&str; 11] = [
+        "allowenv", "approve", "check", "dir", "format", "group", "lint", "reason", "reject",
+        "target", "user",
+    ];
+
+    // commands are logged joined by spaces. a command that shell_join quoted
+    // reads back to exactly the line it came from, so is split as a shell would
+    let words: Vec<String> = match shell_split(command) {
+        Ok(words) if command.contains('\'') && shell_join(&words) == command => words,
+        _ => command.split_whitespace().map(|w| w.to_string()).collect(),
+    };
+    let mut pos = 0;
+    if words
+        .first()
+        .is_some_and(|w| w.rsplit('/').next().unwrap().starts_with("please"))
+    {
+        pos = 1;
+    }
+
+    while pos < words.len() {
+        let word = words[pos].as_str();
+        let (option, inline) = if word == "--" {
+            pos += 1;
+            break;
+        } else if let Some(long) = word.strip_prefix("--") {
+            match long.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (long.to_string(), None),
+            }
+        } else if word.starts_with('-') && word.len() > 1 {
+            let short = word[1..2].to_string();
+            let rest = &word[2..];
+            (
+                short,
+                if rest.is_empty() {
+                    None
+                } else {
+                    Some(rest.to_string())
+                },
+            )
+        } else {
+            break;
+        };
+        pos += 1;
+
+        let takes_value = if option.len() == 1 {
+            VALUE_SHORT.contains(option.as_str())
+        } else {
+            VALUE_LONG.contains(&option.as_str())
+        };
+        if !takes_value {
+            continue;
+        }
+
+        let value = match inline {
+            Some(value) => value,
+            None if pos < words.len() => {
+                pos += 1;
+                words[pos - 1].clone()
+            }
+            None => break,
+        };
+
+        match option.as_str() {
+            "d" | "dir" => request.directory = Some(value),
+            "g" | "group" => request.target_group = Some(value),
+            "r" | "reason" if request.reason.is_none() => request.reason = Some(value),
+            "a" | "allowenv" => {
+                request.allow_env = Some(
+                    value
+                        .split(',')
+                        .filter(|v| !v.trim().is_empty())
+                        .map(|v| v.to_string())
+                        .collect(),
+                )
+            }
+            _ => {}
+        }
+    }
+
+    request.args = words[pos..].to_vec();
+}
+
+/// read a request from a log_action syslog line or a json audit record, the
+/// year is used for syslog timestamps without one
+pub fn parse_logged_request(line: &str, year: i32) -> Option<LoggedRequest> {
+    let line = line.trim();
+    let mut fields: HashMap<String, String>;
+    let hostname;
+    let date;
+    let mut groups = None;
+
+    if line.starts_with('{') {
+        let json: serde_json::Value = serde_json::from_str(line).ok()?;
+        fields = HashMap::new();
+        for (key, value) in json.as_object()? {
+            if let Some(value) = value.as_str() {
+                fields.insert(key.clone(), value.to_string());
+            }
+        }
+
+        hostname = fields.get("hostname").cloned();
+        date = fields.get("date").and_then(|d| {
+            chrono::DateTime::parse_from_rfc3339(d)
+                .map(|d| d.naive_utc())
+                .or_else(|_| NaiveDateTime::parse_from_str(d, "%Y-%m-%d %H:%M:%S"))
+                .ok()
+        });
+        groups = json.get("groups").and_then(|g| g.as_array()).map(|g| {
+            g.iter()
+                .filter_map(|g| g.as_str())
+                .map(|g| g.to_string())
+                .collect()
+        });
+    } else {
+        let start = line.find("user=\"")?;
+        fields = parse_log_fields(&line[start..]);
+        (hostname, date) = parse_syslog_prefix(&line[..start], year);
+    }
+
+    let acl_type = match fields.get("type")?.as_str() {
+        "run" => Acltype::Run,
+        "edit" => Acltype::Edit,
+        "list" => Acltype::List,
+        _ => return None,
+    };
+
+    let mut request = LoggedRequest {
+        action: fields.remove("action").unwrap_or_default(),
+        user: fields.remove("user")?,
+        target: fields.remove("target").unwrap_or_default(),
+        acl_type,
+        reason: fields.remove("reason").filter(|r| !r.is_empty()),
+        args: vec![],
+        target_group: None,
+        directory: None,
+        allow_env: None,
+        hostname,
+        date,
+        groups,
+        tty: fields.remove("tty"),
+        from: fields.remove("from"),
+    };
+    parse_logged_command(&fields.remove("command").unwrap_or_default(), &mut request);
+
+    Some(request)
+}
+
+/// the RunOptions to replay a logged request with. pleaseedit requests make
+/// one for each file
+pub fn logged_request_ro(
+    request: &LoggedRequest,
+    groups: &HashMap<String, u32>,
+) -> Vec<RunOptions> {
+    let mut ro = RunOptions::new();
+    ro.name.clone_from(&request.user);
+    ro.target.clone_from(&request.target);
+    ro.acl_type = request.acl_type.clone();
+    ro.reason.clone_from(&request.reason);
+    ro.target_group.clone_from(&request.target_group);
+    ro.directory.clone_from(&request.directory);
+    ro.allow_env_list.clone_from(&request.allow_env);
+    ro.groups.clone_from(groups);
+    ro.syslog = false;
+    if let Some(hostname) = &request.hostname {
+        ro.hostname.clone_from(hostname);
+    }
+    if let Some(date) = request.date {
+        ro.date = date;
+    }
+
+    // not the tty or source of the host replaying the request
+    ro.tty = request.tty.clone().filter(|tty| tty != "no_tty");
+    ro.from = request
+        .from
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut args = request.args.clone();
+    if args.is_empty() {
+        args.push("".to_string());
+    }
+
+    if request.acl_type != Acltype::Edit {
+        ro.new_args = args;
+        ro.command = replace_new_args(ro.new_args.clone());
+        return vec![ro];
+    }
+
+    args.iter()
+        .map(|file| {
+            let mut file_ro = ro.clone();
+            file_ro.new_args = vec![file.clone()];
+            file_ro.command.clone_from(file);
+            file_ro
+        })
+        .collect()
+}
+
+/// the keys of the policies that the request cannot be replayed against, as
+/// the log does not record what they check
+pub fn unreplayable_keys(policies: &[&[EnvOptions]], request: &LoggedRequest) -> Vec<String> {
+    let sections = || policies.iter().flat_map(|vec_eo| vec_eo.iter());
+    let mut keys = vec![];
+
+    if request.tty.is_none()
+        && sections().any(|eo| matches!(eo.tty, Some(TtyMode::Required | TtyMode::Forbidden)))
+    {
+        keys.push("tty".to_string());
+    }
+    if request.from.is_none() && sections().any(|eo| eo.from.is_some()) {
+        keys.push("from".to_string());
+    }
+
+    keys
+}
+
+/// the decision the policy makes for the request, whether it is permitted
+/// and the section that decided it
+pub fn replay_decision(vec_eo: &[EnvOptions], ro: &RunOptions) -> (bool, String) {
+    let mut ro = ro.clone();
+    let entry = can(vec_eo, &mut ro);
+    (entry.permit() && reason_matches(&entry, &ro), entry.section)
+}
+
+/// a request whose decision differs between two policies
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Flip {
+    pub user: String,
+    pub current: (bool, String),
+    pub candidate: (bool, String),
+    pub request: String,
+}
+
+/// the requests that one policy permits and the other denies
+pub fn simulate(
+    current: &[EnvOptions],
+    candidate: &[EnvOptions],
+    requests: &[RunOptions],
+) -> Vec<Flip> {
+    let mut flips = vec![];
+
+    for ro in requests {
+        let was = replay_decision(current, ro);
+        let will = replay_decision(candidate, ro);
+        if was.0 == will.0 {
+            continue;
+        }
+
+        flips.push(Flip {
+            user: ro.name.clone(),
+            current: was,
+            candidate: will,
+            request: format!(
+                "{} \"{}\" on {} as {}",
+                ro.acl_type, ro.command, ro.hostname, ro.target
+            ),
+        });
+    }
+
+    flips
+}
+
+/// describe a replayed decision, the section is empty when nothing matched
+fn decision_label(decision: &(bool, String)) -> String {
+    format!(
+        "{} ({})",
+        if decision.0 { "permit" } else { "deny" },
+        if decision.1.is_empty() {
+            "no match"
+        } else {
+            &decision.1
+        }
+    )
+}
+
+/// the flips grouped by user and then by the pair of deciding sections
+pub fn produce_sim_report(flips: &[Flip]) -> Vec<String> {
+    let mut sorted: Vec<&Flip> = flips.iter().collect();
+    sorted.sort_by(|a, b| {
+        (&a.user, &a.current.1, &a.candidate.1).cmp(&(&b.user, &b.current.1, &b.candidate.1))
+    });
+
+    let mut lines = vec![];
+    let mut pos = 0;
+    while pos < sorted.len() {
+        let user = &sorted[pos].user;
+        lines.push(format!("{}:", user));
+
+        while pos < sorted.len() && &sorted[pos].user == user {
+            let group = pos;
+            while pos < sorted.len()
+                && &sorted[pos].user == user
+                && sorted[pos].current == sorted[group].current
+                && sorted[pos].candidate == sorted[group].candidate
+            {
+                pos += 1;
+            }
+
+            lines.push(format!(
+                "  {} -> {}: {} request{}",
+                decision_label(&sorted[group].current),
+                decision_label(&sorted[group].candidate),
+                pos - group,
+                if pos - group == 1 { "" } else { "s" }
+            ));
+            for flip in &sorted[group..pos] {
+                lines.push(format!("    {}", flip.request));
+            }
+        }
+    }
+
+    lines
+}
+
+/// a previous version of an edited file
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Backup {
+    pub path: String,
+    pub source: String,
+    pub version: String,
+    pub user: String,
+}
+
+/// number of backups kept for each file when backup_keep is unset
+pub const BACKUP_KEEP: u32 = 10;
+
+/// escape a path so that it is a single file name with no @
+pub fn escape_path(source: &str) -> String {
+    source
+        .replace('%', "%25")
+        .replace('/', "%2F")
+        .replace('@', "%40")
+}
+
+/// reverse escape_path
+pub fn unescape_path(name: &str) -> String {
+    name.replace("%2F", "/")
+        .replace("%40", "@")
+        .replace("%25", "%")
+}
+
+/// version string for a backup taken at time, these sort oldest first
+pub fn backup_version(time: &chrono::DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%S%.6fZ").to_string()
+}
+
+/// file name of a backup, path, version and user separated by @
+pub fn backup_name(source: &str, version: &str, user: &str) -> String {
+    format!("{}@{}@{}", escape_path(source), version, user)
+}
+
+/// split a backup file name in dir back into its parts
+pub fn parse_backup_name(dir: &str, name: &str) -> Option<Backup> {
+    let mut parts = name.splitn(3, '@');
+    let source = parts.next()?;
+    let version = parts.next()?;
+    let user = parts.next()?;
+
+    if source.is_empty() || version.is_empty() || user.is_empty() {
+        return None;
+    }
+
+    Some(Backup {
+        path: format!("{}/{}", dir, name),
+        source: unescape_path(source),
+        version: version.to_string(),
+        user: user.to_string(),
+    })
+}
+
+/// backups of source kept in dir, oldest first
+pub fn list_backups(dir: &str, source: &str) -> Vec<Backup> {
+    let mut backups: Vec<Backup> = vec![];
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return backups,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(backup) = parse_backup_name(dir, &name) {
+            if backup.source == source {
+                backups.push(backup);
+            }
+        }
+    }
+
+    backups.sort_by(|a, b| a.version.cmp(&b.version));
+    backups
+}
+
+/// the backup with version, or a unique version prefix. the newest when
+/// version is None
+pub fn find_backup<'a>(backups: &'a [Backup], version: Option<&str>) -> Result<&'a Backup, String> {
+    let version = match version {
+        None => {
+            return backups
+                .last()
+                .ok_or_else(|| "There are no backups".to_string())
+        }
+        Some(v) => v,
+    };
+
+    if let Some(backup) = backups.iter().find(|b| b.version == version) {
+        return Ok(backup);
+    }
+
+    let found: Vec<&Backup> = backups
+        .iter()
+        .filter(|b| b.version.starts_with(version))
+        .collect();
+    match found.len() {
+        0 => Err(format!("There is no backup version {}", version)),
+        1 => Ok(found[0]),
+        _ => Err(format!("Backup version {} is ambiguous", version)),
+    }
+}
+
+/// the backups beyond keep that should be removed, oldest first
+pub fn expired_backups(backups: &[Backup], keep: u32) -> &[Backup] {
+    let keep = keep as usize;
+    if backups.len() <= keep {
+        return &[];
+    }
+    &backups[..backups.len() - keep]
+}
+
+/// split FILE@version, the version must look like one from backup_version
+pub fn split_rollback(arg: &str) -> (String, Option<String>) {
+    if let Some((file, version)) = arg.rsplit_once('@') {
+        if !version.is_empty()
+            && version
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == 'T' || c == 'Z' || c == '.')
+        {
+            return (file.to_string(), Some(version.to_string()));
+        }
+    }
+    (arg.to_string(), None)
+}
+
+/// the mode of directories made by mkdir=true
+pub const MKDIR_MODE: u32 = 0o755;
+
+/// the ancestors of file that do not exist, outermost first
+pub fn missing_dirs(file: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = file
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .take_while(|dir| fs::symlink_metadata(dir).is_err())
+        .map(|dir| dir.to_path_buf())
+        .collect();
+    missing.reverse();
+    missing
+}
+
+/// parse editowner, keep, target or user:group
+pub fn parse_edit_owner(value: &str) -> Option<EditOwner> {
+    match value.to_lowercase().as_str() {
+        "keep" => return Some(EditOwner::Keep),
+        "target" => return Some(EditOwner::Target),
+        _ => {}
+    }
+
+    let (user, group) = value.split_once(':')?;
+    if user.is_empty() || group.is_empty() || group.contains(':') {
+        return None;
+    }
+    Some(EditOwner::Owner(user.to_string(), group.to_string()))
+}
+
+/// return the extended attributes of path as name and value pairs, this
+/// includes security.* and system.posix_acl_* when the caller can read them
+#[cfg(target_os = "linux")]
+pub fn read_xattrs(path: &Path) -> Result<Vec<(CString, Vec<u8>)>, std::io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+
+    let size = unsafe { libc::listxattr(c_path.as_ptr(), std::ptr::null_mut(), 0) };
+    if size < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut names: Vec<u8> = vec![0; size as usize];
+    let size = unsafe {
+        libc::listxattr(
+            c_path.as_ptr(),
+            names.as_mut_ptr() as *mut libc::c_char,
+            names.len(),
+        )
+    };
+    if size < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    names.truncate(size as usize);
+
+    let mut attrs = vec![];
+    for name in names.split(|c| *c == 0).filter(|n| !n.is_empty()) {
+        let c_name = CString::new(name)?;
+
+        let size =
+            unsafe { libc::getxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut value: Vec<u8> = vec![0; size as usize];
+        let size = unsafe {
+            libc::getxattr(
+                c_path.as_ptr(),
+                c_name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if size < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        value.truncate(size as usize);
+
+        attrs.push((c_name, value));
+    }
+
+    Ok(attrs)
+}
+
+/// extended attributes are not copied on this platform
+#[cfg(not(target_os = "linux"))]
+pub fn read_xattrs(_path: &Path) -> Result<Vec<(CString, Vec<u8>)>, std::io::Error> {
+    Ok(vec![])
+}
+
+/// set each extended attribute on file, return the name that failed
+#[cfg(target_os = "linux")]
+pub fn write_xattrs(
+    file: &std::fs::File,
+    attrs: &[(CString, Vec<u8>)],
+) -> Result<(), (String, std::io::Error)> {
+    use std::os::unix::io::AsRawFd;
+
+    for (name, value) in attrs {
+        let ret = unsafe {
+            libc::fsetxattr(
+                file.as_raw_fd(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if ret != 0 {
+            return Err((
+                name.to_string_lossy().to_string(),
+                std::io::Error::last_os_error(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// extended attributes are not copied on this platform
+#[cfg(not(target_os = "linux"))]
+pub fn write_xattrs(
+    _file: &std::fs::File,
+    _attrs: &[(CString, Vec<u8>)],
+) -> Result<(), (String, std::io::Error)> {
+    Ok(())
+}
+
+/// find editor for user. return /usr/bin/vi if EDITOR and VISUAL are unset
+pub fn get_editor() -> Option<String> {
+    for prog in [String::from("VISUAL"), String::from("EDITOR")].iter() {
+        if let Ok(val) = std::env::var(prog) {
+            if !val.trim().is_empty() {
+                return Some(val);
+            }
+        }
+    }
+
+    None
+}
+
+/// the path of program, looked up in path when it has no directory
+pub fn resolve_editor(program: &str, path: Option<&str>) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    if program.contains('/') || path.is_none() {
+        return program.to_string();
+    }
+
+    for dir in path.unwrap().split(':').filter(|dir| dir.starts_with('/')) {
+        let candidate = format!("{}/{}", dir.trim_end_matches('/'), program);
+        if let Ok(m) = fs::metadata(&candidate) {
+            if m.is_file() && m.permissions().mode() & 0o111 != 0 {
+                return candidate;
+            }
+        }
+    }
+
+    program.to_string()
+}
+
+/// may the section's editor rule run program
+pub fn editor_permitted(entry: &EnvOptions, program: &str, ro: &RunOptions) -> bool {
+    match &entry.editor {
+        None => true,
+        Some(editor) => match regex_build(editor, ro, &entry.file_name, &entry.section, None) {
+            Some(rule) => rule.is_match(program),
+            None => false,
+        },
+    }
+}
+
+/// parse an editor command and resolve its program, None if it is not
+/// permitted by every entry
+fn editor_args(
+    command: &str,
+    entries: &[&EnvOptions],
+    ro: &RunOptions,
+    path: Option<&str>,
+) -> Result<Option<Vec<String>>, String> {
+    let mut args = shell_split(command)?;
+    if args.is_empty() {
+        return Err("there is no command".to_string());
+    }
+    args[0] = resolve_editor(&args[0], path);
+
+    if entries
+        .iter()
+        .all(|entry| editor_permitted(entry, &args[0], ro))
+    {
+        Ok(Some(args))
+    } else {
+        Ok(None)
+    }
+}
+
+/// choose the editor command to run from the requested one, falling back to
+/// the first permitted editor_default. the bool is true on fall back
+pub fn select_editor(
+    requested: Option<&str>,
+    entries: &[&EnvOptions],
+    ro: &RunOptions,
+    path: Option<&str>,
+) -> Result<(Vec<String>, bool), String> {
+    let defaults: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| entry.editor_default.as_deref())
+        .collect();
+
+    let command = match requested {
+        Some(command) => command,
+        None => defaults.first().copied().unwrap_or("/usr/bin/vi"),
+    };
+
+    match editor_args(command, entries, ro, path) {
+        Ok(Some(args)) => return Ok((args, false)),
+        Ok(None) => {}
+        Err(x) => return Err(format!("Could not parse editor {}: {}", command, x)),
+    }
+
+    for default in defaults {
+        if let Ok(Some(args)) = editor_args(default, entries, ro, path) {
+            return Ok((args, true));
+        }
+    }
+
+    Err(format!("You may not use {} as an editor", command))
+}
+
+/// esc_privs, as a Result
+fn try_esc_privs() -> Result<(), PleaseError> {
+    if esc_privs() {
+        Ok(())
+    } else {
+        Err(PleaseError::Privileges)
+    }
+}
+
+/// drop_privs, as a Result
+fn try_drop_privs(ro: &RunOptions) -> Result<(), PleaseError> {
+    if drop_privs(ro) {
+        Ok(())
+    } else {
+        Err(PleaseError::Privileges)
+    }
+}
+
+/// the user and group that an edited file is written as
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UidGid {
+    pub target_uid: nix::unistd::Uid,
+    pub target_gid: nix::unistd::Gid,
+}
+
+/// escalate and then become the target user, as a Result
+fn try_target_privs(target_uid_gid: &UidGid) -> Result<(), PleaseError> {
+    try_esc_privs()?;
+    if set_eprivs(target_uid_gid.target_uid, target_uid_gid.target_gid) {
+        Ok(())
+    } else {
+        Err(PleaseError::Privileges)
+    }
+}
+
+/// what the source file looked like when it was read
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SourceState {
+    dev: libc::dev_t,
+    ino: libc::ino_t,
+    size: libc::off_t,
+    mtime: libc::time_t,
+    mtime_nsec: libc::c_long,
+}
+
+/// a file being edited and the section that permitted it
+#[derive(Clone, Debug)]
+pub struct EditTarget {
+    pub ro: RunOptions,
+    pub entry: EnvOptions,
+    pub source_file: PathBuf,
+    /// the source as it was read, to spot and merge changes by others
+    pub original: Vec<u8>,
+    pub state: Option<SourceState>,
+    /// the content that will replace the source
+    pub file_data: Vec<u8>,
+    pub edit_file: Option<String>,
+    /// has a use of max_uses been taken for this file
+    pub charged: bool,
+}
+
+impl EditTarget {
+    pub fn new(ro: RunOptions, entry: EnvOptions, source_file: &str) -> EditTarget {
+        EditTarget {
+            ro,
+            entry,
+            source_file: PathBuf::from(source_file),
+            original: vec![],
+            state: None,
+            file_data: vec![],
+            edit_file: None,
+            charged: false,
+        }
+    }
+
+    /// the file in the session directory, set by make_session_dir
+    pub fn edit_file(&self) -> &str {
+        self.edit_file.as_deref().unwrap_or_default()
+    }
+
+    fn source(&self) -> String {
+        self.source_file.to_string_lossy().to_string()
+    }
+}
+
+/// the private directory that holds this session's edit files
+static SESSION_DIR: OnceLock<CString> = OnceLock::new();
+
+/// the session directory, opened once so that cleanup cannot be redirected
+static SESSION_FD: OnceLock<libc::c_int> = OnceLock::new();
+
+/// the names of this session's edit files, removed if a signal ends it
+static SESSION_FILES: OnceLock<Vec<CString>> = OnceLock::new();
+
+/// return a path string to work on in the session directory
+fn tmp_edit_file_name(session_dir: &str, source_file: &Path) -> String {
+    format!(
+        "{}/{}",
+        session_dir,
+        escape_path(&source_file.to_string_lossy())
+    )
+}
+
+/// the directories that session directories may be made in
+pub fn session_bases(ro: &RunOptions) -> Vec<String> {
+    let xdg = ro
+        .old_envs
+        .as_ref()
+        .and_then(|envs| envs.get("XDG_RUNTIME_DIR"))
+        .map(|dir| dir.as_str());
+    let base = edit_session_base(xdg, ro.original_uid.as_raw());
+
+    let mut bases = vec![base];
+    if bases[0] != "/tmp" {
+        bases.push("/tmp".to_string());
+    }
+    bases
+}
+
+/// remove the session directory, which only works once it is empty. this is
+/// done as the original user, whose directory it is
+extern "C" fn remove_session_dir() {
+    if let Some(dir) = SESSION_DIR.get() {
+        unsafe {
+            libc::seteuid(libc::getuid());
+            libc::rmdir(dir.as_ptr());
+        }
+    }
+}
+
+/// remove the edit files and session directory, then die from the signal.
+/// privs only go down here, to the original user, whatever they were
+extern "C" fn handle_exit_signal(sig: libc::c_int) {
+    unsafe {
+        libc::seteuid(libc::getuid());
+        if let (Some(fd), Some(files)) = (SESSION_FD.get(), SESSION_FILES.get()) {
+            for file in files {
+                libc::unlinkat(*fd, file.as_ptr(), 0);
+            }
+        }
+        if let Some(dir) = SESSION_DIR.get() {
+            libc::rmdir(dir.as_ptr());
+        }
+        libc::signal(sig, libc::SIG_DFL);
+        libc::raise(sig);
+    }
+}
+
+/// set the signal handlers that remove the edit files
+fn set_exit_signals() -> Result<(), PleaseError> {
+    for sig in [
+        Signal::SIGHUP,
+        Signal::SIGINT,
+        Signal::SIGQUIT,
+        Signal::SIGTERM,
+    ] {
+        unsafe { nix::sys::signal::signal(sig, SigHandler::Handler(handle_exit_signal)) }
+            .map_err(|x| PleaseError::Edit(format!("Could not set {} handler: {}", sig, x)))?;
+    }
+    Ok(())
+}
+
+/// create the private session directory as the original user and name an
+/// edit file in it for each target. the files and directory are removed on
+/// exit or when a signal ends the process, so this is done once per process
+pub fn make_session_dir(
+    ro: &RunOptions,
+    service: &str,
+    targets: &mut [EditTarget],
+) -> Result<(), PleaseError> {
+    try_drop_privs(ro)?;
+
+    let base = session_bases(ro).remove(0);
+    let session_dir = nix::unistd::mkdtemp(format!("{}/{}.XXXXXX", base, service).as_str())
+        .map_err(|x| PleaseError::Edit(format!("Could not create a directory in {}: {}", base, x)))?
+        .to_string_lossy()
+        .to_string();
+
+    let fd = nix::fcntl::open(
+        session_dir.as_str(),
+        nix::fcntl::OFlag::O_RDONLY
+            | nix::fcntl::OFlag::O_DIRECTORY
+            | nix::fcntl::OFlag::O_NOFOLLOW
+            | nix::fcntl::OFlag::O_CLOEXEC,
+        nix::sys::stat::Mode::empty(),
+    )
+    .map_err(|x| PleaseError::Edit(format!("Could not open {}: {}", session_dir, x)))?;
+
+    let c_string = |s: String| {
+        CString::new(s).map_err(|_| PleaseError::Edit("Could not name an edit file".to_string()))
+    };
+
+    let mut files = vec![];
+    for t in targets.iter_mut() {
+        files.push(c_string(escape_path(&t.source()))?);
+        t.edit_file = Some(tmp_edit_file_name(&session_dir, &t.source_file));
+    }
+
+    if SESSION_DIR.set(c_string(session_dir)?).is_err()
+        || SESSION_FD.set(fd.into_raw_fd()).is_err()
+        || SESSION_FILES.set(files).is_err()
+    {
+        return Err(PleaseError::Edit(
+            "An edit session has already been made".to_string(),
+        ));
+    }
+
+    unsafe {
+        libc::atexit(remove_session_dir);
+    }
+    set_exit_signals()
+}
+
+/// return a path string that exitcmd can use adjacent in the source location
+fn source_tmp_file_name(source_file: &Path, service: &str, original_user: &str) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        source_file.to_string_lossy(),
+        prng_alpha_num_string(8),
+        service,
+        original_user,
+    )
+}
+
+/// read the source file as the target user, return the data and its state.
+/// the state is None when the file does not exist
+pub fn read_source_file(
+    source_file: &Path,
+    ro: &RunOptions,
+    target_uid_gid: &UidGid,
+) -> Result<(Vec<u8>, Option<SourceState>), PleaseError> {
+    try_target_privs(target_uid_gid)?;
+    let read = read_source_state(source_file);
+    try_drop_privs(ro)?;
+    read
+}
+
+/// the work of read_source_file, which drops the privs this runs with
+fn read_source_state(source_file: &Path) -> Result<(Vec<u8>, Option<SourceState>), PleaseError> {
+    let mut file_data: Vec<u8> = vec![];
+    if !source_file.exists() {
+        return Ok((file_data, None));
+    }
+
+    let mut file = match File::open(source_file) {
+        Ok(file) => file,
+        Err(_) => {
+            return Err(PleaseError::Edit(format!(
+                "Could not read source file {}",
+                source_file.display()
+            )))
+        }
+    };
+    if file.read_to_end(&mut file_data).is_err() {
+        return Err(PleaseError::Edit(format!(
+            "Could not read source file {}",
+            source_file.display()
+        )));
+    }
+
+    let stat_data = nix::sys::stat::fstat(&file).map_err(|x| {
+        PleaseError::Edit(format!("Could not stat {}: {}", source_file.display(), x))
+    })?;
+
+    let state = SourceState {
+        dev: stat_data.st_dev,
+        ino: stat_data.st_ino,
+        size: stat_data.st_size,
+        mtime: stat_data.st_mtime,
+        mtime_nsec: stat_data.st_mtime_nsec,
+    };
+    Ok((file_data, Some(state)))
+}
+
+/// copy file_data into the tmp file with original user ownership
+pub fn setup_temp_edit_file(
+    ro: &RunOptions,
+    file_data: &[u8],
+    tmp_edit_file: &str,
+) -> Result<(), PleaseError> {
+    try_drop_privs(ro)?;
+
+    let tmp_edit_file_path = Path::new(&tmp_edit_file);
+
+    if tmp_edit_file_path.exists() && fs::remove_file(tmp_edit_file_path).is_err() {
+        return Err(PleaseError::Edit(format!(
+            "Could not remove {}",
+            tmp_edit_file
+        )));
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    options.custom_flags(libc::O_NOFOLLOW);
+    let mut file = options
+        .open(tmp_edit_file_path)
+        .map_err(|_| PleaseError::Edit(format!("Could not create {}", tmp_edit_file)))?;
+
+    if nix::unistd::fchown(&file, Some(ro.original_uid), Some(ro.original_gid)).is_err() {
+        report(&format!("Could not chown {}", tmp_edit_file));
+    }
+
+    if nix::sys::stat::fchmod(
+        &file,
+        nix::sys::stat::Mode::S_IRUSR | nix::sys::stat::Mode::S_IWUSR,
+    )
+    .is_err()
+    {
+        return Err(PleaseError::Edit(format!(
+            "Could not chmod {}",
+            tmp_edit_file
+        )));
+    }
+
+    if file.write_all(file_data).is_err() {
+        return Err(PleaseError::Edit(format!(
+            "Could not write data to {}",
+            tmp_edit_file
+        )));
+    }
+    Ok(())
+}
+
+/// what to do when the source changed while it was being edited
+pub enum Conflict {
+    Merge,
+    Abort,
+    Force,
+}
+
+/// ask what to do now that the source file has changed underneath the edit
+pub fn resolve_conflict(ro: &RunOptions, source_file: &Path) -> Conflict {
+    report(&format!(
+        "{} has been changed by someone else since it was opened for editing",
+        source_file.display()
+    ));
+
+    if ro.tty.is_none() {
+        return Conflict::Abort;
+    }
+
+    loop {
+        let answer = match ask("[m]erge / [a]bort / [f]orce overwrite? ") {
+            Some(answer) => answer,
+            None => return Conflict::Abort,
+        };
+
+        match answer.trim().to_lowercase().as_str() {
+            "m" | "merge" => return Conflict::Merge,
+            "a" | "abort" => return Conflict::Abort,
+            "f" | "force" => return Conflict::Force,
+            _ => {}
+        }
+    }
+}
+
+/// what to do with the changes once they have been shown
+pub enum Confirm {
+    Apply,
+    EditAgain,
+    Discard,
+}
+
+/// show the changes made and ask what to do with them
+pub fn confirm_edit(source_file: &Path, original: &[u8], edited: &[u8]) -> Confirm {
+    let source = source_file.to_string_lossy();
+    let diff = unified_diff(original, edited, &source, &format!("{} (edited)", source));
+
+    if diff.is_empty() {
+        report(&format!("No changes to {}", source));
+    } else {
+        report(diff.trim_end_matches('\n'));
+    }
+
+    loop {
+        let answer = match ask("[a]pply / [e]dit again / [d]iscard? ") {
+            Some(answer) => answer,
+            None => return Confirm::Discard,
+        };
+
+        match answer.trim().to_lowercase().as_str() {
+            "a" | "apply" => return Confirm::Apply,
+            "e" | "edit" => return Confirm::EditAgain,
+            "d" | "discard" => return Confirm::Discard,
+            _ => {}
+        }
+    }
+}
+
+/// return the exitcmd with %{OLD} and %{NEW} replaced
+pub fn build_exitcmd(
+    entry: &EnvOptions,
+    source_file: &str,
+    edit_file: &str,
+) -> Result<process::Command, PleaseError> {
+    let exitcmd = entry.exitcmd.as_deref().unwrap_or_default();
+    let cmd_parts = shell_split(exitcmd)
+        .map_err(|x| PleaseError::Edit(format!("Could not parse exitcmd: {}", x)))?;
+
+    if cmd_parts.is_empty() {
+        return Err(PleaseError::Edit(
+            "exitcmd has too few arguments".to_string(),
+        ));
+    }
+
+    let mut cmd = process::Command::new(&cmd_parts[0]);
+    for j in cmd_parts.iter().skip(1) {
+        cmd.arg(
+            j.replace("%{OLD}", source_file)
+                .replace("%{NEW}", edit_file),
+        );
+    }
+
+    std::env::set_var("PLEASE_EDIT_FILE", edit_file);
+
+    cmd.stdin(process::Stdio::inherit());
+    cmd.stdout(process::Stdio::inherit());
+    cmd.stderr(process::Stdio::inherit());
+
+    Ok(cmd)
+}
+
+/// create the copy next to the source as the target user, privs are left
+/// as the target user
+fn write_target_tmp_file(
+    dir_parent_tmp: &str,
+    file_data: &[u8],
+    target_uid_gid: &UidGid,
+) -> Result<File, PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    options.custom_flags(libc::O_NOFOLLOW);
+
+    let error = || PleaseError::Edit(format!("Could not write data to {}", dir_parent_tmp));
+    let mut file = options.open(dir_parent_tmp).map_err(|_| error())?;
+    file.write_all(file_data).map_err(|_| error())?;
+    Ok(file)
+}
+
+/// remove an edit file as the original user
+pub fn remove_tmp_edit(ro: &RunOptions, edit_file: &str) -> Result<(), PleaseError> {
+    try_drop_privs(ro)?;
+    fs::remove_file(edit_file)
+        .map_err(|_| PleaseError::Edit(format!("Could not remove {}", edit_file)))
+}
+
+/// the mode of a new file, that of its template or 0600
+fn new_file_mode(entry: &EnvOptions) -> nix::sys::stat::Mode {
+    match entry
+        .template
+        .as_ref()
+        .map(|template| nix::sys::stat::stat(template.as_str()))
+    {
+        Some(Ok(m)) => nix::sys::stat::Mode::from_bits_truncate(m.st_mode),
+        _ => nix::sys::stat::Mode::S_IRUSR | nix::sys::stat::Mode::S_IWUSR,
+    }
+}
+
+/// the mode the replacement file is given
+pub fn edit_mode(entry: &EnvOptions, source_file: &Path) -> nix::sys::stat::Mode {
+    match &entry.edit_mode {
+        Some(EditMode::Mode(x)) => nix::sys::stat::Mode::from_bits_truncate(*x as libc::mode_t),
+        _ => match nix::sys::stat::stat(source_file) {
+            Ok(m) => nix::sys::stat::Mode::from_bits_truncate(m.st_mode),
+            _ => new_file_mode(entry),
+        },
+    }
+}
+
+/// may the file be edited when it does not exist, Err(Denied) saying why not
+pub fn creation_ok(
+    ro: &RunOptions,
+    entry: &EnvOptions,
+    source_file: &Path,
+) -> Result<(), PleaseError> {
+    try_esc_privs()?;
+    let exists = fs::symlink_metadata(source_file).is_ok();
+    let missing = missing_dirs(source_file);
+    try_drop_privs(ro)?;
+
+    if exists {
+        return Ok(());
+    }
+
+    if entry.create == Some(false) {
+        return Err(PleaseError::Denied(format!(
+            "You may not create \"{}\" on {} as {}",
+            source_file.display(),
+            &ro.hostname,
+            &ro.target
+        )));
+    }
+
+    if !missing.is_empty() && entry.mkdir != Some(true) {
+        return Err(PleaseError::Denied(format!(
+            "Cannot create \"{}\" as {} does not exist",
+            source_file.display(),
+            missing[0].display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// read the template of a new file
+pub fn read_template(ro: &RunOptions, template: &str) -> Result<Vec<u8>, PleaseError> {
+    try_esc_privs()?;
+    let data = fs::read(template);
+    try_drop_privs(ro)?;
+    data.map_err(|x| PleaseError::Edit(format!("Could not read template {}: {}", template, x)))
+}
+
+/// create the missing directories above the source file with the configured
+/// owner and mode
+fn make_parent_dirs(
+    target: &EditTarget,
+    lookup_name: &User,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    let made = create_parent_dirs(target, lookup_name, target_uid_gid);
+    try_drop_privs(&target.ro)?;
+    made
+}
+
+/// the work of make_parent_dirs, which drops the privs this leaves escalated
+fn create_parent_dirs(
+    target: &EditTarget,
+    lookup_name: &User,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    try_esc_privs()?;
+
+    let missing = missing_dirs(&target.source_file);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let target_owner = (
+        nix::unistd::Uid::from_raw(lookup_name.uid()),
+        target_uid_gid.target_gid,
+    );
+    let mkdir_owner = target
+        .entry
+        .mkdir_owner
+        .clone()
+        .unwrap_or(EditOwner::Target);
+
+    let owner = match &mkdir_owner {
+        EditOwner::Keep => match missing[0].parent().map(nix::sys::stat::stat) {
+            Some(Ok(m)) => (
+                nix::unistd::Uid::from_raw(m.st_uid),
+                nix::unistd::Gid::from_raw(m.st_gid),
+            ),
+            _ => target_owner,
+        },
+        EditOwner::Target => target_owner,
+        EditOwner::Owner(user, group) => match (
+            identity().user_by_name(user),
+            identity().group_by_name(group),
+        ) {
+            (Some(u), Some(g)) => (
+                nix::unistd::Uid::from_raw(u.uid()),
+                nix::unistd::Gid::from_raw(g.gid()),
+            ),
+            _ => {
+                return Err(PleaseError::Edit(format!(
+                    "Could not lookup mkdir_owner {}",
+                    mkdir_owner
+                )))
+            }
+        },
+    };
+    let mode = nix::sys::stat::Mode::from_bits_truncate(
+        target.entry.mkdir_mode.unwrap_or(MKDIR_MODE) as libc::mode_t,
+    );
+
+    for dir in missing.iter() {
+        // set owner and mode through the directory made, not a link put in its place
+        let made = nix::unistd::mkdir(dir, nix::sys::stat::Mode::S_IRWXU)
+            .and_then(|_| {
+                nix::fcntl::open(
+                    dir,
+                    nix::fcntl::OFlag::O_RDONLY
+                        | nix::fcntl::OFlag::O_DIRECTORY
+                        | nix::fcntl::OFlag::O_NOFOLLOW
+                        | nix::fcntl::OFlag::O_CLOEXEC,
+                    nix::sys::stat::Mode::empty(),
+                )
+            })
+            .and_then(|fd| {
+                nix::unistd::fchown(&fd, Some(owner.0), Some(owner.1))?;
+                nix::sys::stat::fchmod(&fd, mode)
+            });
+
+        if let Err(x) = made {
+            return Err(PleaseError::Edit(format!(
+                "Could not create {}: {}",
+                dir.display(),
+                x
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// is the copy in the source directory ready to replace the source, or
+/// should the edit resume
+pub enum Commit {
+    Ready,
+    Resume,
+}
+
+/// remove the copy in the source directory
+fn remove_copy(dir_parent_tmp: &str) -> Result<(), PleaseError> {
+    nix::unistd::unlink(dir_parent_tmp)
+        .map_err(|_| PleaseError::Edit("Could not remove tmp file, giving up".to_string()))
+}
+
+/// remove the copy and return the error that stops the edit
+fn abort_copy(dir_parent_tmp: &str, message: String) -> PleaseError {
+    match remove_copy(dir_parent_tmp) {
+        Ok(()) => PleaseError::Edit(message),
+        Err(_) => PleaseError::Edit(format!(
+            "{}\nCould not remove tmp file either, giving up",
+            message
+        )),
+    }
+}
+
+/// set ownership and mode of the copy in the source directory and run exitcmd.
+/// the copy is removed unless it is ready to be renamed
+fn prepare_target(
+    dir_parent_tmp: &str,
+    target: &EditTarget,
+    lookup_name: &User,
+    dir_parent_tmp_file: &File,
+    target_uid_gid: &UidGid,
+) -> Result<Commit, PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    let target_owner = (
+        nix::unistd::Uid::from_raw(lookup_name.uid()),
+        target_uid_gid.target_gid,
+    );
+    let edit_owner = target.entry.edit_owner.clone().unwrap_or(EditOwner::Keep);
+
+    let owner = match &edit_owner {
+        EditOwner::Keep => match nix::sys::stat::stat(&target.source_file) {
+            Ok(m) => (
+                nix::unistd::Uid::from_raw(m.st_uid),
+                nix::unistd::Gid::from_raw(m.st_gid),
+            ),
+            Err(_) => target_owner,
+        },
+        EditOwner::Target => target_owner,
+        EditOwner::Owner(user, group) => match (
+            identity().user_by_name(user),
+            identity().group_by_name(group),
+        ) {
+            (Some(u), Some(g)) => (
+                nix::unistd::Uid::from_raw(u.uid()),
+                nix::unistd::Gid::from_raw(g.gid()),
+            ),
+            _ => {
+                return Err(abort_copy(
+                    dir_parent_tmp,
+                    format!("Could not lookup editowner {}", edit_owner),
+                ))
+            }
+        },
+    };
+
+    if nix::unistd::fchown(dir_parent_tmp_file, Some(owner.0), Some(owner.1)).is_err() {
+        // the target user may not give the file away, so it is theirs as before
+        if edit_owner == EditOwner::Keep
+            && nix::unistd::fchown(
+                dir_parent_tmp_file,
+                Some(target_owner.0),
+                Some(target_owner.1),
+            )
+            .is_ok()
+        {
+            report(&format!(
+                "Could not keep the owner of {}, it is now owned by {}",
+                target.source(),
+                lookup_name.name().to_string_lossy()
+            ));
+        } else {
+            return Err(abort_copy(
+                dir_parent_tmp,
+                format!("Could not chown {}", dir_parent_tmp),
+            ));
+        }
+    }
+
+    if target.source_file.exists() {
+        let attrs = match read_xattrs(&target.source_file) {
+            Ok(attrs) => attrs,
+            Err(x) if x.raw_os_error() == Some(libc::ENOTSUP) => vec![],
+            Err(x) => {
+                return Err(abort_copy(
+                    dir_parent_tmp,
+                    format!(
+                        "Could not read extended attributes of {}: {}",
+                        target.source(),
+                        x
+                    ),
+                ))
+            }
+        };
+
+        if let Err((name, x)) = write_xattrs(dir_parent_tmp_file, &attrs) {
+            return Err(abort_copy(
+                dir_parent_tmp,
+                format!("Could not copy {} to {}: {}", name, dir_parent_tmp, x),
+            ));
+        }
+    }
+
+    if nix::sys::stat::fchmod(
+        dir_parent_tmp_file,
+        edit_mode(&target.entry, &target.source_file),
+    )
+    .is_err()
+    {
+        return Err(abort_copy(
+            dir_parent_tmp,
+            format!("Could not chmod {}", dir_parent_tmp),
+        ));
+    }
+
+    let source_file = target.source();
+
+    if let Some(validate) = &target.entry.validate {
+        if let Err(x) = validate_data(validate, &target.file_data, &target.ro, &source_file) {
+            // without a terminal there is nobody to fix it
+            if target.ro.tty.is_none() {
+                let mut message = format!("{} failed {} validation:", source_file, validate);
+                if !x.is_empty() {
+                    message = format!("{}\n{}", message, x);
+                }
+                return Err(abort_copy(dir_parent_tmp, message));
+            }
+
+            report(&format!("{} failed {} validation:", source_file, validate));
+            if !x.is_empty() {
+                report(&x);
+            }
+            remove_copy(dir_parent_tmp)?;
+            return Ok(Commit::Resume);
+        }
+    }
+
+    if target.ro.policy {
+        if let Commit::Resume = check_policy(dir_parent_tmp, target)? {
+            return Ok(Commit::Resume);
+        }
+    }
+
+    if target.entry.exitcmd.is_some() {
+        std::env::set_var("PLEASE_SOURCE_FILE", &source_file);
+        std::env::set_var("PLEASE_COMMAND", &target.ro.command);
+        std::env::set_var("SUDO_COMMAND", &target.ro.command);
+        let mut cmd = build_exitcmd(&target.entry, &source_file, dir_parent_tmp)?;
+        match cmd.status() {
+            Err(x) => {
+                if target.ro.resume == Some(true) {
+                    return Err(abort_copy(
+                        dir_parent_tmp,
+                        format!(
+                            "Aborting as exitcmd was non-zero when executing, removing tmp file:\n{}",
+                            x
+                        ),
+                    ));
+                }
+                remove_copy(dir_parent_tmp)?;
+                return Ok(Commit::Resume);
+            }
+            Ok(status) => {
+                if !status.success() {
+                    if target.ro.resume != Some(true) {
+                        return Err(abort_copy(
+                            dir_parent_tmp,
+                            "Aborting as exitcmd was non-zero, removing tmp file".to_string(),
+                        ));
+                    }
+                    remove_copy(dir_parent_tmp)?;
+                    return Ok(Commit::Resume);
+                }
+            }
+        }
+    }
+
+    Ok(Commit::Ready)
+}
+
+/// check the edit as part of the whole policy and that the editor may still
+/// edit it afterwards
+fn check_policy(dir_parent_tmp: &str, target: &EditTarget) -> Result<Commit, PleaseError> {
+    let source_file = target.source();
+
+    if edit_mode(&target.entry, &target.source_file).bits() & 0o022 != 0 {
+        return Err(abort_copy(
+            dir_parent_tmp,
+            format!(
+                "Refusing as {} would be group or other writable, set editmode",
+                source_file
+            ),
+        ));
+    }
+
+    let vec_eo = match policy_check(CONFIG_FILE, &source_file, &target.file_data, &target.ro) {
+        Ok(vec_eo) => vec_eo,
+        Err(x) => {
+            if target.ro.tty.is_none() {
+                return Err(abort_copy(dir_parent_tmp, x));
+            }
+            report(&x);
+            remove_copy(dir_parent_tmp)?;
+            return Ok(Commit::Resume);
+        }
+    };
+
+    if policy_keeps_access(&vec_eo, &target.ro, &source_file) {
+        return Ok(Commit::Ready);
+    }
+
+    report(&format!(
+        "Warning: after this change {} may not edit {}",
+        target.ro.name, source_file
+    ));
+    if target.ro.tty.is_none() {
+        return Ok(Commit::Ready);
+    }
+
+    while let Some(answer) = ask("Apply anyway? [y/N] ") {
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Commit::Ready),
+            "" | "n" | "no" => break,
+            _ => {}
+        }
+    }
+
+    remove_copy(dir_parent_tmp)?;
+    Ok(Commit::Resume)
+}
+
+/// rename the prepared copy over the source file as the target user
+fn rename_to_source(
+    dir_parent_tmp: &str,
+    source_file: &Path,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    fs::rename(dir_parent_tmp, source_file).map_err(|_| {
+        PleaseError::Edit(format!(
+            "Could not rename {} to {}",
+            dir_parent_tmp,
+            source_file.display()
+        ))
+    })
+}
+
+/// check the backup directory is a root only directory, creating it if need be
+fn backup_dir_ok(dir: &str) -> Result<(), PleaseError> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    if !Path::new(dir).exists()
+        && fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .is_err()
+    {
+        return Err(PleaseError::Edit(format!(
+            "Could not create backup directory {}",
+            dir
+        )));
+    }
+
+    match fs::symlink_metadata(dir) {
+        Ok(m) if m.is_dir() && m.uid() == 0 && m.mode() & 0o077 == 0 => Ok(()),
+        _ => Err(PleaseError::Edit(format!(
+            "Backup directory {} must be a directory that only root may access",
+            dir
+        ))),
+    }
+}
+
+/// keep the current content of the source file in the backup directory and
+/// remove backups beyond backup_keep. the source is read as the target user
+fn backup_source(
+    target: &EditTarget,
+    user: &str,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    let dir = match &target.entry.backup {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    let written = write_backup(target, dir, user, target_uid_gid);
+    try_drop_privs(&target.ro)?;
+    written
+}
+
+/// the work of backup_source, which drops the privs this leaves escalated
+fn write_backup(
+    target: &EditTarget,
+    dir: &str,
+    user: &str,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    if !target.source_file.exists() {
+        return Ok(());
+    }
+
+    let data = fs::read(&target.source_file).map_err(|x| {
+        PleaseError::Edit(format!(
+            "Could not read {} to back it up: {}",
+            target.source(),
+            x
+        ))
+    })?;
+
+    try_esc_privs()?;
+    backup_dir_ok(dir)?;
+
+    let source = target.source();
+    let backup = format!(
+        "{}/{}",
+        dir,
+        backup_name(&source, &backup_version(&Utc::now()), user)
+    );
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true).mode(0o600);
+    options.custom_flags(libc::O_NOFOLLOW);
+    let error = || PleaseError::Edit(format!("Could not write backup {}", backup));
+    let mut file = options.open(&backup).map_err(|_| error())?;
+    file.write_all(&data).map_err(|_| error())?;
+
+    let keep = target.entry.backup_keep.unwrap_or(BACKUP_KEEP);
+    for old in expired_backups(&list_backups(dir, &source), keep) {
+        if fs::remove_file(&old.path).is_err() {
+            report(&format!("Could not remove old backup {}", old.path));
+        }
+    }
+
+    Ok(())
+}
+
+/// remove prepared copies that will not be renamed
+fn remove_prepared(
+    prepared: &[(usize, String)],
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    for (_, dir_parent_tmp) in prepared {
+        if nix::unistd::unlink(dir_parent_tmp.as_str()).is_err() {
+            report(&format!("Could not remove {}", dir_parent_tmp));
+        }
+    }
+    Ok(())
+}
+
+/// rename every prepared copy over its source, or put them all back. each
+/// source is first linked to a name beside it, which is renamed back over
+/// the source should a later rename fail
+fn rename_all(
+    targets: &[EditTarget],
+    prepared: &[(usize, String)],
+    service: &str,
+    user: &str,
+    target_uid_gid: &UidGid,
+) -> Result<(), PleaseError> {
+    try_target_privs(target_uid_gid)?;
+
+    // None when the source did not exist and is removed to roll back
+    let mut kept: Vec<(&Path, Option<String>)> = vec![];
+    for (pos, _) in prepared {
+        let source = targets[*pos].source_file.as_path();
+        if fs::symlink_metadata(source).is_err() {
+            kept.push((source, None));
+            continue;
+        }
+        let keep = source_tmp_file_name(source, &format!("{}.keep", service), user);
+        if let Err(x) = fs::hard_link(source, &keep) {
+            remove_kept(&kept);
+            remove_prepared(prepared, target_uid_gid)?;
+            return Err(PleaseError::Edit(format!(
+                "Could not keep {} to roll back to: {}",
+                source.display(),
+                x
+            )));
+        }
+        kept.push((source, Some(keep)));
+    }
+
+    for (renamed, (pos, dir_parent_tmp)) in prepared.iter().enumerate() {
+        if let Err(error) =
+            rename_to_source(dir_parent_tmp, &targets[*pos].source_file, target_uid_gid)
+        {
+            roll_back(&kept[..renamed]);
+            remove_kept(&kept[renamed..]);
+            remove_prepared(&prepared[renamed..], target_uid_gid)?;
+            return Err(PleaseError::Edit(format!(
+                "{}\nThe files already replaced have been rolled back",
+                error
+            )));
+        }
+    }
+
+    remove_kept(&kept);
+    Ok(())
+}
+
+/// put back the sources kept by rename_all
+fn roll_back(kept: &[(&Path, Option<String>)]) {
+    for (source, keep) in kept {
+        let restored = match keep {
+            Some(keep) => fs::rename(keep, source),
+            None => fs::remove_file(source),
+        };
+        if restored.is_err() {
+            report(&format!("Could not roll back {}", source.display()));
+        }
+    }
+}
+
+/// remove the links kept by rename_all once they are not needed
+fn remove_kept(kept: &[(&Path, Option<String>)]) {
+    for keep in kept.iter().filter_map(|(_, keep)| keep.as_ref()) {
+        if nix::unistd::unlink(keep.as_str()).is_err() {
+            report(&format!("Could not remove {}", keep));
+        }
+    }
+}
+
+/// read edit file into memory
+fn edit_file_to_memory(source_file: &Path, edit_file: &str) -> Result<Vec<u8>, PleaseError> {
+    fs::read(edit_file)
+        .map_err(|x| PleaseError::Edit(format!("Could not read {}: {}", source_file.display(), x)))
+}
+
+extern "C" fn handle_sigtstp(
+    child: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _th: *mut libc::c_void,
+) {
+    unsafe {
+        // don't have a handy definition for "5" SI_MESGQ
+        if child == libc::SIGCHLD && (*info).si_code == 5 {
+            libc::kill(libc::getpid(), libc::SIGTSTP);
+        }
+    }
+}
+
+/// the forked child, become the original user and run the editor. the child
+/// never returns
+fn child_editor(ro: &RunOptions, editor: &[String], edit_files: &[&str]) -> ! {
+    if esc_privs() && set_privs(&ro.name, ro.original_uid, ro.original_gid) {
+        if let Some(umask) = ro.old_umask {
+            nix::sys::stat::umask(umask);
+        }
+
+        if let Some(old_envs) = &ro.old_envs {
+            for (key, _) in std::env::vars() {
+                std::env::remove_var(key);
+            }
+            for (key, val) in old_envs.iter() {
+                std::env::set_var(key, val);
+            }
+        }
+
+        let _ = process::Command::new(&editor[0])
+            .args(&editor[1..])
+            .args(edit_files)
+            .exec();
+        report(&format!("Could not execute {}", &editor[0]));
+    }
+    unsafe { libc::_exit(1) }
+}
+
+/// list of the edit files, for messages
+fn edit_file_list(targets: &[EditTarget]) -> String {
+    targets
+        .iter()
+        .map(|t| t.edit_file())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// remove every edit file and log, return the error that ends the edit
+fn discard_edits(service: &str, targets: &[EditTarget]) -> PleaseError {
+    for t in targets {
+        if let Err(error) = remove_tmp_edit(&t.ro, t.edit_file()) {
+            return error;
+        }
+        log_action(service, "discard", &t.ro, &t.ro.original_command.join(" "));
+    }
+    PleaseError::Exit(1)
+}
+
+/// open every edit file in one editor session, true if it exits cleanly
+fn run_editor(
+    ro: &RunOptions,
+    editor: &[String],
+    targets: &[EditTarget],
+) -> Result<bool, PleaseError> {
+    let mut good_edit = false;
+
+    let sig_action = SigAction::new(
+        SigHandler::SigAction(handle_sigtstp),
+        SaFlags::SA_RESTART,
+        SigSet::all(),
+    );
+
+    match unsafe { nix::unistd::fork() } {
+        Ok(nix::unistd::ForkResult::Parent { .. }) => {
+            unsafe {
+                sigaction(Signal::SIGCHLD, &sig_action).map_err(|_| PleaseError::Privileges)?;
+                // the editor deals with interrupts from the terminal
+                nix::sys::signal::signal(Signal::SIGINT, SigHandler::SigIgn)
+                    .map_err(|_| PleaseError::Privileges)?;
+                nix::sys::signal::signal(Signal::SIGQUIT, SigHandler::SigIgn)
+                    .map_err(|_| PleaseError::Privileges)?;
+            };
+
+            if let Ok(nix::sys::wait::WaitStatus::Exited(_pid, 0)) = nix::sys::wait::wait() {
+                good_edit = true;
+            }
+            unsafe { nix::sys::signal::signal(Signal::SIGCHLD, SigHandler::SigDfl) }
+                .map_err(|_| PleaseError::Privileges)?;
+            set_exit_signals()?;
+        }
+        Ok(nix::unistd::ForkResult::Child) => {
+            let edit_files: Vec<&str> = targets.iter().map(|t| t.edit_file()).collect();
+            child_editor(ro, editor, &edit_files);
+        }
+        Err(_) => report("Fork failed"),
+    }
+
+    Ok(good_edit)
+}
+
+/// what became of a change made to the source during the edit
+enum SourceChange {
+    /// the source is as it was read, or is to be overwritten
+    Unchanged,
+    /// the change is merged into file_data, to be edited again
+    Merged,
+    /// the edit is to stop
+    Aborted,
+}
+
+/// compare the source with what was read when the edit began and, if it has
+/// changed, ask what to do. the content is compared, not just its stat
+fn check_source(
+    ro: &RunOptions,
+    service: &str,
+    t: &mut EditTarget,
+    target_uid_gid: &UidGid,
+) -> Result<SourceChange, PleaseError> {
+    let (current, current_state) = read_source_file(&t.source_file, ro, target_uid_gid)?;
+    if current_state == t.state && current == t.original {
+        return Ok(SourceChange::Unchanged);
+    }
+
+    match resolve_conflict(ro, &t.source_file) {
+        Conflict::Merge => {
+            let (merged, conflicts) =
+                merge3(&t.original, &t.file_data, &current, "edited", "current");
+            if conflicts > 0 {
+                report(&format!(
+                    "{} conflicting changes in {} are marked for editing",
+                    conflicts,
+                    t.source()
+                ));
+            }
+
+            t.original = current;
+            t.state = current_state;
+            t.file_data = merged;
+            Ok(SourceChange::Merged)
+        }
+        Conflict::Abort => {
+            log_action(service, "abort", &t.ro, &t.ro.original_command.join(" "));
+            Ok(SourceChange::Aborted)
+        }
+        Conflict::Force => {
+            log_action(
+                service,
+                "force_overwrite",
+                &t.ro,
+                &t.ro.original_command.join(" "),
+            );
+            Ok(SourceChange::Unchanged)
+        }
+    }
+}
+
+/// leave the edits that were not made for --recover and return the error
+/// that stops the session
+fn abort_edit(ro: &RunOptions, service: &str, left: &[&EditTarget]) -> PleaseError {
+    for t in left {
+        if let Err(error) = setup_temp_edit_file(ro, &t.file_data, t.edit_file()) {
+            return error;
+        }
+    }
+
+    PleaseError::Edit(format!(
+        "Exiting as the source changed. Leaving {} in place, see {} --recover.",
+        left.iter()
+            .map(|t| t.edit_file())
+            .collect::<Vec<&str>>()
+            .join(" "),
+        service
+    ))
+}
+
+/// content to start the edit with in place of the source
+pub enum Preset {
+    Rollback(Vec<u8>),
+    Recover(Vec<u8>),
+}
+
+/// the edit session: read each source, open the editor, confirm, spot
+/// concurrent changes, charge max_uses, then run exitcmd and replace each
+/// source. it loops when the edit is resumed
+pub fn edit_loop(
+    ro: &RunOptions,
+    mut targets: Vec<EditTarget>,
+    service: &str,
+    target_uid_gid: &UidGid,
+    lookup_name: &User,
+    mut editor: Option<Vec<String>>,
+    preset: Option<Preset>,
+) -> Result<(), PleaseError> {
+    let confirm = ro.confirm == Some(true) || targets.iter().any(|t| t.entry.confirm == Some(true));
+    let atomic = ro.atomic == Some(true) || targets.iter().any(|t| t.entry.atomic == Some(true));
+
+    // keep what was read so that changes by others can be spotted
+    for t in targets.iter_mut() {
+        let (original, state) = read_source_file(&t.source_file, ro, target_uid_gid)?;
+        t.file_data.clone_from(&original);
+        t.original = original;
+        t.state = state;
+
+        // a new file starts from its template
+        if t.state.is_none() {
+            if let Some(template) = &t.entry.template {
+                t.file_data = read_template(ro, template)?;
+            }
+        }
+    }
+
+    if targets.len() == 1 {
+        std::env::set_var("PLEASE_SOURCE_FILE", targets[0].source());
+    }
+
+    // a rollback starts with the backup in place of an editor session, so
+    // its editor is only chosen should the edit be resumed
+    let mut skip_editor = false;
+    match preset {
+        Some(Preset::Rollback(data)) => {
+            targets[0].file_data = data;
+            skip_editor = true;
+        }
+        Some(Preset::Recover(data)) => targets[0].file_data = data,
+        None => {}
+    }
+
+    // loop around if resume on failure is set
+    loop {
+        for t in targets.iter() {
+            setup_temp_edit_file(ro, &t.file_data, t.edit_file())?;
+        }
+        if targets.len() == 1 {
+            std::env::set_var("PLEASE_EDIT_FILE", targets[0].edit_file());
+        }
+
+        let good_edit = skip_editor || {
+            if editor.is_none() {
+                editor = Some(choose_editor(ro, &targets)?);
+            }
+            run_editor(ro, editor.as_deref().unwrap_or_default(), &targets)?
+        };
+        skip_editor = false;
+
+        if !good_edit {
+            return Err(PleaseError::Edit(format!(
+                "Exiting as editor or child did not close cleanly. Leaving {} in place, see {} --recover.",
+                edit_file_list(&targets),
+                service
+            )));
+        }
+
+        for t in targets.iter_mut() {
+            t.file_data = edit_file_to_memory(&t.source_file, t.edit_file())?;
+        }
+
+        if confirm {
+            if ro.tty.is_none() {
+                report("Cannot confirm changes without a terminal, discarding edit");
+                return Err(discard_edits(service, &targets));
+            }
+
+            let mut edit_again = false;
+            for t in targets.iter() {
+                match confirm_edit(&t.source_file, &t.original, &t.file_data) {
+                    Confirm::Apply => {}
+                    Confirm::EditAgain => {
+                        edit_again = true;
+                        break;
+                    }
+                    Confirm::Discard => {
+                        for t in targets.iter() {
+                            report(&format!("Discarded changes to {}", t.source()));
+                        }
+                        return Err(discard_edits(service, &targets));
+                    }
+                }
+            }
+            if edit_again {
+                continue;
+            }
+        }
+
+        let mut edit_again = false;
+        for pos in 0..targets.len() {
+            match check_source(ro, service, &mut targets[pos], target_uid_gid)? {
+                SourceChange::Unchanged => {}
+                SourceChange::Merged => edit_again = true,
+                SourceChange::Aborted => {
+                    let left: Vec<&EditTarget> = targets.iter().collect();
+                    return Err(abort_edit(ro, service, &left));
+                }
+            }
+        }
+        if edit_again {
+            continue;
+        }
+
+        // the edit is accepted, charge each file once across resumed edits
+        try_esc_privs()?;
+        let mut exhausted = targets
+            .iter()
+            .position(|t| !t.charged && !uses_left(&t.ro.name, &t.entry));
+        if exhausted.is_none() {
+            for (pos, t) in targets.iter_mut().enumerate() {
+                if t.charged {
+                    continue;
+                }
+                if !consume_use(&t.ro.name, &t.entry) {
+                    exhausted = Some(pos);
+                    break;
+                }
+                t.charged = true;
+            }
+        }
+        try_drop_privs(ro)?;
+        if let Some(pos) = exhausted {
+            report(&quota_exhausted(service, &targets[pos]).to_string());
+            return Err(discard_edits(service, &targets));
+        }
+
+        let target_uid_gid = UidGid {
+            target_uid: target_uid_gid.target_uid,
+            target_gid: runopt_target_gid(ro, lookup_name)?,
+        };
+
+        // copies that passed exitcmd, waiting for every file when atomic
+        let mut prepared: Vec<(usize, String)> = vec![];
+        let mut done: Vec<usize> = vec![];
+        let mut resume = false;
+
+        for pos in 0..targets.len() {
+            let t = &targets[pos];
+            log_action(service, "permit", &t.ro, &t.ro.original_command.join(" "));
+            let dir_parent_tmp = source_tmp_file_name(
+                &t.source_file,
+                format!("{}.copy", service).as_str(),
+                &ro.name,
+            );
+
+            if t.entry.mkdir == Some(true) {
+                if let Err(error) = make_parent_dirs(t, lookup_name, &target_uid_gid) {
+                    remove_prepared(&prepared, &target_uid_gid)?;
+                    return Err(error);
+                }
+            }
+
+            // become the target user and create file
+            let dir_parent_tmp_file =
+                write_target_tmp_file(&dir_parent_tmp, &t.file_data, &target_uid_gid)?;
+
+            // original user, remove tmp edit file
+            remove_tmp_edit(ro, t.edit_file())?;
+
+            let commit = prepare_target(
+                &dir_parent_tmp,
+                t,
+                lookup_name,
+                &dir_parent_tmp_file,
+                &target_uid_gid,
+            );
+            match commit {
+                Ok(Commit::Ready) => {
+                    if atomic {
+                        prepared.push((pos, dir_parent_tmp));
+                        continue;
+                    }
+                    if let Err(error) = backup_source(t, &ro.name, &target_uid_gid) {
+                        remove_prepared(&[(pos, dir_parent_tmp)], &target_uid_gid)?;
+                        return Err(error);
+                    }
+
+                    // the source may have changed while exitcmd ran
+                    match check_source(ro, service, &mut targets[pos], &target_uid_gid)? {
+                        SourceChange::Unchanged => {
+                            rename_to_source(
+                                &dir_parent_tmp,
+                                &targets[pos].source_file,
+                                &target_uid_gid,
+                            )?;
+                            done.push(pos);
+                        }
+                        SourceChange::Merged => {
+                            remove_prepared(&[(pos, dir_parent_tmp)], &target_uid_gid)?;
+                            resume = true;
+                        }
+                        SourceChange::Aborted => {
+                            remove_prepared(&[(pos, dir_parent_tmp)], &target_uid_gid)?;
+                            let left: Vec<&EditTarget> = targets
+                                .iter()
+                                .enumerate()
+                                .filter(|(p, _)| !done.contains(p))
+                                .map(|(_, t)| t)
+                                .collect();
+                            return Err(abort_edit(ro, service, &left));
+                        }
+                    }
+                }
+                Ok(Commit::Resume) => {
+                    resume = true;
+                    if atomic {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    remove_prepared(&prepared, &target_uid_gid)?;
+                    for t in targets[pos + 1..].iter() {
+                        remove_tmp_edit(ro, t.edit_file())?;
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        if atomic {
+            if resume {
+                remove_prepared(&prepared, &target_uid_gid)?;
+                continue;
+            }
+            for (pos, _) in prepared.iter() {
+                if let Err(error) = backup_source(&targets[*pos], &ro.name, &target_uid_gid) {
+                    remove_prepared(&prepared, &target_uid_gid)?;
+                    return Err(error);
+                }
+            }
+
+            // the sources may have changed while exitcmd ran
+            let mut edit_again = false;
+            for (pos, _) in prepared.iter() {
+                match check_source(ro, service, &mut targets[*pos], &target_uid_gid)? {
+                    SourceChange::Unchanged => {}
+                    SourceChange::Merged => edit_again = true,
+                    SourceChange::Aborted => {
+                        remove_prepared(&prepared, &target_uid_gid)?;
+                        let left: Vec<&EditTarget> = targets.iter().collect();
+                        return Err(abort_edit(ro, service, &left));
+                    }
+                }
+            }
+            if edit_again {
+                remove_prepared(&prepared, &target_uid_gid)?;
+                continue;
+            }
+
+            rename_all(&targets, &prepared, service, &ro.name, &target_uid_gid)?;
+            break;
+        }
+
+        // only edit again the files that did not make it
+        for pos in done.iter().rev() {
+            targets.remove(*pos);
+        }
+        if targets.is_empty() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// log that the section has no uses left and return the refusal
+fn quota_exhausted(service: &str, t: &EditTarget) -> PleaseError {
+    log_action(
+        service,
+        "quota_exhausted",
+        &t.ro,
+        &t.ro.original_command.join(" "),
+    );
+    PleaseError::Denied(format!(
+        "You have used all of your uses ({}) to edit \"{}\" on {} as {}",
+        t.entry
+            .max_uses
+            .as_ref()
+            .map(|m| m.to_string())
+            .unwrap_or_default(),
+        &t.ro.command,
+        &t.ro.hostname,
+        &t.ro.target
+    ))
+}
+
+/// the files of ro.new_args, each permitted by its own type=edit section.
+/// refusals are logged
+pub fn edit_targets(
+    ro: &RunOptions,
+    vec_eo: &[EnvOptions],
+    service: &str,
+) -> Result<Vec<EditTarget>, PleaseError> {
+    let mut targets: Vec<EditTarget> = vec![];
+    for file in ro.new_args.iter() {
+        // the same file given twice would share one edit file
+        if targets.iter().any(|t| t.source_file == Path::new(file)) {
+            continue;
+        }
+        let mut file_ro = ro.clone();
+        file_ro.new_args = vec![file.clone()];
+        file_ro.command.clone_from(file);
+
+        let entry = can(vec_eo, &mut file_ro);
+        if let Some(syslog) = entry.syslog {
+            file_ro.syslog = syslog;
+        }
+
+        let refused = if !entry.permit() {
+            let mut message = format!(
+                "You may not edit \"{}\" on {} as {}",
+                &file_ro.command, &file_ro.hostname, &file_ro.target
+            );
+            if let Some(why) = tty_explain(vec_eo, &file_ro) {
+                message = format!("{}\n{}", message, why);
+            }
+            Err(PleaseError::Denied(message))
+        } else if entry.approval.is_some() {
+            // read_ini refuses approval on edit sections, rules from elsewhere may not
+            Err(PleaseError::Denied(format!(
+                "Edits of \"{}\" need approval, which pleaseedit does not support",
+                &file_ro.command
+            )))
+        } else {
+            creation_ok(&file_ro, &entry, Path::new(file))
+        };
+
+        if let Err(error) = refused {
+            if let PleaseError::Denied(_) = error {
+                log_action(
+                    service,
+                    "deny",
+                    &file_ro,
+                    &file_ro.original_command.join(" "),
+                );
+            }
+            return Err(error);
+        }
+
+        targets.push(EditTarget::new(file_ro, entry, file));
+    }
+    Ok(targets)
+}
+
+/// check the reason, lockout and password of each target, logging refusals
+pub fn authorise_edits(targets: &[EditTarget], service: &str) -> Result<(), PleaseError> {
+    for t in targets.iter() {
+        let command = t.ro.original_command.join(" ");
+
+        // check if a reason was given
+        if !reason_ok(&t.entry, &t.ro) {
+            log_action(service, "reason_fail", &t.ro, &command);
+            return Err(PleaseError::Exit(1));
+        }
+
+        if locked_out(&t.ro, &t.entry)? {
+            log_action(service, "lockout", &t.ro, &command);
+            return Err(PleaseError::Exit(1));
+        }
+
+        if !challenge_password(&t.ro, &t.entry, service)? {
+            log_action(service, "deny", &t.ro, &command);
+            return Err(PleaseError::Exit(1));
+        }
+    }
+    Ok(())
+}
+
+/// refuse when a target has no uses left, nothing is charged until the
+/// edit is accepted
+pub fn edit_uses_left(
+    ro: &RunOptions,
+    targets: &[EditTarget],
+    service: &str,
+) -> Result<(), PleaseError> {
+    try_esc_privs()?;
+    let exhausted = targets.iter().find(|t| !uses_left(&t.ro.name, &t.entry));
+    try_drop_privs(ro)?;
+
+    match exhausted {
+        Some(t) => Err(quota_exhausted(service, t)),
+        None => Ok(()),
+    }
+}
+
+/// set the environment of the edit. one process and editor serve every
+/// target, so their env_assign must agree
+pub fn set_edit_environment(
+    targets: &[EditTarget],
+    original_user: &User,
+    original_uid: u32,
+    lookup_name: &User,
+) -> Result<(), PleaseError> {
+    if let Some(t) = targets
+        .iter()
+        .find(|t| t.entry.env_assign != targets[0].entry.env_assign)
+    {
+        return Err(PleaseError::Edit(format!(
+            "Cannot edit {} and {} together as their env_assign differs, edit them separately",
+            targets[0].source(),
+            t.source()
+        )));
+    }
+
+    set_environment(
+        &targets[0].ro,
+        &targets[0].entry,
+        original_user,
+        original_uid,
+        lookup_name,
+    );
+    Ok(())
+}
+
+/// the backups of the file, Err if none are kept
+pub fn target_backups(target: &EditTarget) -> Result<Vec<Backup>, PleaseError> {
+    let dir = match &target.entry.backup {
+        Some(dir) => dir,
+        None => {
+            return Err(PleaseError::Edit(format!(
+                "No backups are kept for {}",
+                target.source()
+            )))
+        }
+    };
+
+    try_esc_privs()?;
+    let backups = list_backups(dir, &target.source());
+    try_drop_privs(&target.ro)?;
 
-    match &item.reason.as_ref().unwrap() {
-        ReasonType::Text(value) => {
-            let m_re = match regex_build(value, ro, &item.file_name, &item.section, None) {
-                Some(check) => check,
-                None => {
-                    println!("Could not compile {}", &value);
-                    return false;
-                }
-            };
+    Ok(backups)
+}
 
-            if ro.reason.is_some() && m_re.is_match(ro.reason.as_ref().unwrap()) {
-                return true;
-            }
+/// read the backup to roll back to
+pub fn read_backup(target: &EditTarget, version: Option<&str>) -> Result<Vec<u8>, PleaseError> {
+    let backups = target_backups(target)?;
+    let backup = find_backup(&backups, version)
+        .map_err(|x| PleaseError::Edit(format!("{} for {}", x, target.source())))?;
+
+    try_esc_privs()?;
+    let data = fs::read(&backup.path);
+    try_drop_privs(&target.ro)?;
+    let data =
+        data.map_err(|x| PleaseError::Edit(format!("Could not read {}: {}", backup.path, x)))?;
+
+    report(&format!(
+        "Rolling back {} to {} by {}",
+        target.source(),
+        backup.version,
+        backup.user
+    ));
+    Ok(data)
+}
 
-            println!(
-                "Sorry but there is no reason match to {} \"{}\" on {} as {}",
-                &ro.acl_type, &ro.command, &ro.hostname, &ro.target
-            );
+/// the edits that earlier sessions left behind, oldest first
+pub fn left_edits(ro: &RunOptions, service: &str) -> Result<Vec<LeftEdit>, PleaseError> {
+    try_drop_privs(ro)?;
+    Ok(find_left_edits(
+        &session_bases(ro),
+        service,
+        ro.original_uid.as_raw(),
+    ))
+}
 
-            false
-        }
-        ReasonType::Need(value) => {
-            if value == &true && ro.reason.is_none() {
-                println!(
-                    "Sorry but no reason was given to {} \"{}\" on {} as {}",
-                    &ro.acl_type,
-                    if ro.acl_type == Acltype::List {
-                        &ro.target
-                    } else {
-                        &ro.command
-                    },
-                    &ro.hostname,
-                    &ro.target
-                );
-                return false;
-            }
-            true
-        }
-    }
+/// the newest left edit of the file
+pub fn find_recovery(
+    ro: &RunOptions,
+    service: &str,
+    source: &str,
+) -> Result<LeftEdit, PleaseError> {
+    left_edits(ro, service)?
+        .into_iter()
+        .rev()
+        .find(|edit| edit.source == source)
+        .ok_or_else(|| PleaseError::Edit(format!("There is no edit of {} to recover", source)))
 }
 
-/// find editor for user. return /usr/bin/vi if EDITOR and VISUAL are unset
-pub fn get_editor() -> String {
-    let editor = "/usr/bin/vi";
+/// read the left edit as the original user
+pub fn read_recovery(ro: &RunOptions, edit: &LeftEdit) -> Result<Vec<u8>, PleaseError> {
+    try_drop_privs(ro)?;
+    let data = fs::read(&edit.path)
+        .map_err(|x| PleaseError::Edit(format!("Could not read {}: {}", edit.path, x)))?;
+    report(&format!("Recovering {} from {}", edit.source, edit.path));
+    Ok(data)
+}
 
-    for prog in [String::from("VISUAL"), String::from("EDITOR")].iter() {
-        if let Ok(val) = std::env::var(prog) {
-            return val;
-        }
+/// remove a recovered edit and its session directory once empty
+pub fn remove_recovered(ro: &RunOptions, edit: &LeftEdit) -> Result<(), PleaseError> {
+    remove_tmp_edit(ro, &edit.path)?;
+    if let Some(dir) = Path::new(&edit.path).parent() {
+        let _ = fs::remove_dir(dir);
     }
+    Ok(())
+}
 
-    editor.to_string()
+/// the editor command permitted by every section
+pub fn choose_editor(ro: &RunOptions, targets: &[EditTarget]) -> Result<Vec<String>, PleaseError> {
+    let requested = get_editor();
+    let entries: Vec<&EnvOptions> = targets.iter().map(|t| &t.entry).collect();
+    let path = ro.old_envs.as_ref().and_then(|envs| envs.get("PATH"));
+
+    let (editor, fallback) =
+        select_editor(requested.as_deref(), &entries, ro, path.map(|p| p.as_str()))
+            .map_err(PleaseError::Denied)?;
+    if fallback {
+        report(&format!(
+            "{} is not a permitted editor, using {}",
+            requested.unwrap_or_default(),
+            editor.join(" ")
+        ));
+    }
+    Ok(editor)
 }
 
 /// handler.authenticate without the root privs part for linux
@@ -1448,8 +6786,8 @@ pub fn get_editor() -> String {
 pub fn handler_shim<T: pam::Converse>(
     _ro: &RunOptions,
     handler: &mut Authenticator<T>,
-) -> Result<(), pam::PamError> {
-    handler.authenticate()
+) -> Result<Result<(), pam::PamError>, PleaseError> {
+    Ok(handler.authenticate())
 }
 
 /// handler.authenticate needs esc_privs on netbsd
@@ -1457,130 +6795,106 @@ pub fn handler_shim<T: pam::Converse>(
 pub fn handler_shim<T: pam::Converse>(
     ro: &RunOptions,
     handler: &mut Authenticator<T>,
-) -> Result<(), pam::PamError> {
-    if !esc_privs() {
-        std::process::exit(1);
-    }
+) -> Result<Result<(), pam::PamError>, PleaseError> {
+    try_esc_privs()?;
     let auth = handler.authenticate();
-    if !drop_privs(&ro) {
-        std::process::exit(1);
-    }
-    auth
+    try_drop_privs(ro)?;
+    Ok(auth)
 }
 
-/// read password of user via rpassword
+/// read password of user via pam, Ok(false) when it is not given correctly.
 /// should pam require a password, and it is successful, then we set a token
-pub fn challenge_password(ro: &RunOptions, entry: &EnvOptions, service: &str) -> bool {
+pub fn challenge_password(
+    ro: &RunOptions,
+    entry: &EnvOptions,
+    service: &str,
+) -> Result<bool, PleaseError> {
     if entry.require_pass() {
         if tty_name().is_none() {
-            println!("Cannot read password without tty");
-            return false;
+            report("Cannot read password without tty");
+            return Ok(false);
         }
 
         let mut retry_counter = 0;
 
-        if !esc_privs() {
-            std::process::exit(1);
-        }
+        try_esc_privs()?;
 
         if valid_token(&ro.name, entry) {
             update_token(&ro.name);
-            return true;
+            return Ok(true);
         }
 
-        if !drop_privs(ro) {
-            std::process::exit(1);
-        }
+        try_drop_privs(ro)?;
 
         if !ro.prompt {
-            return false;
+            return Ok(false);
         }
 
         let convo = PamConvo {
             login: ro.name.to_string(),
             passwd: None,
             service: service.to_string(),
+            timeout: entry.timeout,
+            failure: None,
         };
 
-        if entry.timeout.is_some() {
-            extern "C" fn alarm_signal_handler(_: nix::libc::c_int) {
-                println!("Timed out getting password");
-
-                let tty = std::fs::File::open("/dev/tty");
-                if tty.is_ok() {
-                    let term_res = nix::sys::termios::tcgetattr(tty.as_ref().unwrap());
-                    if let Ok(mut term) = term_res {
-                        term.local_flags
-                            .set(nix::sys::termios::LocalFlags::ECHO, true);
-
-                        let res = nix::sys::termios::tcsetattr(
-                            tty.as_ref().unwrap(),
-                            nix::sys::termios::SetArg::TCSANOW,
-                            &term,
-                        );
-                        if res.is_err() {
-                            println!("Couldn't return terminal to original settings");
-                        }
-                    }
-                }
-
-                std::process::exit(1);
-            }
-
-            let sa = SigAction::new(
-                SigHandler::Handler(alarm_signal_handler),
-                SaFlags::SA_RESTART,
-                SigSet::empty(),
-            );
-
-            unsafe {
-                match sigaction(Signal::SIGALRM, &sa) {
-                    Ok(_) => {}
-                    Err(_) => {
-                        println!("Couldn't reset alarm");
-                        std::process::exit(1);
-                    }
-                }
-            }
-        }
-
-        let mut handler = Authenticator::with_handler(service, convo).expect("Cannot init PAM");
+        let mut handler = Authenticator::with_handler(service, convo)
+            .map_err(|e| PleaseError::Pam(e.to_string()))?;
 
         loop {
-            if let Some(timeout) = entry.timeout {
-                alarm::set(timeout);
-            }
+            let auth = handler_shim(ro, &mut handler)?;
 
-            let auth = handler_shim(ro, &mut handler);
-
-            if entry.timeout.is_some() {
-                alarm::cancel();
+            if let Some(failure) = handler.get_handler().failure.take() {
+                return Err(failure);
             }
 
             if auth.is_ok() {
                 if handler.get_handler().passwd.is_some() {
-                    unsafe { signal::signal(signal::SIGALRM, signal::SigHandler::SigDfl).unwrap() };
-                    if !esc_privs() {
-                        std::process::exit(1);
-                    }
+                    try_esc_privs()?;
 
                     update_token(&ro.name);
+                    if entry.lockout.is_some() {
+                        remove_failures(&ro.name);
+                    }
+
+                    try_drop_privs(ro)?;
+                }
+                return Ok(true);
+            }
 
-                    if !drop_privs(ro) {
-                        std::process::exit(1);
+            if entry.lockout.is_some() {
+                try_esc_privs()?;
+                let failures = record_failure(&ro.name, entry);
+                try_drop_privs(ro)?;
+
+                // deny when the failure could not be kept, not fail open
+                let failures = match failures {
+                    Some(failures) => failures,
+                    None => {
+                        report("Authentication failed :-(");
+                        return Ok(false);
                     }
+                };
+
+                if lockout_remaining(entry, &failures, epoch_secs()).is_some() {
+                    report("Authentication failed, too many attempts :-(");
+                    return Ok(false);
                 }
-                return true;
             }
+
+            if let Some(delay) = entry.failure_delay {
+                std::thread::sleep(std::time::Duration::from_secs(delay));
+            }
+
             retry_counter += 1;
-            if retry_counter == 3 {
-                println!("Authentication failed :-(");
+            if retry_counter >= entry.password_retries.unwrap_or(3) {
+                report("Authentication failed :-(");
 
-                return false;
+                return Ok(false);
             }
         }
     }
-    true
+    Ok(true)
 }
 
 /// return rule or exact_rule
@@ -1620,8 +6934,343 @@ pub fn list_dir(eo: &EnvOptions) -> String {
 pub fn list(vec_eo: &[EnvOptions], ro: &RunOptions) {
     //let mut str_list: vec![];
     for s in produce_list(vec_eo, ro) {
-        println!("{}", s);
+        report(&s);
+    }
+}
+
+/// should the section be shown to the user listing
+fn listed(item: &EnvOptions, ro: &RunOptions) -> bool {
+    if !item.group && !name_matches(item, ro, None) {
+        return false;
+    }
+
+    if item.group && !group_matches(item, ro, None) {
+        return false;
+    }
+
+    item.acl_type == ro.acl_type && hostname_ok(item, ro, None) && from_ok(item, ro, None)
+}
+
+/// does the regex match everything
+fn regex_universal(regex: &str) -> bool {
+    matches!(regex, ".*" | "^.*$" | "^.*" | ".*$")
+}
+
+/// does the wide regex or exact value match everything that the narrow one
+/// does. a field that is not set matches anything when none_is_any
+fn field_covers(
+    wide: (&Option<String>, &Option<String>),
+    narrow: (&Option<String>, &Option<String>),
+    none_is_any: bool,
+) -> bool {
+    let (wide_re, wide_exact) = wide;
+    let (narrow_re, narrow_exact) = narrow;
+
+    if let Some(exact) = wide_exact {
+        return narrow_exact.as_ref() == Some(exact);
+    }
+
+    match wide_re {
+        None => none_is_any || (narrow_re.is_none() && narrow_exact.is_none()),
+        Some(re) if regex_universal(re) => true,
+        Some(re) => match narrow_exact {
+            Some(exact) => match Regex::new(&format!("^{}$", re)) {
+                Ok(rule) => rule.is_match(exact),
+                Err(_) => false,
+            },
+            None => narrow_re.as_ref() == Some(re),
+        },
+    }
+}
+
+/// would the wide section always win over the narrow one that follows it,
+/// as wide has last=true and matches whenever narrow does
+pub fn section_shadows(wide: &EnvOptions, narrow: &EnvOptions) -> bool {
+    if wide.last != Some(true) || wide.acl_type != narrow.acl_type {
+        return false;
+    }
+
+    // conditions that could stop the wide section from matching
+    if wide.notbefore.is_some()
+        || wide.notafter.is_some()
+        || wide.datematch.is_some()
+        || wide.from.is_some()
+        || wide.dir.is_some()
+        || wide.exact_dir.is_some()
+        || matches!(wide.tty, Some(TtyMode::Required) | Some(TtyMode::Forbidden))
+    {
+        return false;
+    }
+
+    let name_universal =
+        wide.exact_name.is_none() && wide.name.as_deref().is_some_and(regex_universal);
+    if !name_universal
+        && (wide.group != narrow.group
+            || !field_covers(
+                (&wide.name, &wide.exact_name),
+                (&narrow.name, &narrow.exact_name),
+                false,
+            ))
+    {
+        return false;
+    }
+
+    field_covers(
+        (&wide.rule, &wide.exact_rule),
+        (&narrow.rule, &narrow.exact_rule),
+        false,
+    ) && field_covers(
+        (&wide.target, &wide.exact_target),
+        (&narrow.target, &narrow.exact_target),
+        false,
+    ) && field_covers(
+        (&wide.target_group, &wide.exact_target_group),
+        (&narrow.target_group, &narrow.exact_target_group),
+        false,
+    ) && field_covers(
+        (&wide.hostname, &wide.exact_hostname),
+        (&narrow.hostname, &narrow.exact_hostname),
+        true,
+    )
+}
+
+/// would the default section match whenever the listed item does, now
+fn default_applies(default: &EnvOptions, item: &EnvOptions, ro: &RunOptions) -> bool {
+    permitted_dates_ok(default, ro, None)
+        && tty_ok(default, ro)
+        && environment_ok(default, ro, None)
+        && field_covers(
+            (&default.rule, &default.exact_rule),
+            (&item.rule, &item.exact_rule),
+            false,
+        )
+        && field_covers(
+            (&default.target, &default.exact_target),
+            (&item.target, &item.exact_target),
+            false,
+        )
+        && field_covers(
+            (&default.target_group, &default.exact_target_group),
+            (&item.target_group, &item.exact_target_group),
+            false,
+        )
+        && field_covers(
+            (&default.dir, &default.exact_dir),
+            (&item.dir, &item.exact_dir),
+            false,
+        )
+}
+
+/// the sections listed for the user, with the values that apply once the
+/// matching default sections before them are merged, and the section that
+/// shadows each, if any
+pub fn effective_list(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<(EnvOptions, Option<String>)> {
+    let mut defaults: Vec<&EnvOptions> = vec![];
+    let mut merged: Vec<EnvOptions> = vec![];
+
+    for item in vec_eo {
+        if !listed(item, ro) {
+            continue;
+        }
+
+        if item.section.starts_with("default") {
+            defaults.push(item);
+        }
+
+        // as can() would, only merge the defaults that match with the section
+        let mut default = EnvOptions::new();
+        for wide in defaults.iter() {
+            if default_applies(wide, item, ro) {
+                default = merge_default(&default, wide);
+            }
+        }
+        merged.push(merge_default(&default, item));
     }
+
+    merged
+        .iter()
+        .enumerate()
+        .map(|(pos, item)| {
+            let shadow = merged[..pos]
+                .iter()
+                .find(|wide| section_shadows(wide, item))
+                .map(|wide| wide.section.clone());
+            (item.clone(), shadow)
+        })
+        .collect()
+}
+
+/// the sections to list, merged with defaults when effective
+fn list_entries(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<(EnvOptions, Option<String>)> {
+    if ro.effective {
+        return effective_list(vec_eo, ro);
+    }
+
+    vec_eo
+        .iter()
+        .filter(|item| listed(item, ro))
+        .map(|item| (item.clone(), None))
+        .collect()
+}
+
+/// is a reason required, and the rule it must match
+fn reason_requirement(item: &EnvOptions) -> (bool, Option<String>) {
+    match &item.reason {
+        None | Some(ReasonType::Need(false)) => (false, None),
+        Some(ReasonType::Need(true)) => (true, None),
+        Some(ReasonType::Text(rule)) => (true, Some(rule.clone())),
+    }
+}
+
+/// return the listed sections as json records
+pub fn produce_list_json(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<serde_json::Value> {
+    let mut records = vec![];
+    let mut ro = ro.clone();
+
+    if !ro.target.is_empty() {
+        ro.name.clone_from(&ro.target);
+    }
+
+    for (item, shadowed_by) in list_entries(vec_eo, &ro).iter() {
+        let date = if item.notbefore.is_some() && item.notbefore.unwrap() > ro.date {
+            "upcoming"
+        } else if item.notafter.is_some() && item.notafter.unwrap() < ro.date {
+            "expired"
+        } else {
+            "current"
+        };
+
+        let (reason_required, reason_rule) = reason_requirement(item);
+
+        let uses_remaining = item
+            .max_uses
+            .as_ref()
+            .and_then(|_| uses_remaining(item, &read_uses(&ro.name, item), epoch_secs()));
+
+        records.push(serde_json::json!({
+            "file": item.file_name,
+            "section": item.section,
+            "acl_type": item.acl_type.to_string(),
+            "target": item.target,
+            "exact_target": item.exact_target,
+            "target_group": item.target_group,
+            "exact_target_group": item.exact_target_group,
+            "rule": item.rule,
+            "exact_rule": item.exact_rule,
+            "dir": item.dir,
+            "exact_dir": item.exact_dir,
+            "require_pass": item.require_pass(),
+            "reason_required": reason_required,
+            "reason_rule": reason_rule,
+            "permit": item.permit(),
+            "last": item.last == Some(true),
+            "notbefore": item.notbefore.map(|d| d.to_string()),
+            "notafter": item.notafter.map(|d| d.to_string()),
+            "date": date,
+            "hostname": item.hostname,
+            "exact_hostname": item.exact_hostname,
+            "tty": item.tty.as_ref().map(|t| t.to_string()),
+            "max_uses": item.max_uses.as_ref().map(|m| m.to_string()),
+            "uses_remaining": uses_remaining,
+            "shadowed_by": shadowed_by,
+        }));
+    }
+    records
+}
+
+/// the binary that a permitted command would run
+pub fn check_binary(ro: &RunOptions) -> String {
+    match &ro.cloned_args {
+        Some(args) => args[0].clone(),
+        None => ro.new_args[0].clone(),
+    }
+}
+
+/// describe whether the command in ro would be permitted by entry, as
+/// please -l command shows it. password_needed is false when a token is held
+pub fn produce_check(entry: &EnvOptions, ro: &RunOptions, password_needed: bool) -> Vec<String> {
+    if !entry.permit() {
+        return vec![format!(
+            "You may not run \"{}\" on {} as {}",
+            &ro.command, &ro.hostname, &ro.target
+        )];
+    }
+
+    let mut lines = vec![
+        format!(
+            "You may run \"{}\" on {} as {}",
+            &ro.command, &ro.hostname, &ro.target
+        ),
+        format!("  section: {}", entry.section),
+        format!("  binary: {}", check_binary(ro)),
+        format!(
+            "  password: {}",
+            if !entry.require_pass() {
+                "not required"
+            } else if password_needed {
+                "required"
+            } else {
+                "required, token held"
+            }
+        ),
+    ];
+
+    lines.push(match &entry.reason {
+        None | Some(ReasonType::Need(false)) => "  reason: not required".to_string(),
+        Some(ReasonType::Need(true)) => "  reason: required".to_string(),
+        Some(ReasonType::Text(rule)) => format!("  reason: required, matching {}", rule),
+    });
+
+    if let Some(approval) = &entry.approval {
+        lines.push(format!("  approval: required from {}", approval));
+    }
+
+    if let Some(max_uses) = &entry.max_uses {
+        lines.push(format!(
+            "  uses: {} left of {}",
+            uses_remaining(entry, &read_uses(&ro.name, entry), epoch_secs()).unwrap(),
+            max_uses
+        ));
+    }
+
+    lines
+}
+
+/// produce_check as a json record
+pub fn produce_check_json(
+    entry: &EnvOptions,
+    ro: &RunOptions,
+    password_needed: bool,
+) -> serde_json::Value {
+    if !entry.permit() {
+        return serde_json::json!({
+            "command": ro.command,
+            "hostname": ro.hostname,
+            "target": ro.target,
+            "permit": false,
+        });
+    }
+
+    let (reason_required, reason_rule) = reason_requirement(entry);
+
+    serde_json::json!({
+        "command": ro.command,
+        "hostname": ro.hostname,
+        "target": ro.target,
+        "permit": true,
+        "file": entry.file_name,
+        "section": entry.section,
+        "binary": check_binary(ro),
+        "require_pass": entry.require_pass(),
+        "password_needed": entry.require_pass() && password_needed,
+        "reason_required": reason_required,
+        "reason_rule": reason_rule,
+        "approval": entry.approval,
+        "uses_remaining": entry
+            .max_uses
+            .as_ref()
+            .and_then(|_| uses_remaining(entry, &read_uses(&ro.name, entry), epoch_secs())),
+    })
 }
 
 /// return EnvOptions as a vector of strings
@@ -1634,16 +7283,9 @@ pub fn produce_list(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<String> {
     }
 
     let mut last_file = "";
+    let entries = list_entries(vec_eo, &ro);
 
-    for item in vec_eo {
-        if !item.group && !name_matches(item, &ro, None) {
-            continue;
-        }
-
-        if item.group && !group_matches(item, &ro, None) {
-            continue;
-        }
-
+    for (item, shadowed_by) in entries.iter() {
         let mut prefixes = vec![];
         if item.notbefore.is_some() && item.notbefore.unwrap() > ro.date {
             prefixes.push(format!("upcomming({})", item.notbefore.unwrap()));
@@ -1662,22 +7304,32 @@ pub fn produce_list(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<String> {
             None => {}
         }
 
-        if item.acl_type != ro.acl_type {
-            continue;
-        }
-
         if !item.permit() {
             prefixes.push(String::from("not permitted"));
         }
 
-        if !hostname_ok(item, &ro, None) {
-            continue;
-        }
-
         if item.last.is_some() && item.last.unwrap() {
             prefixes.push(String::from("last"));
         }
 
+        if let Some(section) = shadowed_by {
+            prefixes.push(format!("shadowed(by {})", section));
+        }
+
+        match &item.tty {
+            Some(TtyMode::Required) => prefixes.push(String::from("tty_required")),
+            Some(TtyMode::Forbidden) => prefixes.push(String::from("tty_forbidden")),
+            _ => {}
+        }
+
+        if let Some(max_uses) = &item.max_uses {
+            prefixes.push(format!(
+                "uses({} left of {})",
+                uses_remaining(item, &read_uses(&ro.name, item), epoch_secs()).unwrap(),
+                max_uses
+            ));
+        }
+
         let mut prefix = prefixes.join(", ");
         if !prefix.is_empty() {
             if item.acl_type != Acltype::List {
@@ -1714,6 +7366,10 @@ pub fn produce_list(vec_eo: &[EnvOptions], ro: &RunOptions) -> Vec<String> {
     str_list
 }
 
+/// the directories searched for commands when search_path is unset
+pub const DEFAULT_SEARCH_PATH: &str =
+    "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
 /// return result from search cache lookup
 pub fn search_path_cache(ro: &RunOptions, binary: &str) -> Option<String> {
     match ro.located_bin.get(binary) {
@@ -1735,7 +7391,6 @@ pub fn search_path_cache(ro: &RunOptions, binary: &str) -> Option<String> {
 /// if binary is not an absolute/relative path, look for it in usual places
 pub fn search_path(ro: &mut RunOptions, item: &EnvOptions) -> Option<String> {
     let binary = &ro.new_args[0];
-    let p = Path::new(&binary);
     // println!("Searching for {binary}");
 
     if binary.starts_with('/') || binary.starts_with("./") {
@@ -1744,7 +7399,7 @@ pub fn search_path(ro: &mut RunOptions, item: &EnvOptions) -> Option<String> {
             return lookup;
         }
 
-        if !p.exists() {
+        if !filesystem().exists(binary) {
             ro.located_bin.insert(binary.to_string(), None);
             return None;
         } else {
@@ -1757,7 +7412,7 @@ pub fn search_path(ro: &mut RunOptions, item: &EnvOptions) -> Option<String> {
     let dirs = if item.search_path.is_some() {
         item.search_path.as_ref().unwrap()
     } else {
-        "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"
+        DEFAULT_SEARCH_PATH
     };
 
     for dir in dirs.split(':') {
@@ -1771,9 +7426,7 @@ pub fn search_path(ro: &mut RunOptions, item: &EnvOptions) -> Option<String> {
             return Some(lookup);
         }
 
-        let p = Path::new(&path_name);
-
-        if !p.exists() {
+        if !filesystem().exists(&path_name) {
             ro.located_bin.insert(binary.to_string(), None);
             continue;
         }
@@ -1890,7 +7543,7 @@ pub fn set_environment(
 }
 
 pub fn bad_priv_msg() {
-    println!("I cannot set privs. Exiting as not installed correctly.");
+    report("I cannot set privs. Exiting as not installed correctly.");
 }
 
 /// set privs of usr to target_uid and target_gid. return false if fails
@@ -1966,18 +7619,181 @@ pub fn tty_name() -> Option<String> {
             continue;
         }
 
-        let s;
-        unsafe {
-            s = CStr::from_ptr(ptr).to_str();
+        let s;
+        unsafe {
+            s = CStr::from_ptr(ptr).to_str();
+        }
+        match s {
+            Err(_x) => ttyname = None,
+            Ok(x) => ttyname = Some(x.to_string()),
+        }
+        break;
+    }
+
+    ttyname
+}
+
+/// return the remote host recorded in utmp for the tty, Some("") if the tty
+/// has an entry without one
+#[cfg(target_os = "linux")]
+pub fn utmp_host(tty: &str) -> Option<String> {
+    fn c_chars(chars: &[libc::c_char]) -> String {
+        let bytes: Vec<u8> = chars
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as u8)
+            .collect();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    let line = tty.trim_start_matches("/dev/");
+    let mut host = None;
+
+    unsafe {
+        libc::setutxent();
+        loop {
+            let entry = libc::getutxent();
+            if entry.is_null() {
+                break;
+            }
+            if (*entry).ut_type != libc::USER_PROCESS || c_chars(&(*entry).ut_line) != line {
+                continue;
+            }
+            host = Some(c_chars(&(*entry).ut_host));
+        }
+        libc::endutxent();
+    }
+
+    host
+}
+
+/// utmp is not read on this platform
+#[cfg(not(target_os = "linux"))]
+pub fn utmp_host(_tty: &str) -> Option<String> {
+    None
+}
+
+/// is pid running a root owned sshd, a copy the user made could hold any peer
+#[cfg(target_os = "linux")]
+fn sshd_process(pid: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let exe = match fs::read_link(format!("/proc/{}/exe", pid)) {
+        Ok(exe) => exe,
+        Err(_) => return false,
+    };
+    let named = match exe.file_name() {
+        Some(name) => name.to_string_lossy().starts_with("sshd"),
+        None => false,
+    };
+    match fs::metadata(&exe) {
+        Ok(meta) => named && meta.uid() == 0 && meta.mode() & 0o022 == 0,
+        Err(_) => false,
+    }
+}
+
+/// decode an address from /proc/net/tcp or tcp6, held as host order words
+pub fn proc_net_addr(field: &str) -> Option<IpAddr> {
+    let hex = field.split(':').next()?;
+    if hex.len() % 8 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for i in 0..hex.len() / 8 {
+        let word = u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16).ok()?;
+        bytes
+            .get_mut(i * 4..i * 4 + 4)?
+            .copy_from_slice(&word.to_ne_bytes());
+    }
+    match hex.len() {
+        8 => Some(IpAddr::from([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        32 => Some(IpAddr::from(bytes).to_canonical()),
+        _ => None,
+    }
+}
+
+/// the remote address of an established tcp socket held by pid
+#[cfg(target_os = "linux")]
+fn process_tcp_peer(pid: u32) -> Option<String> {
+    let mut inodes = vec![];
+    for fd in fs::read_dir(format!("/proc/{}/fd", pid)).ok()?.flatten() {
+        if let Ok(link) = fs::read_link(fd.path()) {
+            let link = link.to_string_lossy();
+            if let Some(inode) = link
+                .strip_prefix("socket:[")
+                .and_then(|l| l.strip_suffix(']'))
+            {
+                inodes.push(inode.to_string());
+            }
+        }
+    }
+
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let text = match fs::read_to_string(table) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        for line in text.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // established connections only, not the daemon's listener
+            if fields.len() < 10 || fields[3] != "01" || !inodes.iter().any(|i| i == fields[9]) {
+                continue;
+            }
+            if let Some(addr) = proc_net_addr(fields[2]) {
+                return Some(addr.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// return the peer address of the sshd session we run under, found by walking
+/// our ancestors and asking the kernel for their sockets
+#[cfg(target_os = "linux")]
+pub fn ssh_peer() -> Option<String> {
+    let mut pid = std::process::id();
+    while pid > 1 {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // the command may hold spaces or brackets, the parent follows the state
+        let ppid: u32 = stat[stat.rfind(')')? + 1..]
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()?;
+        if sshd_process(pid) {
+            if let Some(peer) = process_tcp_peer(pid) {
+                return Some(peer);
+            }
         }
-        match s {
-            Err(_x) => ttyname = None,
-            Ok(x) => ttyname = Some(x.to_string()),
+        pid = ppid;
+    }
+    None
+}
+
+/// sockets are not read on this platform
+#[cfg(not(target_os = "linux"))]
+pub fn ssh_peer() -> Option<String> {
+    None
+}
+
+/// return where the user is connecting from. the tty's utmp entry is used,
+/// then the peer of the sshd session. "local" for console sessions and
+/// "unknown" when neither can tell. the environment is not read, the user
+/// can set it to anything
+pub fn connection_source(tty: Option<&str>) -> String {
+    if let Some(tty) = tty {
+        match utmp_host(tty) {
+            Some(host) if host.is_empty() || host.starts_with(':') => {
+                return "local".to_string();
+            }
+            Some(host) => return host,
+            None => {}
         }
-        break;
     }
 
-    ttyname
+    match ssh_peer() {
+        Some(peer) => peer,
+        None => "unknown".to_string(),
+    }
 }
 
 /// add a level of escape to strings when they go to the old as " holds entities
@@ -2011,7 +7827,7 @@ pub fn log_action(service: &str, result: &str, ro: &RunOptions, command: &str) -
     };
 
     match syslog::unix(formatter) {
-        Err(_e) => println!("Could not connect to syslog"),
+        Err(_e) => report("Could not connect to syslog"),
         Ok(mut writer) => {
             let tty_name = tty_name();
 
@@ -2063,7 +7879,7 @@ pub fn token_path(user: &str) -> Option<String> {
 
 pub fn create_token_dir() -> bool {
     if !Path::new(&token_dir()).is_dir() && fs::create_dir_all(token_dir()).is_err() {
-        println!("Could not create token directory");
+        report("Could not create token directory");
         return false;
     }
 
@@ -2153,7 +7969,7 @@ pub fn update_token(user: &str) {
     let token_path_tmp = format!("{}.tmp", &token_path);
     match fs::File::create(&token_path_tmp) {
         Ok(_x) => {}
-        Err(x) => println!("Error creating token: {}", x),
+        Err(x) => report(&format!("Error creating token: {}", x)),
     }
     nix::sys::stat::umask(old_mode);
 
@@ -2179,7 +7995,7 @@ pub fn update_token(user: &str) {
     }
 
     if std::fs::rename(token_path_tmp.as_str(), token_path).is_err() {
-        println!("Could not update token");
+        report("Could not update token");
     }
 }
 
@@ -2200,9 +8016,603 @@ pub fn remove_token(user: &str) {
     if p.is_file() {
         match fs::remove_file(p) {
             Ok(_x) => {}
-            Err(x) => println!("Error removing token {}: {}", p.to_str().unwrap(), x),
+            Err(x) => report(&format!(
+                "Error removing token {}: {}",
+                p.to_str().unwrap(),
+                x
+            )),
+        }
+    }
+}
+
+/// return the directory that failed password attempts are recorded in
+pub fn failure_dir() -> String {
+    "/var/run/please/failures".to_string()
+}
+
+/// return the path of the users failure record
+pub fn failure_path(user: &str) -> String {
+    format!("{}/{}", failure_dir(), user.replace('/', "_"))
+}
+
+/// return the current wall time in seconds
+pub fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// return the number of seconds the lockout has left to run, or None if there
+/// have not been lockout failures within lockout_window of each other
+pub fn lockout_remaining(entry: &EnvOptions, failures: &[u64], now: u64) -> Option<u64> {
+    let lockout = entry.lockout?;
+    if lockout == 0 {
+        return None;
+    }
+
+    let window = entry.lockout_window.unwrap_or(600);
+    let lockout_time = entry.lockout_time.unwrap_or(600);
+
+    let mut failures: Vec<u64> = failures.iter().filter(|t| **t <= now).copied().collect();
+    failures.sort_unstable();
+
+    // the most recent failure that completed a run of lockout failures
+    let locked_at = failures
+        .iter()
+        .enumerate()
+        .filter(|(pos, t)| {
+            *pos + 1 >= lockout as usize && **t - failures[*pos + 1 - lockout as usize] < window
+        })
+        .map(|(_, t)| *t)
+        .max()?;
+
+    if now - locked_at >= lockout_time {
+        return None;
+    }
+
+    Some(lockout_time - (now - locked_at))
+}
+
+/// parse the failure times of a record
+fn parse_failures(data: &str) -> Vec<u64> {
+    data.split('\n')
+        .filter_map(|l| l.trim().parse::<u64>().ok())
+        .collect()
+}
+
+/// read the failure times for user, privs should already be escalated
+pub fn read_failures(user: &str) -> Vec<u64> {
+    let file = match File::open(failure_path(user)) {
+        Ok(f) => f,
+        Err(_) => return vec![],
+    };
+
+    // wait for a failure being recorded, so that it is not read half written
+    let mut file = match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockShared) {
+        Ok(f) => f,
+        Err(_) => return vec![],
+    };
+
+    let mut data = String::new();
+    match file.read_to_string(&mut data) {
+        Ok(_) => parse_failures(&data),
+        Err(_) => vec![],
+    }
+}
+
+/// open and lock the failure record of user, creating it if need be
+fn lock_failures(user: &str) -> Option<nix::fcntl::Flock<File>> {
+    let old_mode = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o077).unwrap());
+    if !Path::new(&failure_dir()).is_dir() && fs::create_dir_all(failure_dir()).is_err() {
+        nix::sys::stat::umask(old_mode);
+        report("Could not create failure directory");
+        return None;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    let file = options.open(failure_path(user));
+    nix::sys::stat::umask(old_mode);
+
+    let file = match file {
+        Ok(f) => f,
+        Err(x) => {
+            report(&format!("Could not open failure record: {}", x));
+            return None;
+        }
+    };
+
+    match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockExclusive) {
+        Ok(f) => Some(f),
+        Err((_, x)) => {
+            report(&format!("Could not lock failure record: {}", x));
+            None
+        }
+    }
+}
+
+/// append a failure for user, discarding those too old to cause a lockout,
+/// and return the failures on record. None when the failure could not be
+/// recorded, which should be treated as a lockout. the record is locked while
+/// it is updated, privs should already be escalated
+pub fn record_failure(user: &str, entry: &EnvOptions) -> Option<Vec<u64>> {
+    let mut file = lock_failures(user)?;
+
+    let mut data = String::new();
+    if file.read_to_string(&mut data).is_err() {
+        report("Could not read failure record");
+        return None;
+    }
+
+    let now = epoch_secs();
+    let window = entry.lockout_window.unwrap_or(600) + entry.lockout_time.unwrap_or(600);
+
+    let mut failures: Vec<u64> = parse_failures(&data)
+        .into_iter()
+        .filter(|t| *t <= now && now - *t < window)
+        .collect();
+    failures.push(now);
+
+    let data: Vec<String> = failures.iter().map(|t| t.to_string()).collect();
+    if file.set_len(0).is_err()
+        || file.seek(std::io::SeekFrom::Start(0)).is_err()
+        || file
+            .write_all(format!("{}\n", data.join("\n")).as_bytes())
+            .is_err()
+    {
+        report("Could not record authentication failure");
+        return None;
+    }
+
+    Some(failures)
+}
+
+/// empty the failure record for user, privs should already be escalated. the
+/// record is emptied under its lock rather than removed so that a failure
+/// being recorded at the same time is not lost
+pub fn remove_failures(user: &str) {
+    let path = failure_path(user);
+    if !Path::new(&path).is_file() {
+        return;
+    }
+
+    match lock_failures(user) {
+        Some(file) if file.set_len(0).is_ok() => {}
+        _ => report(&format!("Could not remove failure record {}", path)),
+    }
+}
+
+/// return true if the user is locked out of authenticating for entry
+pub fn locked_out(ro: &RunOptions, entry: &EnvOptions) -> Result<bool, PleaseError> {
+    if !entry.require_pass() || entry.lockout.is_none() {
+        return Ok(false);
+    }
+
+    try_esc_privs()?;
+    let failures = read_failures(&ro.name);
+    try_drop_privs(ro)?;
+
+    match lockout_remaining(entry, &failures, epoch_secs()) {
+        Some(remaining) => {
+            report(&format!(
+                "Too many authentication failures, try again in {} seconds",
+                remaining
+            ));
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// return the directory that max_uses counters are kept in
+pub fn uses_dir() -> String {
+    "/var/run/please/uses".to_string()
+}
+
+/// return the path of the users counter for the section
+pub fn uses_path(user: &str, entry: &EnvOptions) -> String {
+    format!(
+        "{}/{}:{}:{}",
+        uses_dir(),
+        user.replace('/', "_"),
+        entry.file_name.replace('/', "_"),
+        entry.section.replace('/', "_")
+    )
+}
+
+/// return the uses left within the period, None if there is no max_uses
+pub fn uses_remaining(entry: &EnvOptions, uses: &[u64], now: u64) -> Option<u32> {
+    let max_uses = entry.max_uses.as_ref()?;
+    let recent = uses
+        .iter()
+        .filter(|t| **t <= now && now - **t < max_uses.period)
+        .count();
+
+    Some(max_uses.count.saturating_sub(recent as u32))
+}
+
+/// read the use times for user in the section
+pub fn read_uses(user: &str, entry: &EnvOptions) -> Vec<u64> {
+    match fs::read_to_string(uses_path(user, entry)) {
+        Ok(s) => s
+            .split('\n')
+            .filter_map(|l| l.trim().parse::<u64>().ok())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// true if user has a use left in the section, nothing is recorded
+pub fn uses_left(user: &str, entry: &EnvOptions) -> bool {
+    uses_remaining(entry, &read_uses(user, entry), epoch_secs()) != Some(0)
+}
+
+/// record a use for user if there is quota left, return false if exhausted.
+/// the counter is locked while it is updated, privs should already be escalated
+pub fn consume_use(user: &str, entry: &EnvOptions) -> bool {
+    let max_uses = match &entry.max_uses {
+        Some(m) => m,
+        None => return true,
+    };
+
+    let old_mode = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o077).unwrap());
+    if !Path::new(&uses_dir()).is_dir() && fs::create_dir_all(uses_dir()).is_err() {
+        nix::sys::stat::umask(old_mode);
+        report("Could not create uses directory");
+        return false;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    let file = options.open(uses_path(user, entry));
+    nix::sys::stat::umask(old_mode);
+
+    let file = match file {
+        Ok(f) => f,
+        Err(x) => {
+            report(&format!("Could not open uses counter: {}", x));
+            return false;
+        }
+    };
+
+    let mut file = match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockExclusive) {
+        Ok(f) => f,
+        Err((_, x)) => {
+            report(&format!("Could not lock uses counter: {}", x));
+            return false;
+        }
+    };
+
+    let mut data = String::new();
+    if file.read_to_string(&mut data).is_err() {
+        report("Could not read uses counter");
+        return false;
+    }
+
+    let now = epoch_secs();
+    let mut uses: Vec<u64> = data
+        .split('\n')
+        .filter_map(|l| l.trim().parse::<u64>().ok())
+        .filter(|t| *t <= now && now - *t < max_uses.period)
+        .collect();
+
+    if uses_remaining(entry, &uses, now) == Some(0) {
+        return false;
+    }
+
+    uses.push(now);
+    let data: Vec<String> = uses.iter().map(|t| t.to_string()).collect();
+
+    if file.set_len(0).is_err()
+        || file.seek(std::io::SeekFrom::Start(0)).is_err()
+        || file
+            .write_all(format!("{}\n", data.join("\n")).as_bytes())
+            .is_err()
+    {
+        report("Could not update uses counter");
+        return false;
+    }
+
+    true
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApprovalStatus::Pending => write!(f, "pending"),
+            ApprovalStatus::Approved => write!(f, "approved"),
+            ApprovalStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// a request held in the spool awaiting a second person
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub user: String,
+    pub target: String,
+    pub target_group: Option<String>,
+    pub command: String,
+    pub directory: Option<String>,
+    pub reason: Option<String>,
+    pub section: String,
+    pub approval: String,
+    pub created: u64,
+    pub expires: u64,
+    pub status: ApprovalStatus,
+    pub approver: Option<String>,
+}
+
+/// escape '\' and new lines so that a value sits on one line
+fn escape_spool_value(value: &str) -> String {
+    value.replace('\\', r"\\").replace('\n', r"\n")
+}
+
+fn unescape_spool_value(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(x) => out.push(x),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+impl fmt::Display for ApprovalRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = vec![
+            ("id", Some(self.id.clone())),
+            ("user", Some(self.user.clone())),
+            ("target", Some(self.target.clone())),
+            ("target_group", self.target_group.clone()),
+            ("command", Some(self.command.clone())),
+            ("directory", self.directory.clone()),
+            ("reason", self.reason.clone()),
+            ("section", Some(self.section.clone())),
+            ("approval", Some(self.approval.clone())),
+            ("created", Some(self.created.to_string())),
+            ("expires", Some(self.expires.to_string())),
+            ("status", Some(self.status.to_string())),
+            ("approver", self.approver.clone()),
+        ];
+
+        for (k, v) in fields {
+            if let Some(v) = v {
+                writeln!(f, "{}={}", k, escape_spool_value(&v))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ApprovalRequest {
+    /// build a pending request from the requester's RunOptions and matching entry
+    pub fn new(ro: &RunOptions, entry: &EnvOptions) -> ApprovalRequest {
+        let created = epoch_secs();
+        ApprovalRequest {
+            id: prng_alpha_num_string(12),
+            user: ro.name.clone(),
+            target: ro.target.clone(),
+            target_group: ro.target_group.clone(),
+            command: ro.command.clone(),
+            directory: ro.directory.clone(),
+            reason: ro.reason.clone(),
+            section: format!("{}:{}", entry.file_name, entry.section),
+            approval: entry.approval.clone().unwrap_or_default(),
+            created,
+            expires: created + entry.approval_timeout.unwrap_or(86400),
+            status: ApprovalStatus::Pending,
+            approver: None,
+        }
+    }
+
+    /// parse a spooled request, returning None if it is incomplete
+    pub fn parse(data: &str) -> Option<ApprovalRequest> {
+        let mut map: HashMap<&str, String> = HashMap::new();
+        for line in data.split('\n') {
+            if let Some(pos) = line.find('=') {
+                map.insert(&line[0..pos], unescape_spool_value(&line[pos + 1..]));
+            }
+        }
+
+        Some(ApprovalRequest {
+            id: map.get("id")?.clone(),
+            user: map.get("user")?.clone(),
+            target: map.get("target")?.clone(),
+            target_group: map.get("target_group").cloned(),
+            command: map.get("command")?.clone(),
+            directory: map.get("directory").cloned(),
+            reason: map.get("reason").cloned(),
+            section: map.get("section")?.clone(),
+            approval: map.get("approval")?.clone(),
+            created: map.get("created")?.parse().ok()?,
+            expires: map.get("expires")?.parse().ok()?,
+            status: match map.get("status")?.as_str() {
+                "pending" => ApprovalStatus::Pending,
+                "approved" => ApprovalStatus::Approved,
+                "rejected" => ApprovalStatus::Rejected,
+                _ => return None,
+            },
+            approver: map.get("approver").cloned(),
+        })
+    }
+
+    /// is this a request for what ro is asking to run
+    pub fn matches(&self, ro: &RunOptions) -> bool {
+        self.user == ro.name
+            && self.target == ro.target
+            && self.target_group == ro.target_group
+            && self.command == ro.command
+            && self.directory == ro.directory
+    }
+
+    /// has the request passed its approval_timeout
+    pub fn expired(&self, now: u64) -> bool {
+        now >= self.expires
+    }
+}
+
+/// return the directory that approval requests are spooled in
+pub fn approval_dir() -> String {
+    "/var/spool/please/approval".to_string()
+}
+
+/// return the path of a spooled request, None if the id is not well formed
+pub fn approval_path(id: &str) -> Option<String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(format!("{}/{}", approval_dir(), id))
+}
+
+pub fn create_approval_dir() -> bool {
+    if Path::new(&approval_dir()).is_dir() {
+        return true;
+    }
+
+    let old_mode = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o077).unwrap());
+    let res = fs::create_dir_all(approval_dir());
+    nix::sys::stat::umask(old_mode);
+
+    if res.is_err() {
+        report("Could not create approval directory");
+        return false;
+    }
+    true
+}
+
+/// write a request to the spool, privs should already be escalated
+pub fn write_approval(req: &ApprovalRequest) -> bool {
+    if !create_approval_dir() {
+        return false;
+    }
+
+    let path = match approval_path(&req.id) {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let old_mode = nix::sys::stat::umask(nix::sys::stat::Mode::from_bits(0o077).unwrap());
+    let path_tmp = format!("{}.tmp", &path);
+    let res = fs::write(&path_tmp, req.to_string()).is_ok() && fs::rename(&path_tmp, &path).is_ok();
+    nix::sys::stat::umask(old_mode);
+
+    if !res {
+        report(&format!("Could not write approval request {}", req.id));
+        let _ = fs::remove_file(&path_tmp);
+    }
+    res
+}
+
+/// read a request from the spool, privs should already be escalated
+pub fn read_approval(id: &str) -> Option<ApprovalRequest> {
+    let data = fs::read_to_string(approval_path(id)?).ok()?;
+    let req = ApprovalRequest::parse(&data)?;
+    if req.id != id {
+        return None;
+    }
+    Some(req)
+}
+
+/// take a decided request out of the spool so that it is used once. the
+/// rename succeeds for one caller only, everyone else gets None. privs should
+/// already be escalated
+pub fn claim_approval(id: &str) -> Option<ApprovalRequest> {
+    let path = approval_path(id)?;
+    let claimed = format!("{}.{}.claimed", &path, std::process::id());
+    if fs::rename(&path, &claimed).is_err() {
+        report(&format!("Request {} has already been used", id));
+        return None;
+    }
+
+    let req = fs::read_to_string(&claimed)
+        .ok()
+        .and_then(|data| ApprovalRequest::parse(&data));
+    if fs::remove_file(&claimed).is_err() {
+        report(&format!("Could not remove approval request {}", id));
+    }
+
+    let req = req.filter(|r| r.id == id && r.status != ApprovalStatus::Pending)?;
+    if req.expired(epoch_secs()) {
+        report(&format!("Request {} has expired", id));
+        return None;
+    }
+    Some(req)
+}
+
+/// return the newest spooled request that matches ro, preferring decided
+/// requests over pending ones. expired requests are passed over
+pub fn find_approval(ro: &RunOptions) -> Option<ApprovalRequest> {
+    let mut found: Option<ApprovalRequest> = None;
+    let now = epoch_secs();
+
+    for file in fs::read_dir(approval_dir()).ok()? {
+        let name = match file {
+            Ok(f) => f.file_name().to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+
+        let req = match read_approval(&name) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        if !req.matches(ro) || req.expired(now) {
+            continue;
         }
+
+        found = match found {
+            None => Some(req),
+            Some(f) => {
+                let f_decided = f.status != ApprovalStatus::Pending;
+                let r_decided = req.status != ApprovalStatus::Pending;
+                if r_decided && !f_decided || (r_decided == f_decided && req.created > f.created) {
+                    Some(req)
+                } else {
+                    Some(f)
+                }
+            }
+        };
+    }
+
+    found
+}
+
+/// may ro approve or reject req. the requester may not approve their own
+/// request and the approver must be in a group matching the approval regex.
+/// %{USER} in the regex is the requester, as when their section was read
+pub fn approver_ok(req: &ApprovalRequest, ro: &RunOptions) -> bool {
+    if req.user == ro.name {
+        return false;
     }
+
+    let mut req_ro = ro.clone();
+    req_ro.name.clone_from(&req.user);
+    let approval_re = match regex_build(&req.approval, &req_ro, &req.section, &req.section, None) {
+        Some(check) => check,
+        None => {
+            report(&format!("Could not compile {}", &req.approval));
+            return false;
+        }
+    };
+
+    ro.groups.keys().any(|g| approval_re.is_match(g))
 }
 
 /// turn group list into an indexed list
@@ -2228,7 +8638,11 @@ pub fn replace_new_args(new_args: Vec<String>) -> String {
 
 /// print version string
 pub fn print_version(program: &str) {
-    println!("{} version {}", &program, env!("CARGO_PKG_VERSION"));
+    report(&format!(
+        "{} version {}",
+        &program,
+        env!("CARGO_PKG_VERSION")
+    ));
 }
 
 /// return a lump of random alpha numeric characters
@@ -2240,22 +8654,16 @@ pub fn prng_alpha_num_string(n: usize) -> String {
         .collect()
 }
 
-pub fn runopt_target_gid(ro: &RunOptions, lookup_name: &uzers::User) -> nix::unistd::Gid {
-    if ro.target_group.is_some() {
-        match nix::unistd::Group::from_name(ro.target_group.as_ref().unwrap()) {
-            Ok(x) => match x {
-                Some(g) => g.gid,
-                None => {
-                    println!("Cannot assign group {}", &ro.target_group.as_ref().unwrap());
-                    std::process::exit(1);
-                }
-            },
-            Err(_) => {
-                println!("Cannot assign group {}", &ro.target_group.as_ref().unwrap());
-                std::process::exit(1);
-            }
-        }
-    } else {
-        nix::unistd::Gid::from_raw(lookup_name.primary_group_id())
+/// the gid to become, the target group or the primary group of the target
+pub fn runopt_target_gid(
+    ro: &RunOptions,
+    lookup_name: &uzers::User,
+) -> Result<nix::unistd::Gid, PleaseError> {
+    match &ro.target_group {
+        Some(target_group) => match identity().group_by_name(target_group) {
+            Some(g) => Ok(nix::unistd::Gid::from_raw(g.gid())),
+            None => Err(PleaseError::Group(target_group.clone())),
+        },
+        None => Ok(nix::unistd::Gid::from_raw(lookup_name.primary_group_id())),
     }
 }
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn approval_entry(ro: &mut RunOptions) -> EnvOptions {
+        let config = "[prod_db]
+exact_name=ed
+exact_rule = /bin/bash
+approval = ^dba$
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+
+        assert!(
+            read_ini_config_str(&config, &mut vec_eo, ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        can(&vec_eo, ro)
+    }
+
+    #[test]
+    fn test_approval_parse() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let entry = approval_entry(&mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.approval, Some("^dba$".to_string()));
+    }
+
+    #[test]
+    fn test_approval_edit_refused() {
+        let config = "[hosts]
+exact_name=ed
+approval = ^dba$
+exact_rule = /etc/hosts
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).unwrap();
+
+        // rules that reach pleaseedit with approval set are refused, not edited
+        for eo in vec_eo.iter_mut() {
+            eo.acl_type = Acltype::Edit;
+        }
+        ro.acl_type = Acltype::Edit;
+        ro.syslog = false;
+        ro.new_args = vec!["/etc/hosts".to_string()];
+
+        match edit_targets(&ro, &vec_eo, "pleaseedit") {
+            Err(PleaseError::Denied(message)) => assert_eq!(
+                message,
+                "Edits of \"/etc/hosts\" need approval, which pleaseedit does not support"
+            ),
+            _ => panic!("edit with approval was not refused"),
+        }
+    }
+
+    #[test]
+    fn test_approval_request_round_trip() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let entry = approval_entry(&mut ro);
+        ro.reason = Some("ticket 42\nsecond line with a \\".to_string());
+
+        let req = ApprovalRequest::new(&ro, &entry);
+        assert_eq!(req.status, ApprovalStatus::Pending);
+        assert_eq!(req.section, "static:prod_db");
+        assert_eq!(req.approval, "^dba$");
+        assert!(req.matches(&ro));
+
+        let parsed = ApprovalRequest::parse(&req.to_string());
+        assert_eq!(parsed, Some(req));
+    }
+
+    #[test]
+    fn test_approval_request_incomplete() {
+        assert_eq!(ApprovalRequest::parse("id=abc\nuser=ed\n"), None);
+    }
+
+    #[test]
+    fn test_approval_request_mismatch() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let entry = approval_entry(&mut ro);
+        let req = ApprovalRequest::new(&ro, &entry);
+
+        let mut other = ro.clone();
+        other.command = "/bin/sh".to_string();
+        assert!(!req.matches(&other));
+
+        let mut other = ro.clone();
+        other.target = "oracle".to_string();
+        assert!(!req.matches(&other));
+    }
+
+    #[test]
+    fn test_approver() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        ro.groups.insert("dba".to_string(), 1000);
+        let entry = approval_entry(&mut ro);
+        let req = ApprovalRequest::new(&ro, &entry);
+
+        // cannot approve your own request
+        assert!(!approver_ok(&req, &ro));
+
+        let mut approver = basic_ro("ben", "root");
+        assert!(!approver_ok(&req, &approver));
+
+        approver.groups.insert("dba".to_string(), 1000);
+        assert!(approver_ok(&req, &approver));
+    }
+
+    #[test]
+    fn test_approver_requester_user() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let mut entry = approval_entry(&mut ro);
+        entry.approval = Some("%{USER}_leads".to_string());
+        let req = ApprovalRequest::new(&ro, &entry);
+
+        // %{USER} is the requester, not the approver
+        let mut approver = basic_ro("ben", "root");
+        approver.groups.insert("ben_leads".to_string(), 1000);
+        assert!(!approver_ok(&req, &approver));
+
+        approver.groups.insert("ed_leads".to_string(), 1001);
+        assert!(approver_ok(&req, &approver));
+    }
+
+    #[test]
+    fn test_approval_expiry() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let mut entry = approval_entry(&mut ro);
+
+        let req = ApprovalRequest::new(&ro, &entry);
+        assert_eq!(req.expires, req.created + 86400);
+        assert!(!req.expired(req.created + 86399));
+        assert!(req.expired(req.created + 86400));
+
+        entry.approval_timeout = Some(60);
+        let req = ApprovalRequest::new(&ro, &entry);
+        assert!(req.expired(req.created + 60));
+    }
+
+    /// spool an approved request for ed, privs are already escalated
+    fn spool_approved(expires: u64) -> ApprovalRequest {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let entry = approval_entry(&mut ro);
+        let mut req = ApprovalRequest::new(&ro, &entry);
+        req.status = ApprovalStatus::Approved;
+        req.approver = Some("ben".to_string());
+        req.expires = expires;
+        assert!(write_approval(&req));
+        req
+    }
+
+    #[test]
+    fn test_approval_claimed_once() {
+        // the spool is root's
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let req = spool_approved(epoch_secs() + 60);
+        let claims: Vec<std::thread::JoinHandle<Option<ApprovalRequest>>> = (0..8)
+            .map(|_| {
+                let id = req.id.clone();
+                std::thread::spawn(move || claim_approval(&id))
+            })
+            .collect();
+        let claimed: Vec<ApprovalRequest> = claims
+            .into_iter()
+            .filter_map(|c| c.join().unwrap())
+            .collect();
+
+        assert_eq!(claimed, vec![req.clone()]);
+        assert_eq!(read_approval(&req.id), None);
+        assert_eq!(claim_approval(&req.id), None);
+    }
+
+    #[test]
+    fn test_approval_other_command() {
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        // an approval only lets the approved command run
+        let req = spool_approved(epoch_secs() + 60);
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/sh");
+        ro.command = "/bin/sh".to_string();
+        assert!(find_approval(&ro).map(|r| r.id) != Some(req.id.clone()));
+
+        let mut ro = basic_ro("ben", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(find_approval(&ro).map(|r| r.id) != Some(req.id.clone()));
+
+        assert_eq!(claim_approval(&req.id), Some(req));
+    }
+
+    #[test]
+    fn test_approval_claim_expired() {
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let req = spool_approved(epoch_secs() - 1);
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(find_approval(&ro).map(|r| r.id) != Some(req.id.clone()));
+        assert_eq!(claim_approval(&req.id), None);
+        assert_eq!(read_approval(&req.id), None);
+    }
+
+    #[test]
+    fn test_approval_path() {
+        assert_eq!(approval_path("../../etc/shadow"), None);
+        assert_eq!(approval_path(""), None);
+        assert_eq!(
+            approval_path("abc123"),
+            Some(format!("{}/abc123", approval_dir()))
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use chrono::{TimeZone, Utc};
+    use pleaser::*;
+
+    fn backup(version: &str, user: &str) -> Backup {
+        parse_backup_name(
+            "/var/backup",
+            &backup_name("/etc/nginx/nginx.conf", version, user),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_backup_name() {
+        let name = backup_name("/etc/a@b%2F/c", "20201231T235959.000001Z", "ed");
+        assert_eq!(name, "%2Fetc%2Fa%40b%252F%2Fc@20201231T235959.000001Z@ed");
+        assert!(!name.contains('/'));
+
+        assert_eq!(
+            parse_backup_name("/var/backup", &name),
+            Some(Backup {
+                path: format!("/var/backup/{}", name),
+                source: "/etc/a@b%2F/c".to_string(),
+                version: "20201231T235959.000001Z".to_string(),
+                user: "ed".to_string(),
+            })
+        );
+        assert_eq!(parse_backup_name("/var/backup", "unrelated"), None);
+        assert_eq!(parse_backup_name("/var/backup", "a@@ed"), None);
+    }
+
+    #[test]
+    fn test_backup_version() {
+        let time = Utc.ymd(2020, 1, 2).and_hms(3, 4, 5);
+        assert_eq!(backup_version(&time), "20200102T030405.000000Z");
+    }
+
+    #[test]
+    fn test_find_backup() {
+        let backups = vec![
+            backup("20200101T000000.000000Z", "ed"),
+            backup("20200102T000000.000000Z", "ben"),
+            backup("20200102T120000.000000Z", "ed"),
+        ];
+
+        assert_eq!(find_backup(&backups, None).unwrap().user, "ed");
+        assert_eq!(
+            find_backup(&backups, Some("20200102T000000.000000Z"))
+                .unwrap()
+                .user,
+            "ben"
+        );
+        assert_eq!(find_backup(&backups, Some("20200101")).unwrap().user, "ed");
+        assert_eq!(
+            find_backup(&backups, Some("20200102")),
+            Err("Backup version 20200102 is ambiguous".to_string())
+        );
+        assert_eq!(
+            find_backup(&backups, Some("2019")),
+            Err("There is no backup version 2019".to_string())
+        );
+        assert_eq!(
+            find_backup(&[], None),
+            Err("There are no backups".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expired_backups() {
+        let backups = vec![
+            backup("20200101T000000.000000Z", "ed"),
+            backup("20200102T000000.000000Z", "ed"),
+            backup("20200103T000000.000000Z", "ed"),
+        ];
+
+        assert_eq!(expired_backups(&backups, 5), &[]);
+        assert_eq!(expired_backups(&backups, 3), &[]);
+        assert_eq!(expired_backups(&backups, 1), &backups[..2]);
+    }
+
+    #[test]
+    fn test_list_backups() {
+        let dir = std::env::temp_dir().join(format!("please_backup_{}", std::process::id()));
+        std::fs::create_dir(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        for (source, version) in [
+            ("/etc/hosts", "20200102T000000.000000Z"),
+            ("/etc/hosts", "20200101T000000.000000Z"),
+            ("/etc/hosts.allow", "20200103T000000.000000Z"),
+        ] {
+            std::fs::write(dir.join(backup_name(source, version, "ed")), "").unwrap();
+        }
+        std::fs::write(dir.join("stray"), "").unwrap();
+
+        let backups = list_backups(dir_str, "/etc/hosts");
+        assert_eq!(
+            backups
+                .iter()
+                .map(|b| b.version.as_str())
+                .collect::<Vec<&str>>(),
+            ["20200101T000000.000000Z", "20200102T000000.000000Z"]
+        );
+        assert_eq!(list_backups("/nonexistent/please", "/etc/hosts"), []);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_rollback() {
+        assert_eq!(
+            split_rollback("/etc/hosts"),
+            ("/etc/hosts".to_string(), None)
+        );
+        assert_eq!(
+            split_rollback("/etc/hosts@20200101T0000"),
+            ("/etc/hosts".to_string(), Some("20200101T0000".to_string()))
+        );
+        assert_eq!(
+            split_rollback("/srv/user@example.com"),
+            ("/srv/user@example.com".to_string(), None)
+        );
+        assert_eq!(split_rollback("/srv/a@"), ("/srv/a@".to_string(), None));
+    }
+
+    #[test]
+    fn test_backup_config() {
+        let config = "[etc_edit]
+exact_name = ed
+type = edit
+rule = ^/etc/.*$
+backup = /var/lib/please/backup/
+backup_keep = 3
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.acl_type = Acltype::Edit;
+        basic_cmd(&mut ro, "/etc/hosts");
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.backup, Some("/var/lib/please/backup".to_string()));
+        assert_eq!(entry.backup_keep, Some(3));
+    }
+
+    #[test]
+    fn test_backup_config_error() {
+        for bad in ["backup = backup", "backup_keep = 0", "backup_keep = lots"] {
+            let config = format!(
+                "[etc_edit]
+exact_name = ed
+type = edit
+rule = ^/etc/.*$
+{}
+",
+                bad
+            );
+            let mut bytes = 0;
+            let mut ini_list: HashMap<String, bool> = HashMap::new();
+            let mut vec_eo: Vec<EnvOptions> = vec![];
+            let ro = basic_ro("ed", "root");
+
+            assert!(read_ini_config_str(
+                &config,
+                &mut vec_eo,
+                &ro,
+                true,
+                &mut bytes,
+                &mut ini_list
+            )
+            .is_err());
+        }
+    }
+}
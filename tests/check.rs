@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    const CONFIG: &str = "
+[true]
+name = ed
+target = root
+search_path = /bin:/usr/bin
+rule = ^/(usr/)?bin/true$
+require_pass = false
+approval = ^admins$
+
+[uptime]
+name = ed
+target = root
+rule = ^/usr/bin/uptime( -p)?$
+require_pass = true
+reason = ^CHG[0-9]+$
+";
+
+    fn check(cmd: &str) -> (EnvOptions, RunOptions) {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, cmd);
+        assert!(
+            read_ini_config_str(CONFIG, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        let entry = can(&vec_eo, &mut ro);
+        (entry, ro)
+    }
+
+    #[test]
+    fn test_check_permit() {
+        let (entry, ro) = check("/usr/bin/uptime -p");
+        assert_eq!(
+            produce_check(&entry, &ro, true),
+            [
+                "You may run \"/usr/bin/uptime -p\" on localhost as root",
+                "  section: uptime",
+                "  binary: /usr/bin/uptime",
+                "  password: required",
+                "  reason: required, matching ^CHG[0-9]+$",
+            ]
+        );
+        assert_eq!(
+            produce_check(&entry, &ro, false)[3],
+            "  password: required, token held"
+        );
+
+        let record = produce_check_json(&entry, &ro, true);
+        assert_eq!(record["permit"], true);
+        assert_eq!(record["binary"], "/usr/bin/uptime");
+        assert_eq!(record["password_needed"], true);
+        assert_eq!(record["reason_rule"], "^CHG[0-9]+$");
+    }
+
+    #[test]
+    fn test_check_search_path() {
+        let (entry, ro) = check("true");
+        assert!(entry.permit());
+        assert_eq!(check_binary(&ro), "/bin/true");
+
+        let lines = produce_check(&entry, &ro, true);
+        assert_eq!(lines[1], "  section: true");
+        assert_eq!(lines[3], "  password: not required");
+        assert_eq!(lines[4], "  reason: not required");
+        assert_eq!(lines[5], "  approval: required from ^admins$");
+        assert_eq!(
+            produce_check_json(&entry, &ro, true)["password_needed"],
+            false
+        );
+    }
+
+    #[test]
+    fn test_check_deny() {
+        let (entry, ro) = check("/usr/bin/uptime -s");
+        assert!(!entry.permit());
+        assert_eq!(
+            produce_check(&entry, &ro, true),
+            ["You may not run \"/usr/bin/uptime -s\" on localhost as root"]
+        );
+        assert_eq!(produce_check_json(&entry, &ro, true)["permit"], false);
+    }
+}
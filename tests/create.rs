@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_create_config() {
+        let config = "[default]
+name = ed
+type = edit
+rule = ^/srv/.*$
+create = false
+
+[www]
+exact_name = ed
+type = edit
+rule = ^/srv/www/.*$
+create = true
+template = /etc/please/templates/vhost.conf
+mkdir = true
+mkdir_mode = 2750
+mkdir_owner = www-data:www-data
+
+[other]
+exact_name = ed
+type = edit
+rule = ^/srv/other$
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.acl_type = Acltype::Edit;
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        basic_cmd(&mut ro, "/srv/www/site.conf");
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.create, Some(true));
+        assert_eq!(
+            entry.template,
+            Some("/etc/please/templates/vhost.conf".to_string())
+        );
+        assert_eq!(entry.mkdir, Some(true));
+        assert_eq!(entry.mkdir_mode, Some(0o2750));
+        assert_eq!(
+            entry.mkdir_owner,
+            Some(EditOwner::Owner(
+                "www-data".to_string(),
+                "www-data".to_string()
+            ))
+        );
+
+        basic_cmd(&mut ro, "/srv/other");
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.create, Some(false));
+        assert_eq!(entry.template, None);
+        assert_eq!(entry.mkdir, None);
+    }
+
+    #[test]
+    fn test_create_config_errors() {
+        for line in [
+            "template = relative/file",
+            "mkdir_mode = 999",
+            "mkdir_mode = 17777",
+            "mkdir_owner = nobody",
+        ] {
+            let mut bytes = 0;
+            let mut ini_list: HashMap<String, bool> = HashMap::new();
+            let mut vec_eo: Vec<EnvOptions> = vec![];
+            let ro = basic_ro("ed", "root");
+            let config = format!("[bad]\nname = ed\ntype = edit\nrule = .*\n{}\n", line);
+
+            assert!(
+                read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list)
+                    .is_err(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_dirs() {
+        let base = format!(
+            "{}/please_test_mkdir_{}",
+            std::env::temp_dir().to_str().unwrap(),
+            std::process::id()
+        );
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir(&base).unwrap();
+
+        assert_eq!(
+            missing_dirs(Path::new(&format!("{}/file", base))),
+            Vec::<PathBuf>::new()
+        );
+        assert_eq!(
+            missing_dirs(Path::new(&format!("{}/a/b/file", base))),
+            vec![
+                PathBuf::from(format!("{}/a", base)),
+                PathBuf::from(format!("{}/a/b", base))
+            ]
+        );
+        assert_eq!(missing_dirs(Path::new("file")), Vec::<PathBuf>::new());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
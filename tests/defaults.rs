@@ -26,7 +26,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), false);
@@ -51,7 +51,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), true);
@@ -76,7 +76,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.permit(), true);
@@ -112,7 +112,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), true);
@@ -153,7 +153,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), true);
@@ -182,7 +182,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), true);
@@ -209,7 +209,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_some(), true);
@@ -246,7 +246,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.syslog.is_none(), true);
@@ -278,7 +278,7 @@ type = edit
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         ro.acl_type = Acltype::Edit;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, Some(EditMode::Keep(true)));
@@ -309,7 +309,7 @@ type = edit
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         ro.acl_type = Acltype::Edit;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, None);
@@ -341,7 +341,7 @@ type = edit
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         ro.acl_type = Acltype::Edit;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, Some(EditMode::Mode(0o111)));
@@ -379,7 +379,7 @@ type = edit
         ro.name = "noted".to_string();
         ro.acl_type = Acltype::Edit;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, None);
@@ -406,7 +406,7 @@ rule = .*
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, None);
@@ -434,7 +434,7 @@ require_pass = false
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.edit_mode, None);
@@ -462,7 +462,7 @@ require_pass = true
         let mut vec_eo: Vec<EnvOptions> = vec![];
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = pleaser::can(&vec_eo, &mut ro);
         assert_eq!(can.permit(), true);
@@ -492,7 +492,7 @@ rule = .*
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.token_timeout, None);
@@ -519,7 +519,7 @@ rule = .*
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.token_timeout, None);
@@ -547,7 +547,7 @@ rule = .*
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.token_timeout, Some(60));
@@ -575,7 +575,7 @@ token_timeout = 60
         ro.name = "ed".to_string();
         ro.acl_type = Acltype::Run;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let can = can(&vec_eo, &mut ro);
         assert_eq!(can.token_timeout, Some(60));
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod test {
+    use pleaser::*;
+
+    #[test]
+    fn test_diff_unchanged() {
+        assert_eq!(unified_diff(b"a\nb\n", b"a\nb\n", "old", "new"), "");
+        assert_eq!(unified_diff(b"", b"", "old", "new"), "");
+    }
+
+    #[test]
+    fn test_diff_change() {
+        assert_eq!(
+            unified_diff(b"a\nb\nc\n", b"a\nB\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_new_file() {
+        assert_eq!(
+            unified_diff(b"", b"a\nb\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_emptied() {
+        assert_eq!(
+            unified_diff(b"a\n", b"", "old", "new"),
+            "--- old\n+++ new\n@@ -1,1 +0,0 @@\n-a\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_no_trailing_newline() {
+        assert_eq!(
+            unified_diff(b"a\nb\n", b"a\nb", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_separate_hunks() {
+        let old = b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = b"one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\ntwelve\n";
+        assert_eq!(
+            unified_diff(old, new, "old", "new"),
+            "--- old\n+++ new\n\
+             @@ -1,4 +1,4 @@\n-1\n+one\n 2\n 3\n 4\n\
+             @@ -9,4 +9,4 @@\n 9\n 10\n 11\n-12\n+twelve\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_joined_hunks() {
+        let old = b"1\n2\n3\n4\n5\n6\n7\n";
+        let new = b"1\ntwo\n3\n4\n5\nsix\n7\n";
+        assert_eq!(
+            unified_diff(old, new, "old", "new"),
+            "--- old\n+++ new\n@@ -1,7 +1,7 @@\n 1\n-2\n+two\n 3\n 4\n 5\n-6\n+six\n 7\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_bytes() {
+        assert_eq!(unified_diff(b"\xe9\x00\n", b"\xe9\x00\n", "old", "new"), "");
+        assert_eq!(
+            unified_diff(b"a\x00b\n\xe9\n", b"a\x00b\n\xe8\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\x00b\n-\u{fffd}\n+\u{fffd}\n"
+        );
+    }
+
+    fn numbered(lines: usize, change: impl Fn(usize) -> bool) -> Vec<u8> {
+        (0..lines)
+            .map(|l| {
+                if change(l) {
+                    format!("changed {}\n", l)
+                } else {
+                    format!("{}\n", l)
+                }
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    fn count_lines(diff: &str, prefix: char) -> usize {
+        diff.lines()
+            .filter(|l| l.starts_with(prefix) && !l.starts_with("---") && !l.starts_with("+++"))
+            .count()
+    }
+
+    #[test]
+    fn test_diff_large() {
+        let old = numbered(200_000, |_| false);
+        let new = numbered(200_000, |l| l % 1000 == 500);
+        let diff = unified_diff(&old, &new, "old", "new");
+        assert_eq!(count_lines(&diff, '@'), 200);
+        assert_eq!(count_lines(&diff, '-'), 200);
+        assert_eq!(count_lines(&diff, '+'), 200);
+        assert!(diff.contains("@@ -498,7 +498,7 @@\n 497\n 498\n 499\n-500\n+changed 500\n"));
+
+        // nothing in common, the whole file is replaced
+        let old = numbered(50_000, |_| false);
+        let new = numbered(50_000, |_| true);
+        let diff = unified_diff(&old, &new, "old", "new");
+        assert_eq!(count_lines(&diff, '@'), 1);
+        assert_eq!(count_lines(&diff, '-'), 50_000);
+        assert_eq!(count_lines(&diff, '+'), 50_000);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn edit_entry(config: &str, file: &str) -> EnvOptions {
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.acl_type = Acltype::Edit;
+        basic_cmd(&mut ro, file);
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        entry
+    }
+
+    const CONFIG: &str = "[default]
+name = ed
+type = edit
+rule = ^/etc/.*$
+editor_default = /usr/bin/vi
+
+[hosts]
+exact_name = ed
+type = edit
+rule = ^/etc/hosts$
+editor = ^/usr/bin/(vim|nano)$
+editor_default = '/usr/bin/vim' -n
+
+[motd]
+exact_name = ed
+type = edit
+rule = ^/etc/motd$
+";
+
+    #[test]
+    fn test_editor_config() {
+        let entry = edit_entry(CONFIG, "/etc/hosts");
+        assert_eq!(entry.editor, Some("^/usr/bin/(vim|nano)$".to_string()));
+        assert_eq!(entry.editor_default, Some("'/usr/bin/vim' -n".to_string()));
+
+        let entry = edit_entry(CONFIG, "/etc/motd");
+        assert_eq!(entry.editor, None);
+        assert_eq!(entry.editor_default, Some("/usr/bin/vi".to_string()));
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let ro = basic_ro("ed", "root");
+        assert!(read_ini_config_str(
+            "[bad]\nname = ed\nrule = .*\neditor = (\neditor_default = 'vim\n",
+            &mut vec_eo,
+            &ro,
+            true,
+            &mut bytes,
+            &mut ini_list
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_resolve_editor() {
+        assert_eq!(resolve_editor("/usr/bin/vi", Some("/bin")), "/usr/bin/vi");
+        assert_eq!(resolve_editor("sh", None), "sh");
+        assert_eq!(
+            resolve_editor("sh", Some("relative:/nonexistent:/bin")),
+            "/bin/sh"
+        );
+        assert_eq!(
+            resolve_editor("no_such_editor_here", Some("/bin")),
+            "no_such_editor_here"
+        );
+    }
+
+    #[test]
+    fn test_select_editor() {
+        let ro = basic_ro("ed", "root");
+        let hosts = edit_entry(CONFIG, "/etc/hosts");
+        let motd = edit_entry(CONFIG, "/etc/motd");
+
+        // unrestricted sections take any editor, parsed as a shell would
+        assert_eq!(
+            select_editor(Some("'/opt/my editor/bin/ed' -x"), &[&motd], &ro, None),
+            Ok((
+                vec!["/opt/my editor/bin/ed".to_string(), "-x".to_string()],
+                false
+            ))
+        );
+        assert_eq!(
+            select_editor(None, &[&motd], &ro, None),
+            Ok((vec!["/usr/bin/vi".to_string()], false))
+        );
+        assert!(select_editor(Some("'vim"), &[&motd], &ro, None).is_err());
+
+        assert_eq!(
+            select_editor(Some("/usr/bin/nano"), &[&hosts], &ro, None),
+            Ok((vec!["/usr/bin/nano".to_string()], false))
+        );
+
+        // fall back to the default when the editor is not permitted
+        assert_eq!(
+            select_editor(Some("/usr/bin/emacs"), &[&hosts], &ro, None),
+            Ok((vec!["/usr/bin/vim".to_string(), "-n".to_string()], true))
+        );
+
+        // every section must permit the editor, /usr/bin/vi fails hosts
+        let mut strict = hosts.clone();
+        strict.editor_default = None;
+        assert_eq!(
+            select_editor(Some("/usr/bin/emacs"), &[&strict, &motd], &ro, None),
+            Err("You may not use /usr/bin/emacs as an editor".to_string())
+        );
+        assert_eq!(
+            select_editor(Some("/usr/bin/vim"), &[&strict, &motd], &ro, None),
+            Ok((vec!["/usr/bin/vim".to_string()], false))
+        );
+    }
+}
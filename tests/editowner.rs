@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+    use std::ffi::CString;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_edit_owner() {
+        assert_eq!(parse_edit_owner("keep"), Some(EditOwner::Keep));
+        assert_eq!(parse_edit_owner("Target"), Some(EditOwner::Target));
+        assert_eq!(
+            parse_edit_owner("www-data:www-data"),
+            Some(EditOwner::Owner(
+                "www-data".to_string(),
+                "www-data".to_string()
+            ))
+        );
+        assert_eq!(parse_edit_owner("www-data"), None);
+        assert_eq!(parse_edit_owner(":www-data"), None);
+        assert_eq!(parse_edit_owner("www-data:"), None);
+        assert_eq!(parse_edit_owner("a:b:c"), None);
+    }
+
+    #[test]
+    fn test_edit_owner_config() {
+        let config = "[default:owner]
+name = ed
+rule = .*
+type = edit
+editowner = target
+
+[web_edit]
+exact_name = ed
+type = edit
+exact_rule = /etc/nginx/nginx.conf
+
+[web_owner]
+exact_name = ed
+type = edit
+exact_rule = /var/www/index.html
+editowner = www-data:www-data
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.acl_type = Acltype::Edit;
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        basic_cmd(&mut ro, "/etc/nginx/nginx.conf");
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.edit_owner, Some(EditOwner::Target));
+
+        basic_cmd(&mut ro, "/var/www/index.html");
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(
+            entry.edit_owner.as_ref().unwrap().to_string(),
+            "www-data:www-data"
+        );
+    }
+
+    #[test]
+    fn test_edit_owner_config_error() {
+        let config = "[web_edit]
+exact_name = ed
+type = edit
+exact_rule = /etc/nginx/nginx.conf
+editowner = www-data
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let ro = basic_ro("ed", "root");
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_err()
+        );
+    }
+
+    #[test]
+    fn test_xattr_copy() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("please_xattr_{}", std::process::id()));
+        let dest = dir.join(format!("please_xattr_{}.copy", std::process::id()));
+        std::fs::write(&source, b"source").unwrap();
+        let dest_file = std::fs::File::create(&dest).unwrap();
+
+        let attrs = vec![(CString::new("user.please").unwrap(), b"\x00kept".to_vec())];
+        let source_file = std::fs::File::open(&source).unwrap();
+
+        // not every filesystem holds user attributes
+        if write_xattrs(&source_file, &attrs).is_ok() {
+            let read = read_xattrs(Path::new(&source)).unwrap();
+            assert_eq!(read, attrs);
+            assert!(write_xattrs(&dest_file, &read).is_ok());
+            assert_eq!(read_xattrs(Path::new(&dest)).unwrap(), attrs);
+        }
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&dest).unwrap();
+    }
+}
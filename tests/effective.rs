@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn load(config: &str) -> (Vec<EnvOptions>, RunOptions) {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        ro.target = "ed".to_string();
+        basic_cmd(&mut ro, "");
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        (vec_eo, ro)
+    }
+
+    const CONFIG: &str = "
+[default_nopass]
+name = ed
+rule = .*
+require_pass = false
+
+[uptime]
+name = ed
+rule = ^/usr/bin/uptime$
+
+[everything]
+name = ed
+rule = .*
+last = true
+
+[later]
+name = ed
+rule = ^/usr/bin/id$
+";
+
+    #[test]
+    fn test_effective_list() {
+        let (vec_eo, mut ro) = load(CONFIG);
+
+        assert_eq!(
+            produce_list(&vec_eo, &ro),
+            [
+                "  in file: static",
+                "    default_nopass:root (pass=false,dirs=): .*",
+                "    uptime:root (pass=true,dirs=): ^/usr/bin/uptime$",
+                "    everything: last as root (pass=true,dirs=): .*",
+                "    later:root (pass=true,dirs=): ^/usr/bin/id$",
+            ]
+        );
+
+        ro.effective = true;
+        assert_eq!(
+            produce_list(&vec_eo, &ro),
+            [
+                "  in file: static",
+                "    default_nopass:root (pass=false,dirs=): .*",
+                "    uptime:root (pass=false,dirs=): ^/usr/bin/uptime$",
+                "    everything: last as root (pass=false,dirs=): .*",
+                "    later: shadowed(by everything) as root (pass=false,dirs=): ^/usr/bin/id$",
+            ]
+        );
+
+        let records = produce_list_json(&vec_eo, &ro);
+        assert_eq!(records[1]["require_pass"], false);
+        assert_eq!(records[1]["shadowed_by"], serde_json::Value::Null);
+        assert_eq!(records[3]["shadowed_by"], "everything");
+    }
+
+    #[test]
+    fn test_effective_list_unmatched_default() {
+        let (vec_eo, mut ro) = load(
+            "
+[default_bash]
+name = ed
+exact_rule = /bin/bash
+require_pass = false
+
+[default_expired]
+name = ed
+rule = .*
+notafter = 20191231
+syslog = false
+
+[bash]
+name = ed
+exact_rule = /bin/bash
+
+[uptime]
+name = ed
+rule = ^/usr/bin/uptime$
+",
+        );
+        ro.effective = true;
+
+        let list = effective_list(&vec_eo, &ro);
+        let section = |name: &str| &list.iter().find(|(e, _)| e.section == name).unwrap().0;
+        assert!(!section("bash").require_pass());
+        assert!(section("uptime").require_pass());
+        assert_eq!(section("uptime").syslog, None);
+
+        basic_cmd(&mut ro, "/usr/bin/uptime");
+        ro.acl_type = Acltype::Run;
+        assert!(can(&vec_eo, &mut ro).require_pass());
+    }
+
+    #[test]
+    fn test_section_shadows() {
+        let (vec_eo, _) = load(
+            "
+[last_exact]
+name = ed
+exact_rule = /usr/bin/id
+last = true
+
+[same_exact]
+name = ed
+exact_rule = /usr/bin/id
+
+[bin]
+name = ed
+rule = ^/usr/bin/.*$
+last = true
+notafter = 20300101
+
+[under_bin]
+name = ed
+exact_rule = /usr/bin/uptime
+
+[admins]
+name = admins
+group = true
+rule = .*
+last = true
+
+[ed_all]
+name = ed
+rule = .*
+target = ^(root|www)$
+last = true
+
+[ed_root]
+name = ed
+rule = ^/bin/ls$
+exact_target = www
+",
+        );
+
+        let section = |name: &str| vec_eo.iter().find(|e| e.section == name).unwrap();
+
+        assert!(section_shadows(
+            section("last_exact"),
+            section("same_exact")
+        ));
+        assert!(!section_shadows(
+            section("same_exact"),
+            section("last_exact")
+        ));
+
+        // dated sections may stop matching
+        assert!(!section_shadows(section("bin"), section("under_bin")));
+        let mut bin = section("bin").clone();
+        bin.notafter = None;
+        assert!(section_shadows(&bin, section("under_bin")));
+
+        // a group section does not cover a user section
+        assert!(!section_shadows(section("admins"), section("ed_root")));
+
+        // exact values are matched against the wide regex
+        assert!(section_shadows(section("ed_all"), section("ed_root")));
+        let mut ed_all = section("ed_all").clone();
+        ed_all.hostname = Some("^web[0-9]+$".to_string());
+        assert!(!section_shadows(&ed_all, section("ed_root")));
+    }
+}
@@ -21,7 +21,7 @@ exact_rule = /bin/bash
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -50,7 +50,7 @@ exact_rule = /bin/bash
         let mut ro = basic_ro("ed", "root");
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         basic_cmd(&mut ro, &"/bin/bash file".to_string());
@@ -64,7 +64,7 @@ exact_rule = /bin/bash file
         .to_string();
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -80,7 +80,7 @@ exact_rule = /bin/bash echo\ hello\ world
         .to_string();
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -105,7 +105,7 @@ exact_rule = /bin/bash
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         ro.name = "jim".to_string();
@@ -130,7 +130,7 @@ exact_rule = /bin/bash
         let mut ro = basic_ro("ed", "root");
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         ro.target = "jim".to_string();
@@ -156,7 +156,7 @@ exact_rule = /bin/bash
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         ro.hostname = "thing".to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         ro.hostname = "".to_string();
@@ -176,7 +176,7 @@ exact_rule = /bin/bash
 "
         .to_string();
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         ro.hostname = "thing".to_string();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -198,7 +198,7 @@ exact_dir = /root
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/sh".to_string());
         ro.directory = Some("/root".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -224,7 +224,7 @@ exact_rule = /bin/bash
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -238,7 +238,7 @@ exact_target=root
 exact_rule = /bin/bash
 "
         .to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         ro.name = "zz".to_string();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -259,7 +259,7 @@ rule = /bin/bash
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/sh".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -282,7 +282,7 @@ exact_rule = /bin/sh
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/sh".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -307,7 +307,7 @@ dir = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/sh".to_string());
         ro.directory = Some("/root".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -330,7 +330,7 @@ exact_name = audio
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/bin/sh".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
@@ -352,7 +352,7 @@ exact_rule = /bin/bash echo\ hello\ \\\\\ world
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         ro.new_args = vec![
             "/bin/bash".to_string(),
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn from_config(from: &str) -> String {
+        format!(
+            "[bastion]
+exact_name=ed
+exact_rule = /bin/bash
+from = {}
+",
+            from
+        )
+    }
+
+    fn can_from(config: &str, from: &str) -> bool {
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.from = from.to_string();
+        basic_cmd(&mut ro, "/bin/bash");
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        can(&vec_eo, &mut ro).permit()
+    }
+
+    #[test]
+    fn test_from_unset() {
+        let config = "[any]
+exact_name=ed
+exact_rule = /bin/bash
+";
+        assert!(can_from(config, "local"));
+        assert!(can_from(config, "192.0.2.1"));
+    }
+
+    #[test]
+    fn test_from_cidr() {
+        let config = from_config("10.1.0.0/16, 192.0.2.7");
+        assert!(can_from(&config, "10.1.200.3"));
+        assert!(can_from(&config, "192.0.2.7"));
+        assert!(!can_from(&config, "10.2.0.1"));
+        assert!(!can_from(&config, "192.0.2.8"));
+        assert!(!can_from(&config, "local"));
+    }
+
+    #[test]
+    fn test_from_cidr_v6() {
+        let config = from_config("2001:db8::/32");
+        assert!(can_from(&config, "2001:db8:1::5"));
+        assert!(!can_from(&config, "2001:db9::5"));
+        assert!(!can_from(&config, "10.0.0.1"));
+    }
+
+    #[test]
+    fn test_from_cidr_mapped() {
+        let config = from_config("10.0.0.0/8");
+        assert!(can_from(&config, "::ffff:10.1.2.3"));
+    }
+
+    #[test]
+    fn test_from_local() {
+        let config = from_config("local");
+        assert!(can_from(&config, "local"));
+        assert!(!can_from(&config, "10.1.2.3"));
+    }
+
+    #[test]
+    fn test_from_regex() {
+        let config = from_config(r"bastion\d+\.example\.com, local");
+        assert!(can_from(&config, "bastion01.example.com"));
+        assert!(can_from(&config, "local"));
+        assert!(!can_from(&config, "bastion01.example.com.evil.org"));
+    }
+
+    #[test]
+    fn test_from_unknown() {
+        assert!(!can_from(&from_config("local"), "unknown"));
+        assert!(!can_from(&from_config("0.0.0.0/0, ::/0"), "unknown"));
+        assert!(!can_from(&from_config("unknown"), "unknown"));
+        assert!(!can_from(&from_config(".+"), "unknown"));
+        assert!(can_from(&from_config("local, .*"), "unknown"));
+    }
+
+    #[test]
+    fn test_from_forged_env() {
+        std::env::set_var("SSH_CONNECTION", "10.1.2.3 50000 10.0.0.2 22");
+        std::env::set_var("SSH_CLIENT", "10.1.2.3 50000 22");
+        let forged = connection_source(None);
+        std::env::remove_var("SSH_CONNECTION");
+        std::env::remove_var("SSH_CLIENT");
+        let unset = connection_source(None);
+
+        for from in [forged, unset] {
+            assert_ne!(from, "10.1.2.3");
+            assert!(!can_from(&from_config("local"), &from));
+            assert!(!can_from(&from_config("10.1.0.0/16"), &from));
+        }
+    }
+
+    #[test]
+    fn test_proc_net_addr() {
+        assert_eq!(
+            proc_net_addr("0100007F:0016"),
+            Some("127.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            proc_net_addr("0000000000000000FFFF00000302010A:0016"),
+            Some("10.1.2.3".parse().unwrap())
+        );
+        assert_eq!(
+            proc_net_addr("B80D0120000000000000000005000000:0016"),
+            Some("2001:db8::5".parse().unwrap())
+        );
+        assert_eq!(proc_net_addr("zz"), None);
+    }
+
+    #[test]
+    fn test_from_unlisted_tty() {
+        // a tty without a utmp entry says nothing of where the user is
+        let from = connection_source(Some("pts/99999"));
+        assert_eq!(from, connection_source(None));
+        assert!(!can_from(&from_config("local"), &from));
+    }
+
+    #[test]
+    fn test_cidr_match() {
+        let (network, prefix) = parse_cidr("0.0.0.0/0").unwrap().unwrap();
+        assert!(cidr_match(
+            &network,
+            prefix,
+            &"203.0.113.9".parse().unwrap()
+        ));
+        assert_eq!(parse_cidr("bastion"), None);
+        assert_eq!(parse_cidr("10.0.0.1/abc"), Some(Err(())));
+        assert_eq!(parse_cidr("10.0.0.0/33"), Some(Err(())));
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn lint_config(config: &str) -> Vec<String> {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "");
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        lint(&vec_eo, ro.date)
+            .iter()
+            .map(|w| w.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        assert_eq!(
+            lint_config(
+                "
+[uptime]
+name = ed
+rule = ^/usr/bin/uptime$
+search_path = /usr/bin:/bin
+exitcmd = /bin/true %{NEW}
+notafter = 20300101
+"
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_lint_expired_duplicate_shadowed() {
+        assert_eq!(
+            lint_config(
+                "
+[old]
+name = ed
+rule = ^/usr/bin/id$
+notafter = 20191231
+
+[all]
+name = ed
+rule = ^.*$
+last = true
+
+[ed_id]
+name = ed
+rule = ^/usr/bin/id$
+
+[ed_id_again]
+name = ed
+rule = ^/usr/bin/id$
+"
+            ),
+            [
+                "static: [old] expired, notafter 2019-12-31 23:59:59 has passed",
+                "static: [all] rule ^.*$ matches anything",
+                "static: [ed_id] never reached, [all] has last=true and always matches first",
+                "static: [ed_id_again] duplicate of [ed_id]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_broad() {
+        assert_eq!(
+            lint_config(
+                "
+[systemctl]
+name = ed
+rule = /usr/bin/systemctl .*
+permit_env = .*
+
+[anywhere]
+name = ed
+rule = .*/bin/ls
+
+[deny_all]
+name = ben
+rule = .*
+permit = false
+"
+            ),
+            [
+                "static: [systemctl] rule /usr/bin/systemctl .* ends with .* so matches any suffix",
+                "static: [systemctl] permit_env allows any environment variable",
+                "static: [anywhere] rule .*/bin/ls begins with .* so matches any prefix",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_paths() {
+        assert_eq!(
+            lint_config(
+                "
+[paths]
+name = ed
+rule = ^/usr/bin/id$
+search_path = /tmp:bin:/nonexistent/dir
+exitcmd = /nonexistent/check %{NEW}
+
+[relative_exitcmd]
+name = ed
+rule = ^/usr/bin/id$
+exitcmd = no_such_checker_here
+"
+            ),
+            [
+                "static: [paths] search_path entry /tmp is writable by group or other",
+                "static: [paths] search_path entry bin is not absolute",
+                "static: [paths] search_path entry /nonexistent/dir does not exist",
+                "static: [paths] exitcmd program /nonexistent/check does not exist",
+                "static: [relative_exitcmd] exitcmd program no_such_checker_here does not exist",
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    const CONFIG: &str = "
+[services]
+name = ed
+target = root
+rule = ^/usr/bin/systemctl (start|stop) nginx$
+require_pass = false
+reason = true
+last = true
+notbefore = 20200102
+
+[old]
+name = ed
+exact_rule = /usr/bin/uptime
+exact_target = root
+dir = ^/tmp$
+notafter = 20191231
+reason = ^CHG[0-9]+$
+
+[hosts]
+name = ed
+type = edit
+rule = ^/etc/hosts$
+permit = false
+
+[someone_else]
+name = ben
+rule = .*
+";
+
+    fn records(acl_type: Acltype) -> Vec<serde_json::Value> {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        ro.target = "ed".to_string();
+        basic_cmd(&mut ro, "");
+        assert!(
+            read_ini_config_str(CONFIG, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        ro.acl_type = acl_type;
+        produce_list_json(&vec_eo, &ro)
+    }
+
+    #[test]
+    fn test_list_json_run() {
+        let run = records(Acltype::Run);
+        assert_eq!(run.len(), 2);
+
+        assert_eq!(run[0]["file"], "static");
+        assert_eq!(run[0]["section"], "services");
+        assert_eq!(run[0]["acl_type"], "run");
+        assert_eq!(run[0]["target"], "root");
+        assert_eq!(run[0]["rule"], "^/usr/bin/systemctl (start|stop) nginx$");
+        assert_eq!(run[0]["exact_rule"], serde_json::Value::Null);
+        assert_eq!(run[0]["require_pass"], false);
+        assert_eq!(run[0]["reason_required"], true);
+        assert_eq!(run[0]["reason_rule"], serde_json::Value::Null);
+        assert_eq!(run[0]["permit"], true);
+        assert_eq!(run[0]["last"], true);
+        assert_eq!(run[0]["notbefore"], "2020-01-02 00:00:00");
+        assert_eq!(run[0]["date"], "upcoming");
+
+        assert_eq!(run[1]["section"], "old");
+        assert_eq!(run[1]["exact_rule"], "/usr/bin/uptime");
+        assert_eq!(run[1]["exact_target"], "root");
+        assert_eq!(run[1]["dir"], "^/tmp$");
+        assert_eq!(run[1]["require_pass"], true);
+        assert_eq!(run[1]["reason_rule"], "^CHG[0-9]+$");
+        assert_eq!(run[1]["last"], false);
+        assert_eq!(run[1]["date"], "expired");
+    }
+
+    #[test]
+    fn test_list_json_edit() {
+        let edit = records(Acltype::Edit);
+        assert_eq!(edit.len(), 1);
+        assert_eq!(edit[0]["section"], "hosts");
+        assert_eq!(edit[0]["acl_type"], "edit");
+        assert_eq!(edit[0]["permit"], false);
+        assert_eq!(edit[0]["reason_required"], false);
+
+        assert!(records(Acltype::List).is_empty());
+    }
+
+    #[test]
+    fn test_parse_list_format() {
+        assert_eq!(parse_list_format("json"), Some(ListFormat::Json));
+        assert_eq!(parse_list_format("TEXT"), Some(ListFormat::Text));
+        assert_eq!(parse_list_format("xml"), None);
+    }
+}
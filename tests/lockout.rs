@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn lockout_entry() -> EnvOptions {
+        let config = "[ed]
+exact_name=ed
+exact_rule = /bin/bash
+password_retries = 5
+failure_delay = 2
+lockout = 3
+lockout_window = 60
+lockout_time = 300
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+
+        assert!(
+            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        can(&vec_eo, &mut ro)
+    }
+
+    #[test]
+    fn test_lockout_parse() {
+        let entry = lockout_entry();
+        assert!(entry.permit());
+        assert_eq!(entry.password_retries, Some(5));
+        assert_eq!(entry.failure_delay, Some(2));
+        assert_eq!(entry.lockout, Some(3));
+        assert_eq!(entry.lockout_window, Some(60));
+        assert_eq!(entry.lockout_time, Some(300));
+    }
+
+    #[test]
+    fn test_lockout_bad_value_unchecked() {
+        let config = "[ed]
+exact_name=ed
+exact_rule = /bin/bash
+lockout = three
+lockout_time = -1
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+
+        // without fail_error the values are left unset, rather than a panic
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.lockout, None);
+        assert_eq!(entry.lockout_time, None);
+    }
+
+    #[test]
+    fn test_record_failure_concurrent() {
+        // the failure records are root's
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let user = format!("lockout_test_{}", std::process::id());
+        let entry = lockout_entry();
+        let records: Vec<std::thread::JoinHandle<Option<Vec<u64>>>> = (0..16)
+            .map(|_| {
+                let user = user.clone();
+                let entry = entry.clone();
+                std::thread::spawn(move || record_failure(&user, &entry))
+            })
+            .collect();
+        for record in records {
+            assert!(record.join().unwrap().is_some());
+        }
+
+        assert_eq!(read_failures(&user).len(), 16);
+        assert!(lockout_remaining(&entry, &read_failures(&user), epoch_secs()).is_some());
+
+        remove_failures(&user);
+        assert!(read_failures(&user).is_empty());
+        let _ = std::fs::remove_file(failure_path(&user));
+    }
+
+    #[test]
+    fn test_lockout_unset() {
+        let entry = EnvOptions::new();
+        assert_eq!(lockout_remaining(&entry, &[100, 101, 102, 103], 104), None);
+    }
+
+    #[test]
+    fn test_lockout_below_threshold() {
+        let entry = lockout_entry();
+        assert_eq!(lockout_remaining(&entry, &[1000, 1010], 1020), None);
+    }
+
+    #[test]
+    fn test_lockout_within_window() {
+        let entry = lockout_entry();
+        assert_eq!(
+            lockout_remaining(&entry, &[1000, 1010, 1020], 1020),
+            Some(300)
+        );
+        assert_eq!(
+            lockout_remaining(&entry, &[1020, 1000, 1010], 1120),
+            Some(200)
+        );
+    }
+
+    #[test]
+    fn test_lockout_outside_window() {
+        let entry = lockout_entry();
+        assert_eq!(lockout_remaining(&entry, &[1000, 1050, 1100], 1100), None);
+    }
+
+    #[test]
+    fn test_lockout_expired() {
+        let entry = lockout_entry();
+        assert_eq!(lockout_remaining(&entry, &[1000, 1010, 1020], 1320), None);
+    }
+
+    #[test]
+    fn test_lockout_default_merge() {
+        let config = "[default:lockout]
+name = .*
+rule = .*
+lockout = 5
+failure_delay = 3
+
+[ed]
+name = ed
+rule = /bin/bash
+failure_delay = 1
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
+        let entry = can(&vec_eo, &mut ro);
+        assert_eq!(entry.lockout, Some(5));
+        assert_eq!(entry.failure_delay, Some(1));
+    }
+}
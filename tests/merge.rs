@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod test {
+    use pleaser::*;
+
+    fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        let (out, conflicts) = merge3(
+            base.as_bytes(),
+            ours.as_bytes(),
+            theirs.as_bytes(),
+            "edited",
+            "current",
+        );
+        (String::from_utf8(out).unwrap(), conflicts)
+    }
+
+    #[test]
+    fn test_merge_unchanged() {
+        assert_eq!(
+            merge("a\nb\n", "a\nb\n", "a\nb\n"),
+            ("a\nb\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_one_side() {
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nB\nc\n", "a\nb\nc\n"),
+            ("a\nB\nc\n".to_string(), 0)
+        );
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nb\nc\n", "a\nb\nC\n"),
+            ("a\nb\nC\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_both_sides() {
+        assert_eq!(
+            merge(
+                "1\n2\n3\n4\n5\n",
+                "one\n2\n3\n4\n5\n",
+                "1\n2\n3\n4\nfive\n6\n"
+            ),
+            ("one\n2\n3\n4\nfive\n6\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_same_change() {
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nB\nc\n", "a\nB\nc\n"),
+            ("a\nB\nc\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nours\nc\n", "a\ntheirs\nc\n"),
+            (
+                "a\n<<<<<<< edited\nours\n||||||| original\nb\n=======\ntheirs\n>>>>>>> current\nc\n"
+                    .to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_no_trailing_newline() {
+        assert_eq!(merge("a\nb", "A\nb", "a\nb"), ("A\nb".to_string(), 0));
+        let (out, conflicts) = merge("a", "b", "c");
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            out,
+            "<<<<<<< edited\nb\n||||||| original\na\n=======\nc\n>>>>>>> current\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_bytes() {
+        let base = b"caf\xe9\n\x00\x01\x02\nend";
+        let ours = b"caf\xe9 cr\xe8me\n\x00\x01\x02\nend";
+        let theirs = b"caf\xe9\n\x00\x01\x02\nend\xff";
+        let (out, conflicts) = merge3(base, ours, theirs, "edited", "current");
+        assert_eq!(conflicts, 0);
+        assert_eq!(out, b"caf\xe9 cr\xe8me\n\x00\x01\x02\nend\xff".to_vec());
+    }
+
+    #[test]
+    fn test_merge_large() {
+        let lines = |change: &dyn Fn(usize) -> Option<&'static str>| -> String {
+            (0..200_000)
+                .map(|l| match change(l) {
+                    Some(side) => format!("{} {}\n", side, l),
+                    None => format!("{}\n", l),
+                })
+                .collect()
+        };
+        let base = lines(&|_| None);
+        let ours = lines(&|l| if l % 1000 == 100 { Some("ours") } else { None });
+        let theirs = lines(&|l| {
+            if l % 1000 == 600 {
+                Some("theirs")
+            } else {
+                None
+            }
+        });
+
+        let (out, conflicts) = merge(&base, &ours, &theirs);
+        assert_eq!(conflicts, 0);
+        assert_eq!(
+            out,
+            lines(&|l| match l % 1000 {
+                100 => Some("ours"),
+                600 => Some("theirs"),
+                _ => None,
+            })
+        );
+
+        let theirs = lines(&|l| if l == 150_100 { Some("theirs") } else { None });
+        let (out, conflicts) = merge(&base, &ours, &theirs);
+        assert_eq!(conflicts, 1);
+        assert!(out.contains("<<<<<<< edited\nours 150100\n||||||| original\n150100\n"));
+    }
+}
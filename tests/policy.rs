@@ -0,0 +1,109 @@
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    struct Policy {
+        dir: std::path::PathBuf,
+        main: String,
+        include: String,
+    }
+
+    impl Policy {
+        fn new(name: &str) -> Policy {
+            let dir =
+                std::env::temp_dir().join(format!("please_policy_{}_{}", name, std::process::id()));
+            std::fs::create_dir(&dir).unwrap();
+            let main = dir.join("please.ini").to_str().unwrap().to_string();
+            let include = dir.join("ed.ini").to_str().unwrap().to_string();
+
+            std::fs::write(
+                &main,
+                format!(
+                    "[policy]\nexact_name = ed\ntype = edit\nexact_rule = {}\n\n[include]\ninclude = {}\n",
+                    main, include
+                ),
+            )
+            .unwrap();
+            std::fs::write(
+                &include,
+                format!(
+                    "[ed_include]\nexact_name = ed\ntype = edit\nexact_rule = {}\n",
+                    include
+                ),
+            )
+            .unwrap();
+
+            Policy { dir, main, include }
+        }
+    }
+
+    impl Drop for Policy {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_policy_check_main() {
+        let policy = Policy::new("main");
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, &policy.main);
+
+        let data = std::fs::read(&policy.main).unwrap();
+        let vec_eo = policy_check(&policy.main, &policy.main, &data, &ro).unwrap();
+        assert_eq!(vec_eo.len(), 2);
+        assert!(policy_keeps_access(&vec_eo, &ro, &policy.main));
+        assert!(policy_keeps_access(&vec_eo, &ro, &policy.include));
+
+        assert_eq!(
+            policy_check(&policy.main, &policy.main, b"[broken]\nname = (\n", &ro).err(),
+            Some(format!("{} would not be a valid policy", policy.main))
+        );
+    }
+
+    #[test]
+    fn test_policy_check_include() {
+        let policy = Policy::new("include");
+        let ro = basic_ro("ed", "root");
+
+        // the candidate replaces the include on disk
+        let vec_eo = policy_check(
+            &policy.main,
+            &policy.include,
+            b"[ed_include]\nexact_name = ben\ntype = edit\nrule = .*\n",
+            &ro,
+        )
+        .unwrap();
+        assert!(policy_keeps_access(&vec_eo, &ro, &policy.main));
+        assert!(!policy_keeps_access(&vec_eo, &ro, &policy.include));
+
+        assert!(policy_check(&policy.main, &policy.include, b"[x]\nunknown = 1\n", &ro).is_err());
+        assert!(policy_check(&policy.main, &policy.include, b"\xff", &ro).is_err());
+    }
+
+    #[test]
+    fn test_policy_check_dropped_include() {
+        let policy = Policy::new("dropped");
+        let ro = basic_ro("ed", "root");
+
+        let data = format!(
+            "[policy]\nexact_name = ed\ntype = edit\nexact_rule = {}\n",
+            policy.main
+        );
+        assert!(policy_check(&policy.main, &policy.main, data.as_bytes(), &ro).is_ok());
+        assert!(policy_check(&policy.main, &policy.include, b"", &ro).is_ok());
+
+        let other = format!("{}/other.ini", policy.dir.to_str().unwrap());
+        assert_eq!(
+            policy_check(&policy.main, &other, b"", &ro).err(),
+            Some(format!(
+                "{} would no longer be included by {}",
+                other, policy.main
+            ))
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn load(config: &str) -> Vec<EnvOptions> {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "");
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        vec_eo
+    }
+
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        produce_diff(&diff_policy(&load(old), &load(new)))
+    }
+
+    #[test]
+    fn test_diff_normalised() {
+        assert_eq!(
+            diff(
+                "
+[id]
+exact_name = ed
+rule = ^/usr/bin/id$
+require_pass = true
+
+[hosts]
+name = ed
+type = edit
+rule = ^/etc/hosts$
+editmode = 644
+",
+                "
+[id]
+name = ^ed$
+exact_rule = /usr/bin/id
+
+[hosts]
+name = ed
+type = edit
+exact_rule = /etc/hosts
+editmode = 0644
+"
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_diff_broadens() {
+        assert_eq!(
+            diff(
+                "
+[default_ed]
+name = ed
+rule = .*
+require_pass = true
+
+[uptime]
+name = ed
+exact_rule = /usr/bin/uptime
+reason = ^CHG[0-9]+$
+notafter = 20300101
+
+[hosts]
+name = ed
+type = edit
+rule = /etc/hosts
+exitcmd = /usr/bin/check %{NEW}
+
+[ben]
+name = ben
+rule = .*
+permit = false
+",
+                "
+[default_ed]
+name = ed
+rule = .*
+require_pass = false
+
+[uptime]
+name = ed
+rule = /usr/bin/.*
+reason = true
+
+[hosts]
+name = ed
+type = edit
+rule = /etc/hosts
+env_assign.LANG = C
+
+[ls]
+name = ed
+rule = /bin/ls
+"
+            ),
+            [
+                "removed [ben] (broadens access)",
+                "    type = run",
+                "    group = false",
+                "    name = \"ben\"",
+                "    rule = .*",
+                "    target = \"root\"",
+                "    permit = false",
+                "    require_pass = true",
+                "    last = false",
+                "changed [default_ed] (broadens access)",
+                "    require_pass: true -> false (broadens access)",
+                "changed [uptime] (broadens access)",
+                "    rule: \"/usr/bin/uptime\" -> /usr/bin/.* (broadens access)",
+                "    require_pass: true -> false (broadens access)",
+                "    notafter: 2030-01-01 23:59:59 -> (unset) (broadens access)",
+                "    reason: CHG[0-9]+ -> required (broadens access)",
+                "changed [hosts] (broadens access)",
+                "    exitcmd: /usr/bin/check %{NEW} -> (unset) (broadens access)",
+                "    env_assign.LANG: (unset) -> C",
+                "added [ls] (broadens access)",
+                "    type = run",
+                "    group = false",
+                "    name = \"ed\"",
+                "    rule = \"/bin/ls\"",
+                "    target = \"root\"",
+                "    permit = true",
+                "    require_pass = false",
+                "    last = false",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_narrows() {
+        let changes = diff_policy(
+            &load(
+                "
+[svc]
+name = ed
+rule = /usr/bin/systemctl (start|stop) .*
+target = (root|www)
+",
+            ),
+            &load(
+                "
+[svc]
+name = ed
+rule = /usr/bin/systemctl restart nginx
+target = root
+permit = false
+reason = true
+",
+            ),
+        );
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, SectionChangeKind::Changed);
+        let access: Vec<(&str, AccessChange)> = changes[0]
+            .keys
+            .iter()
+            .map(|k| (k.key.as_str(), k.access))
+            .collect();
+        assert_eq!(
+            access,
+            [
+                ("rule", AccessChange::MayBroaden),
+                ("target", AccessChange::Narrows),
+                ("permit", AccessChange::Narrows),
+                ("reason", AccessChange::Narrows),
+            ]
+        );
+        assert_eq!(changes[0].access, AccessChange::MayBroaden);
+    }
+
+    #[test]
+    fn test_normalised_section() {
+        let vec_eo = load(
+            "
+[hosts]
+exact_name = ed
+type = edit
+rule = ^/etc/hosts\\.allow$
+editmode = 600
+mkdir_mode = 750
+env_assign.B = 2
+env_assign.A = 1
+",
+        );
+
+        let normalised = normalised_section(&vec_eo[0]);
+        let get = |key: &str| {
+            normalised
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("name"), Some("\"ed\""));
+        assert_eq!(get("rule"), Some("\"/etc/hosts.allow\""));
+        assert_eq!(get("editmode"), Some("0600"));
+        assert_eq!(get("mkdir_mode"), Some("0750"));
+        assert_eq!(get("create"), Some("true"));
+        assert_eq!(get("exitcmd"), None);
+        assert_eq!(
+            normalised[normalised.len() - 2..],
+            [
+                ("env_assign.A".to_string(), "1".to_string()),
+                ("env_assign.B".to_string(), "2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_unreadable() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::process::CommandExt;
+
+        // a setuid copy of please is run as nobody, which needs root to make
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let dir = format!("/tmp/please_diff_test.{}", std::process::id());
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let please = format!("{}/please", dir);
+        std::fs::copy(env!("CARGO_BIN_EXE_please"), &please).unwrap();
+        std::fs::set_permissions(&please, std::fs::Permissions::from_mode(0o4755)).unwrap();
+
+        let old = format!("{}/old.ini", dir);
+        let secret = format!("{}/secret.ini", dir);
+        std::fs::write(&old, "[public]\nname = ed\nrule = /bin/true\n").unwrap();
+        std::fs::write(&secret, "[secret]\nname = ed\nrule = /bin/secret\n").unwrap();
+        std::fs::set_permissions(&old, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let output = std::process::Command::new(&please)
+            .args(["--diff", &old, &secret])
+            .uid(65534)
+            .gid(65534)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!output.status.success());
+        assert!(stdout.contains("Permission denied"));
+        assert!(!stdout.contains("/bin/secret"));
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(feature = "serde")]
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    // the reporter is shared by every test in this file
+    static REPORTER: Mutex<()> = Mutex::new(());
+
+    // stdout is the reporter again before the next test may start
+    fn buffer_reporter() -> (
+        (RestoreProviders, MutexGuard<'static, ()>),
+        Arc<BufferReporter>,
+    ) {
+        let lock = REPORTER.lock().unwrap_or_else(|e| e.into_inner());
+        let reporter = Arc::new(BufferReporter::new());
+        set_reporter(reporter.clone());
+        ((RestoreProviders, lock), reporter)
+    }
+
+    #[test]
+    fn test_check_env_options() {
+        let (_lock, reporter) = buffer_reporter();
+        let mut ro = basic_ro("ed", "root");
+
+        let mut item = EnvOptions::new();
+        item.section = "ok".to_string();
+        item.name = Some("ed".to_string());
+        item.rule = Some("/bin/bash".to_string());
+        item.configured = true;
+        assert!(check_env_options(&item, &ro, "cmdb"));
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(can(&[item.clone()], &mut ro).permit());
+
+        item.rule = Some("(".to_string());
+        item.from = Some("10.0.0.0/40".to_string());
+        assert!(!check_env_options(&item, &ro, "cmdb"));
+        assert_eq!(
+            reporter.take(),
+            [
+                "Error parsing cmdb",
+                "Could not parse 10.0.0.0/40 at cmdb: ok"
+            ]
+        );
+
+        let mut item = EnvOptions::new();
+        item.backup = Some("backups".to_string());
+        item.backup_keep = Some(0);
+        item.editor_default = Some("".to_string());
+        item.mkdir_mode = Some(0o17777);
+        item.template = Some("template".to_string());
+        assert!(!check_env_options(&item, &ro, "cmdb"));
+        assert_eq!(
+            reporter.take(),
+            [
+                "Error parsing cmdb, section has no name",
+                "Error parsing cmdb: , backup must be an absolute directory",
+                "Error parsing cmdb: , backup_keep must be a number above 0",
+                "Error parsing cmdb: , editor_default must be a command",
+                "Error parsing cmdb: , mkdir_mode must be an octal mode",
+                "Error parsing cmdb: , template must be an absolute file",
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_policy_json_round_trip() {
+        let config = "
+[ed_bash]
+name = ed
+target = root
+rule = /bin/bash
+notbefore = 20200101
+require_pass = false
+env_assign.PATH = /bin
+
+[ed_hosts]
+name = ed
+type = edit
+rule = /etc/hosts
+editmode = 644
+editowner = root:adm
+reason = ^CHG[0-9]+$
+max_uses = 5/day
+";
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).unwrap();
+
+        let json = serde_json::to_string(&vec_eo).unwrap();
+        let mut loaded: Vec<EnvOptions> = vec![];
+        read_policy_str(&json, PolicyFormat::Json, &mut loaded, &ro, "cmdb.json").unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].file_name, "cmdb.json");
+        assert_eq!(loaded[1].edit_mode, Some(EditMode::Mode(0o644)));
+        assert_eq!(
+            loaded[1].edit_owner,
+            Some(EditOwner::Owner("root".to_string(), "adm".to_string()))
+        );
+        assert_eq!(
+            loaded[1].reason,
+            Some(ReasonType::Text("^CHG[0-9]+$".to_string()))
+        );
+        for (old, new) in vec_eo.iter().zip(loaded.iter()) {
+            assert_eq!(normalised_section(old), normalised_section(new));
+        }
+
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(can(&loaded, &mut ro).permit());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_policy_toml() {
+        let policy = r#"
+[[section]]
+section = "ed_bash"
+name = "ed"
+rule = "/bin/bash"
+acl_type = "run"
+require_pass = false
+
+[[section]]
+section = "ed_deny_bash"
+name = "ed"
+rule = "/bin/bash"
+permit = false
+hostname = "db.*"
+"#;
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        read_policy_str(policy, PolicyFormat::Toml, &mut vec_eo, &ro, "cmdb.toml").unwrap();
+        assert_eq!(vec_eo.len(), 2);
+        assert_eq!(vec_eo[0].target, Some("root".to_string()));
+
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(can(&vec_eo, &mut ro).permit());
+        ro.hostname = "db1".to_string();
+        assert!(!can(&vec_eo, &mut ro).permit());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_policy_errors() {
+        let (_lock, reporter) = buffer_reporter();
+        let ro = basic_ro("ed", "root");
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+
+        assert_eq!(
+            read_policy_str(
+                r#"[{"section": "ok", "rule": "/bin/bash"}, {"section": "bad", "rule": "("}]"#,
+                PolicyFormat::Json,
+                &mut vec_eo,
+                &ro,
+                "cmdb.json"
+            ),
+            Err(PleaseError::Config("cmdb.json".to_string()))
+        );
+        assert!(vec_eo.is_empty());
+        assert_eq!(reporter.take(), ["Error parsing cmdb.json"]);
+
+        assert!(read_policy_str(
+            r#"[{"section": "bad", "acl_type": "sudo"}]"#,
+            PolicyFormat::Json,
+            &mut vec_eo,
+            &ro,
+            "cmdb.json"
+        )
+        .is_err());
+        assert_eq!(reporter.take().len(), 1);
+
+        assert!(read_policy_file("/etc/please.yaml", &mut vec_eo, &ro).is_err());
+        assert_eq!(
+            reporter.take(),
+            ["Error parsing /etc/please.yaml, policy documents end .json or .toml"]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_run_options_serde() {
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash -l");
+        ro.original_uid = nix::unistd::Uid::from_raw(1000);
+
+        let json = serde_json::to_string(&ro).unwrap();
+        let loaded: RunOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.name, "ed");
+        assert_eq!(loaded.original_uid.as_raw(), 1000);
+        assert_eq!(loaded.new_args, ["/bin/bash", "-l"]);
+        assert_eq!(loaded.date, ro.date);
+        assert_eq!(loaded.acl_type, Acltype::Run);
+    }
+}
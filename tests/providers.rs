@@ -0,0 +1,287 @@
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use getopts::Options;
+    use pleaser::*;
+
+    // the providers are shared by every test in this file
+    static PROVIDERS: Mutex<()> = Mutex::new(());
+
+    // the system providers are put back before the next test may start,
+    // however the test ends
+    struct Providers {
+        _restore: RestoreProviders,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    fn providers() -> Providers {
+        Providers {
+            _restore: RestoreProviders,
+            _lock: PROVIDERS.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    fn load_file(path: &str, ro: &RunOptions) -> Result<Vec<EnvOptions>, PleaseError> {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        read_ini_config_file(path, &mut vec_eo, ro, true, &mut bytes, &mut ini_list)?;
+        Ok(vec_eo)
+    }
+
+    struct NoHostname;
+
+    impl HostnameSource for NoHostname {
+        fn hostname(&self) -> Result<String, PleaseError> {
+            Err(PleaseError::Hostname("EINVAL".to_string()))
+        }
+    }
+
+    fn ed_identity() -> FakeIdentity {
+        let mut identity = FakeIdentity::new();
+        identity.add_user("root", 0);
+        identity.add_user("ed", 1000);
+        identity.add_user("bob", 1001);
+        identity.add_group("nogroup", 65534);
+        identity.add_group("admins", 2000);
+        identity.add_member("ed", "admins");
+        identity.login("ed");
+        identity
+    }
+
+    #[test]
+    fn test_include() {
+        let _lock = providers();
+        let mut fs = FakeFilesystem::new();
+        fs.add_file(
+            "/etc/please.ini",
+            "[ed_all]\ninclude = /etc/please.d/00_please.ini\npermit = false\n",
+        );
+        fs.add_file(
+            "/etc/please.d/00_please.ini",
+            "[ed_all]\nexact_name = ed\nrule = /bin/bash\nrequire_pass = false\n",
+        );
+        fs.add_file("/bin/bash", "");
+        set_filesystem(Arc::new(fs));
+
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = load_file("/etc/please.ini", &ro).unwrap();
+        assert!(can(&vec_eo, &mut ro).permit());
+
+        basic_cmd(&mut ro, "bash");
+        assert!(can(&vec_eo, &mut ro).permit());
+        assert_eq!(ro.command, "/bin/bash");
+    }
+
+    #[test]
+    fn test_includedir() {
+        let _lock = providers();
+        let mut fs = FakeFilesystem::new();
+        fs.add_file("/etc/please.ini", "[ed_all]\nincludedir = /etc/please.d\n");
+        fs.add_file(
+            "/etc/please.d/00_please.ini",
+            "[ed_all]\nexact_name = ed\nrule = /bin/bash\n",
+        );
+        fs.add_file(
+            "/etc/please.d/01_please.ini",
+            "[ed_all]\nexact_name = ed\nrule = /bin/bash\npermit = false\n",
+        );
+        fs.add_file("/etc/please.d/02_please.ini.dpkg-old", "not = ini");
+        fs.add_file("/bin/bash", "");
+        set_filesystem(Arc::new(fs));
+
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = load_file("/etc/please.ini", &ro).unwrap();
+        assert_eq!(vec_eo.len(), 2);
+        assert!(!can(&vec_eo, &mut ro).permit());
+    }
+
+    #[test]
+    fn test_config_read_errors() {
+        let _lock = providers();
+        let reporter = Arc::new(BufferReporter::new());
+        set_reporter(reporter.clone());
+
+        let mut fs = FakeFilesystem::new();
+        fs.add_file(
+            "/etc/please.ini",
+            "[ed_all]\ninclude = /etc/please.d/00_please_missing.ini\npermit = false\n",
+        );
+        fs.add_file_mode("/etc/writable.ini", "[ed_all]\nname = ed\n", 0o664);
+        set_filesystem(Arc::new(fs));
+
+        let ro = basic_ro("ed", "root");
+        assert_eq!(
+            load_file("/etc/please.ini", &ro).unwrap_err(),
+            PleaseError::Config("/etc/please.ini".to_string())
+        );
+        assert_eq!(
+            reporter.take(),
+            [
+                "Could not open /etc/please.d/00_please_missing.ini: No such file or directory (os error 2)",
+                "Could not include file"
+            ]
+        );
+
+        assert!(load_file("/etc/writable.ini", &ro).is_err());
+        assert_eq!(
+            reporter.take(),
+            ["Refusing to parse file as group or other write permission bits are set"]
+        );
+
+        assert!(load_file("/etc", &ro).is_err());
+        assert_eq!(reporter.take(), ["Refusing to open non-regular file"]);
+    }
+
+    #[test]
+    fn test_command_not_found() {
+        let _lock = providers();
+        let mut fs = FakeFilesystem::new();
+        fs.add_file("/usr/bin/id", "");
+        fs.add_file("/opt/bin/tool", "");
+        set_filesystem(Arc::new(fs));
+
+        let mut ro = basic_ro("ed", "root");
+        let mut item = EnvOptions::new();
+
+        basic_cmd(&mut ro, "id");
+        assert_eq!(search_path(&mut ro, &item), Some("/usr/bin/id".to_string()));
+
+        basic_cmd(&mut ro, "command_not_found");
+        assert_eq!(search_path(&mut ro, &item), None);
+
+        basic_cmd(&mut ro, "tool");
+        assert_eq!(search_path(&mut ro, &item), None);
+        ro.located_bin.clear();
+        item.search_path = Some("/opt/bin/".to_string());
+        assert_eq!(
+            search_path(&mut ro, &item),
+            Some("/opt/bin/tool".to_string())
+        );
+    }
+
+    #[test]
+    fn test_group_rules() {
+        let _lock = providers();
+        set_identity(Arc::new(ed_identity()));
+
+        let ed = identity().user_by_uid(identity().current_uid()).unwrap();
+        let mut ro = basic_ro("ed", "root");
+        ro.groups = group_hash(identity().user_groups(&ed).unwrap());
+        assert_eq!(ro.groups.get("admins"), Some(&2000));
+        assert_eq!(ro.groups.get("ed"), Some(&1000));
+        assert_eq!(RunOptions::new().original_uid.as_raw(), 1000);
+
+        let mut fs = FakeFilesystem::new();
+        fs.add_file(
+            "/etc/please.ini",
+            "[admins]\nname = admins\ngroup = true\nrule = /bin/bash\ntarget_group = nogroup\n",
+        );
+        fs.add_file("/bin/bash", "");
+        set_filesystem(Arc::new(fs));
+
+        let vec_eo = load_file("/etc/please.ini", &ro).unwrap();
+        basic_cmd(&mut ro, "/bin/bash");
+        ro.target_group = Some("nogroup".to_string());
+        assert!(can(&vec_eo, &mut ro).permit());
+
+        let bob = identity().user_by_name("bob").unwrap();
+        assert_eq!(runopt_target_gid(&ro, &bob).unwrap().as_raw(), 65534);
+        ro.target_group = None;
+        assert_eq!(runopt_target_gid(&ro, &bob).unwrap().as_raw(), 1001);
+
+        let mut ro = basic_ro("bob", "root");
+        ro.groups = group_hash(identity().user_groups(&bob).unwrap());
+        basic_cmd(&mut ro, "/bin/bash");
+        ro.target_group = Some("nogroup".to_string());
+        assert!(!can(&vec_eo, &mut ro).permit());
+    }
+
+    #[test]
+    fn test_exact_hostname() {
+        let _lock = providers();
+        set_hostname_source(Arc::new(FakeHostname("web1".to_string())));
+
+        let mut opts = Options::new();
+        opts.optopt("g", "group", "become target group", "GROUP");
+        opts.optflag("h", "help", "print usage help");
+        opts.optflag("n", "noprompt", "do nothing if a password is required");
+        opts.optflag("p", "purge", "purge access token");
+        opts.optopt("r", "reason", "provide reason for execution", "REASON");
+        opts.optopt("t", "target", "become target user", "USER");
+        opts.optopt("u", "user", "become target user", "USER");
+        opts.optflag("v", "version", "print version and exit");
+        opts.optflag("w", "warm", "warm access token and exit");
+
+        let mut ro = basic_ro("ed", "root");
+        let matches = opts.parse(["/bin/bash"]).unwrap();
+        common_opt_arguments(&matches, &opts, &mut ro, "please", "please").unwrap();
+        assert_eq!(ro.hostname, "web1");
+
+        let mut fs = FakeFilesystem::new();
+        fs.add_file(
+            "/etc/please.ini",
+            "[web]\nexact_hostname = web1\nexact_name = ed\nexact_rule = /bin/bash\n",
+        );
+        fs.add_file(
+            "/etc/db.ini",
+            "[db]\nexact_hostname = db1\nexact_name = ed\nexact_rule = /bin/bash\n",
+        );
+        fs.add_file("/bin/bash", "");
+        set_filesystem(Arc::new(fs));
+
+        let vec_eo = load_file("/etc/please.ini", &ro).unwrap();
+        assert!(can(&vec_eo, &mut ro).permit());
+
+        let vec_eo = load_file("/etc/db.ini", &ro).unwrap();
+        assert!(!can(&vec_eo, &mut ro).permit());
+
+        set_hostname_source(Arc::new(NoHostname));
+        assert_eq!(
+            common_opt_arguments(&matches, &opts, &mut ro, "please", "please"),
+            Err(PleaseError::Hostname("EINVAL".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_clock() {
+        let _lock = providers();
+        let now = NaiveDate::from_ymd_opt(2020, 12, 25)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        set_clock(Arc::new(FakeClock(now)));
+
+        let mut ro = RunOptions::new();
+        assert_eq!(ro.date, now);
+        ro.name = "ed".to_string();
+        ro.target = "root".to_string();
+
+        let mut fs = FakeFilesystem::new();
+        fs.add_file(
+            "/etc/please.ini",
+            "[xmas]\nname = ed\nrule = /bin/bash\nnotbefore = 20201225\nnotafter = 20201226\n",
+        );
+        fs.add_file("/bin/bash", "");
+        set_filesystem(Arc::new(fs));
+
+        let vec_eo = load_file("/etc/please.ini", &ro).unwrap();
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(can(&vec_eo, &mut ro).permit());
+
+        set_clock(Arc::new(FakeClock(now - chrono::Duration::days(1))));
+        let mut ro = RunOptions::new();
+        ro.name = "ed".to_string();
+        ro.target = "root".to_string();
+        basic_cmd(&mut ro, "/bin/bash");
+        assert!(!can(&vec_eo, &mut ro).permit());
+    }
+}
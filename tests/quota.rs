@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    #[test]
+    fn test_parse_max_uses() {
+        assert_eq!(
+            parse_max_uses("3/day"),
+            Some(MaxUses {
+                count: 3,
+                period: 86400
+            })
+        );
+        assert_eq!(
+            parse_max_uses("1 / 12h"),
+            Some(MaxUses {
+                count: 1,
+                period: 43200
+            })
+        );
+        assert_eq!(
+            parse_max_uses("10/90"),
+            Some(MaxUses {
+                count: 10,
+                period: 90
+            })
+        );
+        assert_eq!(
+            parse_max_uses("2/2w"),
+            Some(MaxUses {
+                count: 2,
+                period: 1209600
+            })
+        );
+        assert_eq!(parse_max_uses("3"), None);
+        assert_eq!(parse_max_uses("3/"), None);
+        assert_eq!(parse_max_uses("3/0"), None);
+        assert_eq!(parse_max_uses("x/day"), None);
+        assert_eq!(parse_max_uses("3/fortnight"), None);
+    }
+
+    #[test]
+    fn test_max_uses_display() {
+        assert_eq!(parse_max_uses("3/day").unwrap().to_string(), "3/1d");
+        assert_eq!(parse_max_uses("3/48h").unwrap().to_string(), "3/2d");
+        assert_eq!(parse_max_uses("3/90s").unwrap().to_string(), "3/90s");
+    }
+
+    #[test]
+    fn test_max_uses_config() {
+        let config = "[emergency]
+exact_name=ed
+exact_rule = /bin/bash
+max_uses = 2/day
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+
+        assert!(
+            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(uses_remaining(&entry, &[], 100000), Some(2));
+        assert_eq!(uses_remaining(&entry, &[99000], 100000), Some(1));
+        assert_eq!(uses_remaining(&entry, &[99000, 99500], 100000), Some(0));
+        assert_eq!(uses_remaining(&entry, &[1000, 99500], 100000), Some(1));
+        assert_eq!(uses_remaining(&EnvOptions::new(), &[99000], 100000), None);
+    }
+
+    #[test]
+    fn test_consume_use_concurrent() {
+        // the counters live under /var/run/please
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let mut entry = EnvOptions::new();
+        entry.section = format!("concurrent_{}", std::process::id());
+        entry.file_name = "quota_test".to_string();
+        entry.max_uses = parse_max_uses("3/day");
+        let _ = std::fs::remove_file(uses_path("ed", &entry));
+
+        // only as many uses as the quota allows are charged
+        let uses: Vec<std::thread::JoinHandle<bool>> = (0..16)
+            .map(|_| {
+                let entry = entry.clone();
+                std::thread::spawn(move || consume_use("ed", &entry))
+            })
+            .collect();
+        let charged = uses
+            .into_iter()
+            .map(|used| used.join().unwrap())
+            .filter(|&used| used)
+            .count();
+
+        assert_eq!(charged, 3);
+        assert_eq!(read_uses("ed", &entry).len(), 3);
+        assert!(!uses_left("ed", &entry));
+
+        std::fs::remove_file(uses_path("ed", &entry)).unwrap();
+    }
+
+    #[test]
+    fn test_max_uses_list() {
+        let config = "[emergency]
+exact_name=ed
+exact_rule = /bin/bash
+max_uses = 2/day
+"
+        .to_string();
+
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "ed");
+        basic_cmd(&mut ro, "");
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
+
+        let list = produce_list(&vec_eo, &ro);
+        assert_eq!(
+            list,
+            [
+                "  in file: static",
+                "    emergency: uses(2 left of 2/1d) as root (pass=true,dirs=): exact(/bin/bash)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_uses_aborted() {
+        // the counters live under /var/run/please
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let mut entry = EnvOptions::new();
+        entry.section = format!("aborted_{}", std::process::id());
+        entry.file_name = "quota_test".to_string();
+        entry.max_uses = parse_max_uses("1/day");
+        let _ = std::fs::remove_file(uses_path("ed", &entry));
+
+        // checking before an edit or command is aborted charges nothing
+        assert!(uses_left("ed", &entry));
+        assert!(uses_left("ed", &entry));
+        assert!(read_uses("ed", &entry).is_empty());
+
+        assert!(consume_use("ed", &entry));
+        assert_eq!(read_uses("ed", &entry).len(), 1);
+        assert!(!uses_left("ed", &entry));
+        assert!(!consume_use("ed", &entry));
+        assert_eq!(read_uses("ed", &entry).len(), 1);
+
+        std::fs::remove_file(uses_path("ed", &entry)).unwrap();
+    }
+}
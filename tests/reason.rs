@@ -23,7 +23,7 @@ reason = true
         let mut ro = basic_ro("ed", "root");
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let can_do = can(&vec_eo, &mut ro);
         assert_eq!((can_do).permit(), true);
         assert_eq!(reason_ok(&can_do, &ro), false);
@@ -46,7 +46,7 @@ reason = true
         ro.reason = Some("simple reason".to_string());
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let can_do = can(&vec_eo, &mut ro);
         assert_eq!((can_do).permit(), true);
         assert_eq!(reason_ok(&can_do, &ro), true);
@@ -69,7 +69,7 @@ reason = bigdb
         ro.reason = Some("simple reason".to_string());
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let can_do = can(&vec_eo, &mut ro);
         assert_eq!((can_do).permit(), true);
         assert_eq!(reason_ok(&can_do, &ro), false);
@@ -92,7 +92,7 @@ reason = bigdb
         ro.reason = Some("bigdb".to_string());
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let can_do = can(&vec_eo, &mut ro);
         assert_eq!((can_do).permit(), true);
         assert_eq!(reason_ok(&can_do, &ro), true);
@@ -115,7 +115,7 @@ reason = .*%{HOSTNAME}.*
         ro.reason = Some("bash on localhost".to_string());
         basic_cmd(&mut ro, &"/bin/bash".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let can_do = can(&vec_eo, &mut ro);
         assert_eq!((can_do).permit(), true);
         assert_eq!(reason_ok(&can_do, &ro), true);
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod test {
+    use pleaser::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn scratch_dir(name: &str) -> String {
+        let dir = format!(
+            "{}/please_test_{}_{}",
+            std::env::temp_dir().to_str().unwrap(),
+            name,
+            std::process::id()
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_escape_path() {
+        assert_eq!(escape_path("/etc/hosts"), "%2Fetc%2Fhosts");
+        assert_eq!(escape_path("/a@b%c"), "%2Fa%40b%25c");
+        assert_eq!(unescape_path(&escape_path("/a@b%2F/c")), "/a@b%2F/c");
+    }
+
+    #[test]
+    fn test_edit_session_base() {
+        let uid = nix::unistd::getuid().as_raw();
+        let dir = scratch_dir("base");
+
+        assert_eq!(edit_session_base(None, uid), "/tmp");
+        assert_eq!(edit_session_base(Some(""), uid), "/tmp");
+        assert_eq!(edit_session_base(Some("relative/dir"), uid), "/tmp");
+        assert_eq!(edit_session_base(Some(&dir), uid), dir);
+        assert_eq!(edit_session_base(Some(&format!("{}/", dir)), uid), dir);
+        assert_eq!(edit_session_base(Some(&dir), uid + 1), "/tmp");
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(edit_session_base(Some(&dir), uid), "/tmp");
+
+        let link = format!("{}.link", dir);
+        let _ = std::fs::remove_file(&link);
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert_eq!(edit_session_base(Some(&link), uid), "/tmp");
+
+        std::fs::remove_file(&link).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_left_edits() {
+        let uid = nix::unistd::getuid().as_raw();
+        let base = scratch_dir("left");
+
+        let session = format!("{}/pleaseedit.abc123", base);
+        std::fs::create_dir(&session).unwrap();
+        std::fs::set_permissions(&session, std::fs::Permissions::from_mode(0o700)).unwrap();
+        std::fs::write(format!("{}/%2Fetc%2Fhosts", session), "127.0.0.1").unwrap();
+
+        // not private, so not trusted
+        let open = format!("{}/pleaseedit.open12", base);
+        std::fs::create_dir(&open).unwrap();
+        std::fs::set_permissions(&open, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(format!("{}/%2Fetc%2Fmotd", open), "hello").unwrap();
+
+        // another service
+        let other = format!("{}/other.abc123", base);
+        std::fs::create_dir(&other).unwrap();
+        std::fs::set_permissions(&other, std::fs::Permissions::from_mode(0o700)).unwrap();
+        std::fs::write(format!("{}/%2Fetc%2Fissue", other), "hello").unwrap();
+
+        let left = find_left_edits(std::slice::from_ref(&base), "pleaseedit", uid);
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].source, "/etc/hosts");
+        assert_eq!(left[0].path, format!("{}/%2Fetc%2Fhosts", session));
+
+        assert!(find_left_edits(std::slice::from_ref(&base), "pleaseedit", uid + 1).is_empty());
+        assert!(find_left_edits(&["/nonexistent/dir".to_string()], "pleaseedit", uid).is_empty());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use getopts::Options;
+    use pleaser::*;
+
+    fn common_opts() -> Options {
+        let mut opts = Options::new();
+        opts.optopt("g", "group", "become target group", "GROUP");
+        opts.optflag("h", "help", "print usage help");
+        opts.optflag("n", "noprompt", "do nothing if a password is required");
+        opts.optflag("p", "purge", "purge access token");
+        opts.optopt("r", "reason", "provide reason for execution", "REASON");
+        opts.optopt("t", "target", "become target user", "USER");
+        opts.optopt("u", "user", "become target user", "USER");
+        opts.optflag("v", "version", "print version and exit");
+        opts.optflag("w", "warm", "warm access token and exit");
+        opts
+    }
+
+    #[test]
+    fn test_reporter() {
+        let _restore = RestoreProviders;
+        let reporter = Arc::new(BufferReporter::new());
+        set_reporter(reporter.clone());
+
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "");
+
+        assert_eq!(
+            read_ini_config_str(
+                "[bad]\nname = ed\nrule = (\n",
+                &mut vec_eo,
+                &ro,
+                true,
+                &mut bytes,
+                &mut ini_list
+            ),
+            Err(PleaseError::Config("static".to_string()))
+        );
+        assert_eq!(reporter.take(), ["Error parsing static: bad:3"]);
+
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        assert_eq!(
+            read_ini_config_str(
+                "[inc]\ninclude = relative.ini\n",
+                &mut vec_eo,
+                &ro,
+                true,
+                &mut bytes,
+                &mut ini_list
+            ),
+            Err(PleaseError::Config("static".to_string()))
+        );
+        assert_eq!(reporter.take(), ["Includes should start with /"]);
+
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        assert_eq!(
+            read_ini_config_file(
+                "/nonexistent/please.ini",
+                &mut vec_eo,
+                &ro,
+                true,
+                &mut bytes,
+                &mut ini_list
+            ),
+            Err(PleaseError::Config("/nonexistent/please.ini".to_string()))
+        );
+        assert_eq!(reporter.take().len(), 1);
+
+        let mut bytes = 1024 * 1024 * 10;
+        assert_eq!(
+            read_ini_config_file(
+                "/etc/passwd",
+                &mut vec_eo,
+                &ro,
+                true,
+                &mut bytes,
+                &mut ini_list
+            ),
+            Err(PleaseError::ConfigTooLarge)
+        );
+
+        let opts = common_opts();
+        let matches = opts.parse(["-v"]).unwrap();
+        let error = common_opt_arguments(&matches, &opts, &mut ro, "please", "please").unwrap_err();
+        assert_eq!(error, PleaseError::Exit(0));
+        assert_eq!(error.exit_code(), 0);
+        assert_eq!(error.to_string(), "");
+        assert_eq!(
+            reporter.take(),
+            [format!("please version {}", env!("CARGO_PKG_VERSION"))]
+        );
+    }
+
+    #[test]
+    fn test_common_opt_arguments_errors() {
+        let opts = common_opts();
+        let mut ro = basic_ro("ed", "root");
+        let matches = opts.parse(["-t", "bob", "-u", "ben", "id"]).unwrap();
+        assert_eq!(
+            common_opt_arguments(&matches, &opts, &mut ro, "please", "please"),
+            Err(PleaseError::Usage(
+                "Cannot use -t and -u with conflicting values".to_string()
+            ))
+        );
+
+        let matches = opts.parse(["-t", "bob", "id"]).unwrap();
+        assert_eq!(
+            common_opt_arguments(&matches, &opts, &mut ro, "please", "please"),
+            Ok(())
+        );
+        assert_eq!(ro.target, "bob");
+        assert_eq!(ro.new_args, ["id"]);
+    }
+
+    #[test]
+    fn test_runopt_target_gid() {
+        let root = uzers::get_user_by_uid(0).unwrap();
+        let mut ro = basic_ro("ed", "root");
+
+        assert_eq!(runopt_target_gid(&ro, &root).unwrap().as_raw(), 0);
+
+        ro.target_group = Some("no_such_group_here".to_string());
+        let error = runopt_target_gid(&ro, &root).unwrap_err();
+        assert_eq!(error, PleaseError::Group("no_such_group_here".to_string()));
+        assert_eq!(error.to_string(), "Cannot assign group no_such_group_here");
+        assert_eq!(error.exit_code(), 1);
+    }
+}
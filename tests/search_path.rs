@@ -22,7 +22,7 @@ search_path = :
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -43,7 +43,7 @@ search_path =
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -64,7 +64,7 @@ search_path = /nonexistent
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -85,7 +85,7 @@ search_path = /bin
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"bash".to_string());
         let c = can(&vec_eo, &mut ro);
@@ -114,7 +114,7 @@ rule = .*/bash
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"bash".to_string());
         let c = can(&vec_eo, &mut ro);
@@ -143,7 +143,7 @@ rule = .*/e2fsck
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ro = basic_ro("ed", "root");
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"e2fsck".to_string());
         let c = can(&vec_eo, &mut ro);
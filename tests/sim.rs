@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use chrono::{NaiveDate, TimeZone};
+    use pleaser::*;
+
+    fn load(config: &str) -> Vec<EnvOptions> {
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "");
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        vec_eo
+    }
+
+    #[test]
+    fn test_parse_log_fields() {
+        let fields =
+            parse_log_fields(r#"user="ed" cwd="/tmp" reason="say \"hi\"" command="/usr/bin/id""#);
+        assert_eq!(fields["user"], "ed");
+        assert_eq!(fields["cwd"], "/tmp");
+        assert_eq!(fields["reason"], "say \"hi\"");
+        assert_eq!(fields["command"], "/usr/bin/id");
+    }
+
+    #[test]
+    fn test_parse_logged_request_syslog() {
+        let request = parse_logged_request(
+            r#"Mar  4 10:20:30 web1 please[1234]: user="ed" cwd="/home/ed" tty="pts/0" action="reason_fail" target="root" type="run" reason="" command="please -r CHG1 -d /tmp -a PATH,HOME -- /usr/bin/uptime -p" matching_section="static:uptime""#,
+            2020,
+        )
+        .unwrap();
+
+        assert_eq!(request.action, "reason_fail");
+        assert_eq!(request.user, "ed");
+        assert_eq!(request.target, "root");
+        assert_eq!(request.acl_type, Acltype::Run);
+        assert_eq!(request.reason, Some("CHG1".to_string()));
+        assert_eq!(request.args, ["/usr/bin/uptime", "-p"]);
+        assert_eq!(request.directory, Some("/tmp".to_string()));
+        assert_eq!(
+            request.allow_env,
+            Some(vec!["PATH".to_string(), "HOME".to_string()])
+        );
+        assert_eq!(request.hostname, Some("web1".to_string()));
+        // a stamp without an offset is the local time of the host
+        let local = NaiveDate::from_ymd_opt(2020, 3, 4)
+            .unwrap()
+            .and_hms_opt(10, 20, 30)
+            .unwrap();
+        assert_eq!(
+            request.date,
+            chrono::Local
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|d| d.naive_utc())
+        );
+
+        let request = parse_logged_request(
+            r#"2020-05-06T07:08:09+02:00 db2 pleaseedit[99]: user="ben" cwd="/" tty="no_tty" action="deny" target="root" type="edit" reason="" command="pleaseedit -g wheel /etc/hosts /etc/motd" matching_section="""#,
+            2020,
+        )
+        .unwrap();
+        assert_eq!(request.acl_type, Acltype::Edit);
+        assert_eq!(request.target_group, Some("wheel".to_string()));
+        assert_eq!(request.args, ["/etc/hosts", "/etc/motd"]);
+        assert_eq!(request.hostname, Some("db2".to_string()));
+        assert_eq!(
+            request.date,
+            Some(
+                NaiveDate::from_ymd_opt(2020, 5, 6)
+                    .unwrap()
+                    .and_hms_opt(5, 8, 9)
+                    .unwrap()
+            )
+        );
+
+        assert_eq!(
+            parse_logged_request("Mar  4 10:20:30 web1 cron[1]: hi", 2020),
+            None
+        );
+    }
+
+    #[test]
+    fn test_logged_command_quoting() {
+        let command: Vec<String> = [
+            "please",
+            "-r",
+            "fix \"web\" it's down",
+            "/bin/sh",
+            "-c",
+            "echo  a\\b",
+            "",
+        ]
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+        let logged = shell_join(&command);
+        assert_eq!(
+            logged,
+            r#"please -r 'fix "web" it'\''s down' /bin/sh -c 'echo  a\b' ''"#
+        );
+
+        let request = parse_logged_request(
+            &format!(
+                r#"Mar  4 10:20:30 web1 please[1234]: user="ed" cwd="/" tty="no_tty" action="permit" target="root" type="run" reason="" command="{}" matching_section="""#,
+                escape_log(&logged)
+            ),
+            2020,
+        )
+        .unwrap();
+        assert_eq!(request.reason, Some(command[2].clone()));
+        assert_eq!(request.args, command[3..]);
+
+        // the logged format joins the command with spaces
+        let request = parse_logged_request(
+            r#"Mar  4 10:20:30 web1 please[1234]: user="ed" cwd="/" tty="no_tty" action="permit" target="root" type="run" reason="" command="please /bin/echo it's 'quoted'" matching_section="""#,
+            2020,
+        )
+        .unwrap();
+        assert_eq!(request.args, ["/bin/echo", "it's", "'quoted'"]);
+    }
+
+    #[test]
+    fn test_logged_request_ro_tty_from() {
+        let request = parse_logged_request(
+            r#"Mar  4 10:20:30 web1 please[1234]: user="ed" cwd="/" tty="no_tty" action="permit" target="root" type="run" reason="" command="please /bin/id" matching_section="""#,
+            2020,
+        )
+        .unwrap();
+        let ro = &logged_request_ro(&request, &HashMap::new())[0];
+        assert_eq!(ro.tty, None);
+        assert_eq!(ro.from, "unknown");
+
+        let request = parse_logged_request(
+            r#"{"user":"ed","action":"permit","target":"root","type":"run","command":"/usr/bin/id","tty":"/dev/pts/3","from":"10.1.2.3"}"#,
+            2020,
+        )
+        .unwrap();
+        let ro = &logged_request_ro(&request, &HashMap::new())[0];
+        assert_eq!(ro.tty, Some("/dev/pts/3".to_string()));
+        assert_eq!(ro.from, "10.1.2.3");
+
+        let policy = load(
+            "
+[id]
+name = ed
+target = root
+rule = /usr/bin/id
+from = 10.0.0.0/8
+tty = required
+",
+        );
+        assert!(unreplayable_keys(&[&policy], &request).is_empty());
+
+        let request = parse_logged_request(
+            r#"{"user":"ed","action":"permit","target":"root","type":"run","command":"/usr/bin/id"}"#,
+            2020,
+        )
+        .unwrap();
+        assert_eq!(unreplayable_keys(&[&policy], &request), ["tty", "from"]);
+        assert!(unreplayable_keys(&[&load("[id]\nname = ed\nrule = .*\n")], &request).is_empty());
+    }
+
+    #[test]
+    fn test_parse_logged_request_json() {
+        let request = parse_logged_request(
+            r#"{"user":"ed","action":"permit","target":"root","type":"run","command":"/usr/bin/id -u","hostname":"web1","date":"2020-01-02 03:04:05","groups":["admins"]}"#,
+            2020,
+        )
+        .unwrap();
+        assert_eq!(request.action, "permit");
+        assert_eq!(request.args, ["/usr/bin/id", "-u"]);
+        assert_eq!(request.groups, Some(vec!["admins".to_string()]));
+        assert_eq!(request.hostname, Some("web1".to_string()));
+
+        assert_eq!(parse_logged_request("{\"user\":\"ed\"}", 2020), None);
+        assert_eq!(parse_logged_request("{not json", 2020), None);
+    }
+
+    #[test]
+    fn test_logged_request_ro_edit() {
+        let request = parse_logged_request(
+            r#"{"user":"ed","action":"permit","target":"root","type":"edit","command":"pleaseedit /etc/hosts /etc/motd"}"#,
+            2020,
+        )
+        .unwrap();
+
+        let ros = logged_request_ro(&request, &HashMap::new());
+        assert_eq!(ros.len(), 2);
+        assert_eq!(ros[0].command, "/etc/hosts");
+        assert_eq!(ros[1].new_args, ["/etc/motd"]);
+        assert_eq!(ros[1].acl_type, Acltype::Edit);
+    }
+
+    #[test]
+    fn test_simulate() {
+        let current = load(
+            "
+[uptime]
+name = ed
+target = root
+rule = ^/usr/bin/uptime$
+require_pass = false
+
+[id]
+name = ed
+target = root
+rule = ^/usr/bin/id$
+reason = ^CHG[0-9]+$
+",
+        );
+        let candidate = load(
+            "
+[id]
+name = ed
+target = root
+rule = ^/usr/bin/id$
+
+[ben_uptime]
+name = ben
+target = root
+rule = ^/usr/bin/uptime$
+",
+        );
+
+        let lines = [
+            r#"{"user":"ed","action":"permit","target":"root","type":"run","command":"/usr/bin/uptime","hostname":"localhost"}"#,
+            r#"{"user":"ed","action":"reason_fail","target":"root","type":"run","command":"please /usr/bin/id","hostname":"localhost"}"#,
+            r#"{"user":"ed","action":"permit","target":"root","type":"run","command":"please -r CHG1 /usr/bin/id","hostname":"localhost"}"#,
+            r#"{"user":"ben","action":"deny","target":"root","type":"run","command":"/usr/bin/uptime","hostname":"localhost"}"#,
+        ];
+
+        let mut requests = vec![];
+        for line in lines {
+            let request = parse_logged_request(line, 2020).unwrap();
+            requests.append(&mut logged_request_ro(&request, &HashMap::new()));
+        }
+
+        let flips = simulate(&current, &candidate, &requests);
+        assert_eq!(
+            produce_sim_report(&flips),
+            [
+                "ben:",
+                "  deny (no match) -> permit (ben_uptime): 1 request",
+                "    run \"/usr/bin/uptime\" on localhost as root",
+                "ed:",
+                "  deny (id) -> permit (id): 1 request",
+                "    run \"/usr/bin/id\" on localhost as root",
+                "  permit (uptime) -> deny (no match): 1 request",
+                "    run \"/usr/bin/uptime\" on localhost as root",
+            ]
+        );
+    }
+}
@@ -23,7 +23,7 @@ target_group = potato
         let mut ro = basic_ro("ed", "root");
         ro.target_group = Some("potato".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -51,7 +51,7 @@ exact_target_group = potato
         let mut ro = basic_ro("ed", "root");
         ro.target_group = Some("potato".to_string());
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, &"/bin/bash".to_string());
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -81,7 +81,7 @@ target_group = oracle
         basic_cmd(&mut ro, &"/etc/please.ini".to_string());
         ro.target_group = Some("oracle".to_string());
         ro.acl_type = Acltype::Edit;
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -106,7 +106,7 @@ target_group = oracle
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/etc/missing".to_string());
         ro.target_group = Some("oracle".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
@@ -134,7 +134,7 @@ target_group = oracle
         basic_cmd(&mut ro, &"/etc/please.ini".to_string());
         ro.target_group = Some("oracle".to_string());
         ro.acl_type = Acltype::List;
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -158,7 +158,7 @@ type = edit
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, &"/etc/please.ini".to_string());
         ro.target_group = Some("oracle".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
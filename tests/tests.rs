@@ -36,7 +36,7 @@ regex=^/bin/bash .*$
         let mut ro = basic_ro("ed", "root");
 
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
 
@@ -61,7 +61,7 @@ regex = /bin/bash
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
 
@@ -97,21 +97,21 @@ regex=^/bin/bash"
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
         ro.name = "other".to_string();
         ro.target = "thingy".to_string();
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         ro.name = "other".to_string();
         ro.target = "oracle".to_string();
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
 
@@ -144,7 +144,7 @@ target=^ "
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         ro.date = NaiveDate::from_ymd(2019, 12, 31).and_hms(0, 0, 0);
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -184,7 +184,7 @@ require_pass = false
         ro.acl_type = Acltype::List;
 
         ro.target = "ed".to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
         ro.target = "root".to_string();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -206,7 +206,7 @@ regex=^.*
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         basic_cmd(&mut ro, "/bin/bash");
 
         ro.date = NaiveDate::from_ymd(2020, 8, 8).and_hms(0, 0, 0);
@@ -250,7 +250,7 @@ regex=^/bin/bash .*$
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
         ro.date = NaiveDate::from_ymd(2019, 12, 31).and_hms(0, 0, 0);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, "/bin/bash /usr/local/oracle/backup_script");
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -289,7 +289,7 @@ regex=^/bin/bash.*$
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, "/bin/bash /usr/local/oracle/backup_script");
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
@@ -329,7 +329,7 @@ regex=^/bin/sh.*$
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         basic_cmd(&mut ro, "/bin/bash /usr/local/oracle/backup_script");
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
@@ -367,7 +367,7 @@ regex=/bin/sh\\b.*
         let mut ro = basic_ro("ed", "root");
         ro.name = "".to_string();
         ro.target = "oracle".to_string();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
@@ -389,7 +389,7 @@ regex=.*
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -431,7 +431,7 @@ regex = ^"
         let mut ro = basic_ro("ed", "root");
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/etc/apache/httpd2.conf");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -450,7 +450,7 @@ regex =^/bin/cat /etc/%{USER}"
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/cat /etc/ed");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -475,7 +475,8 @@ regex = ^/bin/cat /etc/("
         basic_cmd(&mut ro, "/bin/cat /etc/ed");
 
         assert_eq!(
-            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list),
+            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list)
+                .is_err(),
             true
         );
 
@@ -494,7 +495,8 @@ regex = ^/bin/cat /etc/
         basic_cmd(&mut ro, "/bin/cat /etc/ed");
 
         assert_eq!(
-            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list),
+            read_ini_config_str(&config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list)
+                .is_err(),
             false
         );
     }
@@ -517,7 +519,7 @@ regex = ^.*$
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/cat /etc/ed");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
@@ -575,7 +577,7 @@ target = ^(eng|dba|net)ops$
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/cat /etc/ed");
         ro.acl_type = Acltype::List;
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -627,7 +629,7 @@ regex = ^/var/www/html/%{USER}.html
         basic_cmd(&mut ro, "/etc/please.ini");
         ro.acl_type = Acltype::Edit;
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
         ro.groups.insert(String::from("root"), 1);
@@ -665,7 +667,7 @@ regex = ^/var/www/html/%{USER}.html$
         basic_cmd(&mut ro, "/var/www/html/ed.html");
         ro.acl_type = Acltype::Edit;
         ro.groups.insert(String::from("root"), 1);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -690,7 +692,7 @@ regex = ^/var/www/html/%USER.html$"
         basic_cmd(&mut ro, "/var/www/html/ed.html");
 
         ro.groups.insert(String::from("root"), 1);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
 
@@ -712,7 +714,7 @@ regex = ^/var/www/html/%{USER}.html$"
         let mut ro = basic_ro("ed", "root");
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/var/www/html/ed.html");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         ro.groups.insert(String::from("root"), 1);
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -734,7 +736,7 @@ regex = /bin/bash"
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(
             vec_eo
                 .iter()
@@ -769,7 +771,7 @@ regex = /bin/bash"
         let mut ro = basic_ro("ed", "root");
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/etc/please.ini");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
 
@@ -791,7 +793,7 @@ dir=.*
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
 
@@ -817,7 +819,7 @@ dir=/var/www
         let mut ro = basic_ro("ed", "root");
         ro.target = "oracle".to_string();
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false, "no directory given",);
 
@@ -849,7 +851,7 @@ dir=/tmp
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
         ro.directory = Some("/tmp".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true, "dir_tmp",);
     }
@@ -872,7 +874,7 @@ regex=.*
         ro.target = "oracle".to_string();
         basic_cmd(&mut ro, "/bin/bash");
         ro.directory = Some("/".to_string());
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false, "directory given",);
 
@@ -898,7 +900,7 @@ datematch=Fri.*UTC.*
         let mut ro = basic_ro("ed", "root");
         ro.date = NaiveDate::from_ymd(2020, 10, 02).and_hms(22, 0, 0);
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
@@ -918,7 +920,7 @@ datematch=Fri.*\\s22:00:00\\s+UTC\\s2020
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         ro.date = NaiveDate::from_ymd(2020, 10, 02).and_hms(21, 0, 0);
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
         ro.date = NaiveDate::from_ymd(2020, 10, 02).and_hms(23, 0, 0);
@@ -939,7 +941,7 @@ datematch=Thu\\s+1\\s+Oct\\s+22:00:00\\s+UTC\\s+2020
         let mut vec_eo: Vec<EnvOptions> = vec![];
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         ro.date = NaiveDate::from_ymd(2020, 10, 01).and_hms(21, 0, 0);
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
         ro.date = NaiveDate::from_ymd(2020, 10, 01).and_hms(23, 0, 0);
@@ -966,7 +968,7 @@ editmode=0644
         let mut ro = basic_ro("ed", "root");
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/etc/please.ini");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let entry = can(&vec_eo, &mut ro);
 
@@ -984,7 +986,7 @@ editmode=keep
 
         bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let entry = can(&vec_eo, &mut ro);
 
@@ -1001,12 +1003,12 @@ editmode=keep
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         assert_eq!(
-            read_ini_config_file(".", &mut vec_eo, &ro, true, &mut bytes, &mut ini_list),
+            read_ini_config_file(".", &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_err(),
             true
         );
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         assert_eq!(
-            read_ini_config_file("", &mut vec_eo, &ro, true, &mut bytes, &mut ini_list),
+            read_ini_config_file("", &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_err(),
             true
         );
         let mut ini_list: HashMap<String, bool> = HashMap::new();
@@ -1018,7 +1020,8 @@ editmode=keep
                 true,
                 &mut bytes,
                 &mut ini_list
-            ),
+            )
+            .is_err(),
             true
         );
     }
@@ -1046,7 +1049,7 @@ permit=true
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let entry = can(&vec_eo, &mut ro);
 
@@ -1070,7 +1073,7 @@ reason=true
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let entry = can(&vec_eo, &mut ro);
 
@@ -1105,7 +1108,7 @@ reason=true
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/bash");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let entry = can(&vec_eo, &mut ro);
 
@@ -1132,7 +1135,7 @@ regex=^/usr/bin/wc (/var/log/[a-zA-Z0-9-]+(\\.\\d+)?(\\s)?)+$
             "/usr/bin/wc /var/log/messages /var/log/syslog /var/log/maillog",
         );
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
 
         basic_cmd(&mut ro, "/usr/bin/wc /var/log/messages /var/log/messages.1");
@@ -1179,7 +1182,7 @@ exitcmd = /usr/bin/please -c %{NEW}
         ro.groups.insert(String::from("lpadmin"), 1);
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/etc/please.ini");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
 
@@ -1199,7 +1202,8 @@ include = ./some.ini
         let mut bytes = 0;
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         assert_eq!(
-            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list),
+            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list)
+                .is_err(),
             true
         );
 
@@ -1210,7 +1214,8 @@ includedir = ./dir.d/some.ini
         .to_string();
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         assert_eq!(
-            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list),
+            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list)
+                .is_err(),
             true
         );
 
@@ -1221,7 +1226,8 @@ includedir = /dev/null
         .to_string();
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         assert_eq!(
-            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list),
+            read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list)
+                .is_err(),
             false
         );
     }
@@ -1327,7 +1333,7 @@ type = list
         ro.target = "ed".to_string();
         ro.acl_type = Acltype::List;
         basic_cmd(&mut ro, "");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         let list = produce_list(&vec_eo, &ro);
         assert_eq!(list, ["  in file: static", "    list:list: root"]);
@@ -1347,7 +1353,7 @@ reason = false
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "");
         ro.allow_env_list = Some(vec!["PATH".to_string()]);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
@@ -1369,7 +1375,7 @@ regex = .*
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
         let entry = can(&vec_eo, &mut ro);
 
         assert_eq!(
@@ -1396,7 +1402,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "");
         ro.allow_env_list = Some(vec!["PATH".to_string(), "HOME".to_string()]);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1422,7 +1428,7 @@ rule = .*
             "HOME".to_string(),
             "DISASTER".to_string(),
         ]);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), false);
     }
@@ -1443,7 +1449,7 @@ rule = .*
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "");
         ro.allow_env_list = None;
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1463,7 +1469,7 @@ regex = /bin/echo [%]\{USER\}
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/echo %{USER}");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1483,7 +1489,7 @@ regex = /bin/echo \x25\{USER\}
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, "/bin/echo %{USER}");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1503,7 +1509,7 @@ regex = /bin/echo hello\x5c\x5cworld
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         ro.new_args = vec!["/bin/echo".to_string(), r#"hello\world"#.to_string()];
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1523,7 +1529,7 @@ regex = /bin/echo hello[\\][\\]world
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         basic_cmd(&mut ro, r#"/bin/echo hello\world"#);
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1544,7 +1550,7 @@ exact_rule = /bin/echo hello\ world
         let mut ro = basic_ro("ed", "root");
         ro.new_args = vec!["/bin/echo".to_string(), "hello world".to_string()];
 
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1564,7 +1570,7 @@ rule = /bin/echo hello\\ world
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         ro.new_args = vec!["/bin/echo".to_string(), "hello world".to_string()];
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1584,7 +1590,7 @@ rule = /bin/echo hello\\ \\\\\\\\\\ world
         let mut ini_list: HashMap<String, bool> = HashMap::new();
         let mut ro = basic_ro("ed", "root");
         ro.new_args = vec!["/bin/echo".to_string(), r#"hello \\ world"#.to_string()];
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).permit(), true);
     }
@@ -1605,7 +1611,7 @@ rule = /etc/fstab
         let mut ro = basic_ro("ed", "root");
         ro.acl_type = Acltype::Edit;
         basic_cmd(&mut ro, "/etc/fstab");
-        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list);
+        read_ini_config_str(&config, &mut vec_eo, &ro, false, &mut bytes, &mut ini_list).unwrap();
 
         assert_eq!(can(&vec_eo, &mut ro).edit_mode, None);
     }
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    fn read_config(config: &str, ro: &RunOptions) -> Vec<EnvOptions> {
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, ro, true, &mut bytes, &mut ini_list).is_ok()
+        );
+        vec_eo
+    }
+
+    #[test]
+    fn test_tty_required() {
+        let config = "[interactive]
+exact_name=ed
+exact_rule = /bin/bash
+tty = required
+";
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = read_config(config, &ro);
+
+        ro.tty = Some("/dev/pts/1".to_string());
+        assert!(can(&vec_eo, &mut ro).permit());
+        assert_eq!(tty_explain(&vec_eo, &ro), None);
+
+        ro.tty = None;
+        assert!(!can(&vec_eo, &mut ro).permit());
+        assert_eq!(
+            tty_explain(&vec_eo, &ro),
+            Some("This must be run from a terminal (tty=required)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tty_forbidden() {
+        let config = "[cron]
+exact_name=ed
+exact_rule = /bin/bash
+tty = forbidden
+";
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = read_config(config, &ro);
+
+        ro.tty = None;
+        assert!(can(&vec_eo, &mut ro).permit());
+
+        ro.tty = Some("/dev/pts/1".to_string());
+        assert!(!can(&vec_eo, &mut ro).permit());
+        assert_eq!(
+            tty_explain(&vec_eo, &ro),
+            Some("This may not be run from a terminal (tty=forbidden)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tty_any() {
+        let config = "[whatever]
+exact_name=ed
+exact_rule = /bin/bash
+tty = any
+";
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = read_config(config, &ro);
+
+        ro.tty = None;
+        assert!(can(&vec_eo, &mut ro).permit());
+        ro.tty = Some("/dev/pts/1".to_string());
+        assert!(can(&vec_eo, &mut ro).permit());
+    }
+
+    #[test]
+    fn test_tty_unrelated_deny() {
+        let config = "[interactive]
+exact_name=ed
+exact_rule = /bin/bash
+tty = required
+";
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/sh");
+        ro.tty = None;
+        let vec_eo = read_config(config, &ro);
+
+        assert!(!can(&vec_eo, &mut ro).permit());
+        assert_eq!(tty_explain(&vec_eo, &ro), None);
+    }
+
+    #[test]
+    fn test_tty_picks_section() {
+        let config = "[interactive]
+exact_name=ed
+exact_rule = /bin/bash
+tty = required
+
+[cron]
+exact_name=ed
+exact_rule = /bin/bash
+tty = forbidden
+require_pass = false
+";
+        let mut ro = basic_ro("ed", "root");
+        basic_cmd(&mut ro, "/bin/bash");
+        let vec_eo = read_config(config, &ro);
+
+        // a section that does not suit the tty is passed over, not denied
+        ro.tty = Some("/dev/pts/1".to_string());
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.section, "interactive");
+        assert!(entry.require_pass());
+
+        ro.tty = None;
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.section, "cron");
+        assert!(!entry.require_pass());
+    }
+
+    #[test]
+    fn test_tty_list() {
+        let config = "[interactive]
+exact_name=ed
+exact_rule = /bin/bash
+tty = required
+";
+        let mut ro = basic_ro("ed", "ed");
+        basic_cmd(&mut ro, "");
+        let vec_eo = read_config(config, &ro);
+
+        assert_eq!(
+            produce_list(&vec_eo, &ro),
+            [
+                "  in file: static",
+                "    interactive: tty_required as root (pass=true,dirs=): exact(/bin/bash)"
+            ]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+mod basic_ro;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use basic_ro::*;
+    use pleaser::*;
+
+    #[test]
+    fn test_parse_validate() {
+        assert_eq!(parse_validate("json"), Some(Validate::Json));
+        assert_eq!(parse_validate("YAML"), Some(Validate::Yaml));
+        assert_eq!(parse_validate("yml"), Some(Validate::Yaml));
+        assert_eq!(parse_validate("sh"), Some(Validate::Shell));
+        assert_eq!(parse_validate("xml"), None);
+        assert_eq!(Validate::Please.to_string(), "please");
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let config = "[json_edit]
+exact_name = ed
+type = edit
+rule = ^/etc/app/.*\\.json$
+validate = json
+
+[bad]
+exact_name = ed
+type = edit
+rule = ^/etc/other$
+validate = xml
+";
+        let mut bytes = 0;
+        let mut ini_list: HashMap<String, bool> = HashMap::new();
+        let mut vec_eo: Vec<EnvOptions> = vec![];
+        let mut ro = basic_ro("ed", "root");
+        ro.acl_type = Acltype::Edit;
+        basic_cmd(&mut ro, "/etc/app/config.json");
+
+        assert!(
+            read_ini_config_str(config, &mut vec_eo, &ro, true, &mut bytes, &mut ini_list).is_err()
+        );
+
+        let entry = can(&vec_eo, &mut ro);
+        assert!(entry.permit());
+        assert_eq!(entry.validate, Some(Validate::Json));
+    }
+
+    #[test]
+    fn test_validate_ini() {
+        assert!(validate_ini(b"# comment\n; other\n[section]\nkey = value\n\nempty=\n").is_ok());
+        assert_eq!(
+            validate_ini(b"[section]\nnot a pair\n"),
+            Err("line 2: expected key=value".to_string())
+        );
+        assert_eq!(
+            validate_ini(b"[section\n"),
+            Err("line 1: bad section header [section".to_string())
+        );
+        assert!(validate_ini(b"= value\n").is_err());
+        assert!(validate_ini(b"[a]\nk=\xff\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_please_ini() {
+        let ro = basic_ro("ed", "root");
+        assert!(
+            validate_please_ini(b"[ok]\nname = ed\nrule = .*\n", &ro, "/etc/please.ini").is_ok()
+        );
+        assert_eq!(
+            validate_please_ini(b"[bad]\nname = ed\nrule = (\n", &ro, "/etc/please.ini"),
+            Err("/etc/please.ini is not a valid please.ini".to_string())
+        );
+        assert!(validate_please_ini(b"[bad]\nunknown_key = 1\n", &ro, "/etc/please.ini").is_err());
+    }
+
+    #[test]
+    fn test_validate_json() {
+        assert!(validate_json(b"{\"a\": [1, 2, {\"b\": null}]}").is_ok());
+        assert!(validate_json(b"{\"a\": [1, 2,]}").is_err());
+        assert!(validate_json(b"").is_err());
+    }
+
+    #[test]
+    fn test_validate_toml() {
+        assert!(validate_toml(b"[server]\nport = 80\nname = \"web\"\n").is_ok());
+        assert!(validate_toml(b"[server]\nport = \n").is_err());
+        assert!(validate_toml(b"a = 1\na = 2\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_yaml() {
+        assert!(validate_yaml(b"server:\n  port: 80\n  names: [a, b]\n").is_ok());
+        assert!(validate_yaml(b"server:\n  names: [a, b\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_shell() {
+        let ro = basic_ro("ed", "root");
+        assert!(validate_data(&Validate::Shell, b"if true; then echo ok; fi\n", &ro, "x").is_ok());
+        assert!(validate_data(&Validate::Shell, b"if true; then echo ok\n", &ro, "x").is_err());
+    }
+
+    #[test]
+    fn test_shell_split() {
+        assert_eq!(
+            shell_split("/usr/bin/check  --file %{NEW}").unwrap(),
+            ["/usr/bin/check", "--file", "%{NEW}"]
+        );
+        assert_eq!(
+            shell_split(r#"/bin/sh -c 'test -s "$1"' check %{NEW}"#).unwrap(),
+            ["/bin/sh", "-c", "test -s \"$1\"", "check", "%{NEW}"]
+        );
+        assert_eq!(
+            shell_split(r#"a "b \"c\" \d" e\ f ''"#).unwrap(),
+            ["a", "b \"c\" \\d", "e f", ""]
+        );
+        assert_eq!(shell_split("  ").unwrap(), Vec::<String>::new());
+        assert!(shell_split("'open").is_err());
+        assert!(shell_split("\"open").is_err());
+        assert!(shell_split("trailing\\").is_err());
+    }
+}